@@ -23,7 +23,7 @@ use ripple_sdk::{
     log::error,
 };
 
-use crate::state::bootstrap_state::BootstrapState;
+use crate::{service::watchdog::Watchdog, state::bootstrap_state::BootstrapState};
 
 use super::{
     extn::{
@@ -83,6 +83,7 @@ async fn execute_step<T: Bootstep<BootstrapState>>(
     state: &Bootstrap<BootstrapState>,
 ) -> RippleResponse {
     let name = step.get_name();
+    Watchdog::notify_status(&format!("Bootstrap step {}", name));
     if let Err(e) = state.step(step).await {
         error!("Failed at Bootstrap step {}", name);
         Err(e)
@@ -18,12 +18,15 @@
 use std::ffi::OsStr;
 
 use ripple_sdk::{
-    api::manifest::extn_manifest::ExtnManifestEntry,
+    api::{manifest::extn_manifest::ExtnManifestEntry, status_update::ExtnStatus},
     async_trait::async_trait,
-    extn::ffi::ffi_library::load_extn_library_metadata,
+    extn::ffi::ffi_library::{
+        get_sdk_version, load_extn_library_metadata, ExtnMetadata, ExtnVersionMismatch,
+    },
     framework::bootstrap::Bootstep,
     libloading::Library,
-    log::{debug, info, warn},
+    log::{debug, error, info, warn},
+    semver::Version,
     utils::error::RippleError,
 };
 
@@ -47,6 +50,24 @@ impl LoadExtensionMetadataStep {
             }
         }
     }
+
+    /// Returns the symbols of the library declared in the manifest entry which require a
+    /// `ripple_sdk` not compatible with the given sdk version.
+    fn get_version_mismatches(
+        entry: &ExtnManifestEntry,
+        metadata: &ExtnMetadata,
+        sdk_version: &Version,
+    ) -> Vec<ExtnVersionMismatch> {
+        metadata
+            .symbols
+            .iter()
+            .filter(|symbol| {
+                let id = symbol.id.to_string();
+                entry.symbols.iter().any(|s| s.id == id)
+            })
+            .filter_map(|symbol| symbol.check_sdk_version(sdk_version).err())
+            .collect()
+    }
 }
 
 #[async_trait]
@@ -60,14 +81,17 @@ impl Bootstep<BootstrapState> for LoadExtensionMetadataStep {
         let manifest = state.platform_state.get_manifest();
         let default_path = manifest.default_path;
         let default_extn = manifest.default_extension;
+        // extensions hosted in a process are not loaded by Ripple
         let extn_paths: Vec<(String, ExtnManifestEntry)> = manifest
             .extns
             .into_iter()
+            .filter(|f| !f.is_process())
             .map(|f| {
                 (f.get_path(&default_path, &default_extn), f)
                 // TODO Add Resolution checks later on
             })
             .collect();
+        let sdk_version = get_sdk_version();
         unsafe {
             let mut loaded_extns = state.extn_state.loaded_libraries.write().unwrap();
             for (extn_path, entry) in extn_paths {
@@ -79,9 +103,46 @@ impl Bootstep<BootstrapState> for LoadExtensionMetadataStep {
                 );
                 let r = Self::load_extension_library(extn_path.clone(), entry);
                 match r {
-                    Some(loaded_extn) => {
-                        info!("Adding {}", loaded_extn.metadata.symbols.len());
-                        loaded_extns.push(loaded_extn);
+                    Some(mut loaded_extn) => {
+                        let mismatches = Self::get_version_mismatches(
+                            &loaded_extn.entry,
+                            &loaded_extn.metadata,
+                            &sdk_version,
+                        );
+                        if mismatches.is_empty() {
+                            info!("Adding {}", loaded_extn.metadata.symbols.len());
+                            for symbol in &loaded_extn.metadata.symbols {
+                                if !symbol.supports_health_check() {
+                                    warn!(
+                                        "{} is built against ripple_sdk {} without health checks, it is not supervised",
+                                        symbol.id, symbol.required_version
+                                    );
+                                    state.extn_state.set_unsupervised(&symbol.id.to_string());
+                                }
+                            }
+                            loaded_extns.push(loaded_extn);
+                        } else {
+                            for mismatch in &mismatches {
+                                error!("Refusing to load file={}: {}", extn_path, mismatch);
+                            }
+                            if !loaded_extn.entry.optional {
+                                return Err(RippleError::BootstrapError);
+                            }
+                            warn!("Skipping optional extension file={}", extn_path);
+                            for symbol in &loaded_extn.metadata.symbols {
+                                state.extn_state.update_extn_status(
+                                    symbol.id.clone(),
+                                    ExtnStatus::Incompatible,
+                                );
+                            }
+                            loaded_extn.entry.exclude();
+                            state
+                                .extn_state
+                                .excluded_extns
+                                .write()
+                                .unwrap()
+                                .push(loaded_extn.entry);
+                        }
                     }
                     None => warn!(
                         "file={} doesnt contain a valid extension library",
@@ -101,3 +162,65 @@ impl Bootstep<BootstrapState> for LoadExtensionMetadataStep {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::{
+        api::manifest::extn_manifest::{ExtnKind, ExtnSymbol},
+        extn::{
+            extn_id::{ExtnClassId, ExtnId},
+            ffi::ffi_library::ExtnSymbolMetadata,
+        },
+        framework::ripple_contract::{ContractFulfiller, RippleContract},
+    };
+
+    #[test]
+    fn test_get_version_mismatches() {
+        let device = ExtnId::new_channel(ExtnClassId::Device, "thunder".into());
+        let launcher = ExtnId::new_channel(ExtnClassId::Launcher, "internal".into());
+        let metadata = ExtnMetadata {
+            name: "test".into(),
+            symbols: vec![
+                ExtnSymbolMetadata::get(
+                    device.clone(),
+                    ContractFulfiller::new(vec![RippleContract::DeviceInfo]),
+                    Version::new(2, 0, 0),
+                ),
+                ExtnSymbolMetadata::get(
+                    launcher,
+                    ContractFulfiller::new(vec![RippleContract::Launcher]),
+                    Version::new(3, 0, 0),
+                ),
+            ],
+        };
+        let entry = ExtnManifestEntry {
+            path: "libtest".into(),
+            symbols: vec![ExtnSymbol {
+                id: device.to_string(),
+                uses: vec![],
+                fulfills: vec![],
+                config: None,
+            }],
+            resolution: None,
+            kind: ExtnKind::Library,
+            optional: true,
+        };
+
+        // symbols which are not declared in the manifest are not loaded
+        let mismatches = LoadExtensionMetadataStep::get_version_mismatches(
+            &entry,
+            &metadata,
+            &Version::new(1, 1, 0),
+        );
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].id, device);
+
+        assert!(LoadExtensionMetadataStep::get_version_mismatches(
+            &entry,
+            &metadata,
+            &Version::new(2, 1, 0)
+        )
+        .is_empty());
+    }
+}
@@ -24,13 +24,13 @@ use ripple_sdk::{
         ffi::{ffi_channel::load_channel_builder, ffi_jsonrpsee::load_jsonrpsee_methods},
     },
     framework::bootstrap::Bootstep,
-    log::{debug, error, info},
+    log::{debug, error, info, warn},
     utils::error::RippleError,
 };
 
 use crate::state::{
     bootstrap_state::{BootstrapState, ChannelsState},
-    extn_state::PreLoadedExtnChannel,
+    extn_state::{ExtnChannelSource, PreLoadedExtnChannel},
 };
 use jsonrpsee::core::server::rpc_module::Methods;
 
@@ -38,8 +38,31 @@ use jsonrpsee::core::server::rpc_module::Methods;
 /// Currently this step loads
 /// 1. Device Channel
 /// 2. Device Extensions
+/// 3. Channels of extensions hosted in a process
 pub struct LoadExtensionsStep;
 
+impl LoadExtensionsStep {
+    /// Channels of the extensions hosted in a process, only channels can be hosted
+    fn get_process_channels(state: &BootstrapState) -> Vec<PreLoadedExtnChannel> {
+        let manifest = state.platform_state.get_manifest();
+        let mut channels = Vec::new();
+        for entry in manifest.extns.iter().filter(|entry| entry.is_process()) {
+            let path = entry.get_path(&manifest.default_path, &manifest.default_extension);
+            for symbol in &entry.symbols {
+                match ExtnId::try_from(symbol.id.clone()) {
+                    Ok(extn_id) if extn_id.is_channel() => channels.push(PreLoadedExtnChannel {
+                        channel: ExtnChannelSource::Process(path.clone()),
+                        extn_id,
+                        symbol: symbol.clone(),
+                    }),
+                    _ => warn!("{} cannot be hosted in a process", symbol.id),
+                }
+            }
+        }
+        channels
+    }
+}
+
 #[async_trait]
 impl Bootstep<BootstrapState> for LoadExtensionsStep {
     fn get_name(&self) -> String {
@@ -70,7 +93,7 @@ impl Bootstep<BootstrapState> for LoadExtensionsStep {
                             debug!("building channel {}", channel.id);
                             if let Ok(extn_channel) = (builder.build)(extn_id.to_string()) {
                                 let preloaded_channel = PreLoadedExtnChannel {
-                                    channel: extn_channel,
+                                    channel: ExtnChannelSource::Library(extn_channel),
                                     extn_id: extn_id.clone(),
                                     symbol: channel.clone(),
                                 };
@@ -129,6 +152,14 @@ impl Bootstep<BootstrapState> for LoadExtensionsStep {
             }
         }
 
+        for channel in Self::get_process_channels(&state) {
+            if channel.extn_id.is_device_channel() {
+                device_channels.push(channel);
+            } else {
+                deferred_channels.push(channel);
+            }
+        }
+
         {
             let mut device_channel_state = state.extn_state.device_channels.write().unwrap();
             info!("{} Device channels extension loaded", device_channels.len());
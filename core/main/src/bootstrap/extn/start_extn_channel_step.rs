@@ -18,6 +18,7 @@
 use ripple_sdk::{
     api::status_update::ExtnStatus,
     async_trait::async_trait,
+    extn::extn_id::ExtnId,
     framework::{bootstrap::Bootstep, RippleResponse},
     log::{error, warn},
     tokio::sync::mpsc,
@@ -40,6 +41,22 @@ fn start_preloaded_channel(
     Ok(())
 }
 
+/// Orders the extensions fulfilling each contract by the resolution entries of the manifest
+fn apply_resolutions(state: &BootstrapState) {
+    let client = state.platform_state.get_client().get_extn_client();
+    let manifest = state.platform_state.get_manifest();
+    for entry in state.extn_state.get_extn_entries(&manifest) {
+        let Some(resolution) = entry.resolution else {
+            continue;
+        };
+        for symbol in entry.symbols {
+            if let Ok(id) = ExtnId::try_from(symbol.id) {
+                client.set_resolution(&id, &resolution);
+            }
+        }
+    }
+}
+
 /// Bootstep which starts the All Extns channels intitiating including the device interface connection channel.
 /// This step calls the start method on the all the Channels and waits for a successful
 /// [ExtnStatus] before proceeding to the next boot step.
@@ -75,6 +92,7 @@ impl Bootstep<BootstrapState> for StartExtnChannelsStep {
                 }
             }
         }
+        apply_resolutions(&state);
         for extn_id in extn_ids {
             let (tx, mut tr) = mpsc::channel(1);
             if !state
@@ -93,6 +111,7 @@ impl Bootstep<BootstrapState> for StartExtnChannelsStep {
                             error!("{} extension failed to load. Ripple needs to be restarted.",extn_id.to_string());
                             return Err(RippleError::BootstrapError);
                         }
+                        ExtnStatus::Incompatible => {}
                     }
                 }
             }
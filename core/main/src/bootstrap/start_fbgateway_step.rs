@@ -22,7 +22,7 @@ use crate::{
         firebolt_gateway::FireboltGateway,
         handlers::{
             accessory_rpc::AccessoryRippleProvider, account_rpc::AccountRPCProvider,
-            advertising_rpc::AdvertisingRPCProvider,
+            admin_rpc::AdminRPCProvider, advertising_rpc::AdvertisingRPCProvider,
             audio_description_rpc::AudioDescriptionRPCProvider,
             authentication_rpc::AuthRPCProvider, capabilities_rpc::CapRPCProvider,
             closed_captions_rpc::ClosedcaptionsRPCProvider, device_rpc::DeviceRPCProvider,
@@ -32,22 +32,27 @@ use crate::{
             metrics_management_rpc::MetricsManagementProvider, metrics_rpc::MetricsRPCProvider,
             parameters_rpc::ParametersRPCProvider, privacy_rpc::PrivacyProvider,
             profile_rpc::ProfileRPCProvider, provider_registrar::ProviderRegistrar,
-            second_screen_rpc::SecondScreenRPCProvider,
+            rules_rpc::RulesRPCProvider, second_screen_rpc::SecondScreenRPCProvider,
             secure_storage_rpc::SecureStorageRPCProvider, user_grants_rpc::UserGrantsRPCProvider,
             voice_guidance_rpc::VoiceguidanceRPCProvider, wifi_rpc::WifiRPCProvider,
         },
-        rpc::RippleRPCProvider,
+        rpc::{register_aliases, RippleRPCProvider},
+    },
+    service::{
+        extn::extn_supervisor::ExtnSupervisor, replay::ReplayService,
+        telemetry_builder::TelemetryBuilder, watchdog::Watchdog,
+    },
+    state::{
+        bootstrap_state::BootstrapState, extn_state::ExtnState, platform_state::PlatformState,
     },
-    service::telemetry_builder::TelemetryBuilder,
-    state::{bootstrap_state::BootstrapState, platform_state::PlatformState},
 };
 use jsonrpsee::core::{async_trait, server::rpc_module::Methods};
 use ripple_sdk::log::{debug, info};
-use ripple_sdk::{framework::bootstrap::Bootstep, utils::error::RippleError};
+use ripple_sdk::{framework::bootstrap::Bootstep, tokio, utils::error::RippleError};
 pub struct FireboltGatewayStep;
 
 impl FireboltGatewayStep {
-    async fn init_handlers(&self, state: PlatformState, extn_methods: Methods) -> Methods {
+    async fn init_handlers(&self, state: PlatformState, extn_state: ExtnState) -> Methods {
         let mut methods = Methods::new();
 
         // TODO: Ultimately this may be able to register all providers below, for now just does
@@ -78,12 +83,17 @@ impl FireboltGatewayStep {
         let _ = methods.merge(AudioDescriptionRPCProvider::provide_with_alias(
             state.clone(),
         ));
+        let _ = methods.merge(RulesRPCProvider::provide_with_alias(state.clone()));
+        let _ = methods.merge(register_aliases(
+            &state,
+            AdminRPCProvider::provide(state.clone(), extn_state.clone()),
+        ));
 
         // LCM Api(s) not required for internal launcher
         if !state.has_internal_launcher() {
             let _ = methods.merge(LifecycleManagementProvider::provide_with_alias(state));
         }
-        let _ = methods.merge(extn_methods);
+        let _ = methods.merge(extn_state.get_extn_methods());
         methods
     }
 }
@@ -96,10 +106,7 @@ impl Bootstep<BootstrapState> for FireboltGatewayStep {
 
     async fn setup(&self, state: BootstrapState) -> Result<(), RippleError> {
         let methods = self
-            .init_handlers(
-                state.platform_state.clone(),
-                state.extn_state.get_extn_methods(),
-            )
+            .init_handlers(state.platform_state.clone(), state.extn_state.clone())
             .await;
         let gateway = FireboltGateway::new(state.clone(), methods);
         debug!("Handlers initialized");
@@ -109,6 +116,10 @@ impl Bootstep<BootstrapState> for FireboltGatewayStep {
         {
             return Err(RippleError::BootstrapError);
         }
+        Watchdog::notify_status("Running");
+        Watchdog::start(state.platform_state.clone());
+        ExtnSupervisor::start(state.platform_state.clone(), state.extn_state.clone());
+        tokio::spawn(ReplayService::start(state.platform_state.clone()));
         TelemetryBuilder::send_ripple_telemetry(&state.platform_state);
         info!(
             "Ripple Total Bootstrap time: {}",
@@ -38,18 +38,35 @@ impl Bootstep<BootstrapState> for StartWsStep {
         let internal_ws_enabled = manifest.get_internal_ws_enabled();
         let iai_c = iai.clone();
         if ws_enabled {
-            let ws_addr = manifest.get_ws_gateway_host();
+            let ws_config = manifest.get_ws_configuration();
+            if ws_config.unix_socket.is_some() {
+                let (ws_config, state_for_ws, iai) =
+                    (ws_config.clone(), state.platform_state.clone(), iai.clone());
+                tokio::spawn(async move {
+                    FireboltWs::start_unix(ws_config, state_for_ws, true, iai).await;
+                });
+            }
             let state_for_ws = state.platform_state.clone();
             tokio::spawn(async move {
-                FireboltWs::start(ws_addr.as_str(), state_for_ws, true, iai.clone()).await;
+                FireboltWs::start(ws_config, state_for_ws, true, iai.clone()).await;
             });
         }
 
         if internal_ws_enabled {
-            let ws_addr = manifest.get_internal_gateway_host();
+            let ws_config = manifest.get_internal_ws_configuration();
+            if ws_config.unix_socket.is_some() {
+                let (ws_config, state_for_ws, iai) = (
+                    ws_config.clone(),
+                    state.platform_state.clone(),
+                    iai_c.clone(),
+                );
+                tokio::spawn(async move {
+                    FireboltWs::start_unix(ws_config, state_for_ws, false, iai).await;
+                });
+            }
             let state_for_ws = state.platform_state;
             tokio::spawn(async move {
-                FireboltWs::start(ws_addr.as_str(), state_for_ws, false, iai_c).await;
+                FireboltWs::start(ws_config, state_for_ws, false, iai_c).await;
             });
         }
 
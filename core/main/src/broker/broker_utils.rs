@@ -20,14 +20,31 @@ use futures::stream::{SplitSink, SplitStream};
 use futures_util::StreamExt;
 use jsonrpsee::core::RpcResult;
 use ripple_sdk::{
-    api::gateway::rpc_gateway_api::{CallContext, JsonRpcApiError, RpcRequest},
+    api::{
+        gateway::rpc_gateway_api::{CallContext, JsonRpcApiError, RpcRequest},
+        session::{AccountSession, SessionTokenRequest, TokenContext},
+    },
+    extn::extn_client_message::ExtnResponse,
     log::{error, info},
     tokio::{self, net::TcpStream},
-    utils::rpc_utils::extract_tcp_port,
+    utils::{error::RippleError, rpc_utils::extract_tcp_port},
 };
 use serde_json::Value;
 use std::time::Duration;
-use tokio_tungstenite::{client_async, tungstenite::Message, WebSocketStream};
+use tokio_tungstenite::{
+    client_async, client_async_tls_with_config,
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{header::AUTHORIZATION, HeaderValue},
+        Message,
+    },
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+
+use super::rules_engine::{RuleEndpoint, RuleEndpointAuth};
+
+pub type EndpointWsSink = SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>;
+pub type EndpointWsStream = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
 
 pub struct BrokerUtils;
 
@@ -72,6 +89,152 @@ impl BrokerUtils {
         }
     }
 
+    /// Connects to a websocket endpoint defined in the rules, using TLS for `wss` urls and
+    /// adding the `Authorization` header for endpoints with auth configuration.
+    /// Retries until the connection is established, fails only for an invalid url.
+    pub async fn get_endpoint_ws_broker(
+        ps: Option<&PlatformState>,
+        endpoint: &RuleEndpoint,
+        session: Option<&AccountSession>,
+        alias: Option<String>,
+    ) -> Result<(EndpointWsSink, EndpointWsStream), RippleError> {
+        let url = endpoint.get_url();
+        info!("Broker Endpoint url {}", url);
+        let url_path = if let Some(a) = alias {
+            format!("{}{}", url, a)
+        } else {
+            url.clone()
+        };
+        let tcp_port = Self::get_tcp_address(&url)?;
+        let mut index = 0;
+
+        loop {
+            match Self::connect_endpoint_ws(ps, endpoint, session, &url_path, &tcp_port).await {
+                Ok(stream) => break Ok(stream.split()),
+                Err(e) => {
+                    if (index % 10).eq(&0) {
+                        error!(
+                            "Broker with {} failed with retry for last {} secs in {} error={}",
+                            url_path, index, tcp_port, e
+                        );
+                    }
+                }
+            }
+            index += 1;
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    /// Returns the `host:port` address of a url, using the default port of the scheme
+    /// when the url has none.
+    pub fn get_tcp_address(url: &str) -> Result<String, RippleError> {
+        let url = url::Url::parse(url).map_err(|e| RippleError::BrokerError(e.to_string()))?;
+        match (url.host(), url.port_or_known_default()) {
+            (Some(host), Some(port)) => Ok(format!("{}:{}", host, port)),
+            _ => Err(RippleError::BrokerError(format!(
+                "no host and port in {}",
+                url
+            ))),
+        }
+    }
+
+    async fn connect_endpoint_ws(
+        ps: Option<&PlatformState>,
+        endpoint: &RuleEndpoint,
+        session: Option<&AccountSession>,
+        url_path: &str,
+        tcp_port: &str,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, RippleError> {
+        let mut request = url_path
+            .into_client_request()
+            .map_err(|e| RippleError::BrokerError(e.to_string()))?;
+        if let Some(auth) = Self::get_auth_header(ps, endpoint, session, None).await? {
+            let value = HeaderValue::from_str(&auth)
+                .map_err(|e| RippleError::BrokerError(e.to_string()))?;
+            request.headers_mut().insert(AUTHORIZATION, value);
+        }
+        let connector = if endpoint.is_secure() {
+            Connector::NativeTls(Self::get_tls_connector(endpoint)?)
+        } else {
+            Connector::Plain
+        };
+        let stream = TcpStream::connect(tcp_port)
+            .await
+            .map_err(|e| RippleError::BrokerError(e.to_string()))?;
+        // Some WS servers lock on to the Port but not setup handshake till they are fully setup
+        let (stream, _) = client_async_tls_with_config(request, stream, None, Some(connector))
+            .await
+            .map_err(|e| RippleError::BrokerError(e.to_string()))?;
+        Ok(stream)
+    }
+
+    /// Creates the TLS connector for an endpoint. Certificates from the configured `ca_file`
+    /// bundle are trusted in addition to the system roots.
+    pub fn get_tls_connector(
+        endpoint: &RuleEndpoint,
+    ) -> Result<native_tls::TlsConnector, RippleError> {
+        let mut builder = native_tls::TlsConnector::builder();
+        if let Some(ca_file) = endpoint.tls.as_ref().and_then(|t| t.ca_file.as_ref()) {
+            let bundle = std::fs::read_to_string(ca_file).map_err(|e| {
+                RippleError::BrokerError(format!("cannot read ca_file {} {}", ca_file, e))
+            })?;
+            for pem in Self::split_pem_bundle(&bundle) {
+                let certificate = native_tls::Certificate::from_pem(pem.as_bytes())
+                    .map_err(|e| RippleError::BrokerError(format!("{} {}", ca_file, e)))?;
+                builder.add_root_certificate(certificate);
+            }
+        }
+        builder
+            .build()
+            .map_err(|e| RippleError::BrokerError(e.to_string()))
+    }
+
+    fn split_pem_bundle(bundle: &str) -> Vec<String> {
+        const END_MARKER: &str = "-----END CERTIFICATE-----";
+        bundle
+            .split_inclusive(END_MARKER)
+            .filter(|pem| pem.contains(END_MARKER))
+            .map(|pem| pem.trim().to_owned())
+            .collect()
+    }
+
+    /// Returns the value for the `Authorization` header of an endpoint.
+    /// Session tokens are requested from the distributor token processors, an app scoped
+    /// token is requested when both the account session and the app id are available.
+    pub async fn get_auth_header(
+        ps: Option<&PlatformState>,
+        endpoint: &RuleEndpoint,
+        session: Option<&AccountSession>,
+        app_id: Option<&str>,
+    ) -> Result<Option<String>, RippleError> {
+        match &endpoint.auth {
+            None => Ok(None),
+            Some(RuleEndpointAuth::Bearer { token }) => Ok(Some(format!("Bearer {}", token))),
+            Some(RuleEndpointAuth::Session { token_type }) => {
+                let ps = ps.ok_or(RippleError::ClientMissing)?;
+                let context = match (session, app_id) {
+                    (Some(session), Some(app_id)) => Some(TokenContext {
+                        distributor_id: session.id.clone(),
+                        app_id: app_id.to_owned(),
+                    }),
+                    _ => None,
+                };
+                let response = ps
+                    .get_client()
+                    .send_extn_request(SessionTokenRequest {
+                        token_type: *token_type,
+                        options: Vec::new(),
+                        context,
+                    })
+                    .await?;
+                match response.payload.extract() {
+                    Some(ExtnResponse::Token(t)) => Ok(Some(format!("Bearer {}", t.value))),
+                    _ => Err(RippleError::InvalidOutput),
+                }
+            }
+        }
+    }
+
     pub async fn process_internal_main_request<'a>(
         state: &mut PlatformState,
         method: &'a str,
@@ -106,3 +269,25 @@ impl BrokerUtils {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_tcp_address() {
+        assert_eq!(
+            BrokerUtils::get_tcp_address("ws://127.0.0.1:9998/jsonrpc").unwrap(),
+            "127.0.0.1:9998"
+        );
+        assert_eq!(
+            BrokerUtils::get_tcp_address("wss://example.com/path").unwrap(),
+            "example.com:443"
+        );
+        assert_eq!(
+            BrokerUtils::get_tcp_address("ws://[::1]/path").unwrap(),
+            "[::1]:80"
+        );
+        assert!(BrokerUtils::get_tcp_address("example.com/path").is_err());
+    }
+}
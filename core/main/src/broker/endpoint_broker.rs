@@ -18,54 +18,71 @@
 use ripple_sdk::{
     api::{
         firebolt::fb_capabilities::{
-            FireboltPermission, CAPABILITY_NOT_AVAILABLE, JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
+            FireboltPermission, CAPABILITY_NOT_AVAILABLE, JSON_RPC_ERROR_ENDPOINT_UNAVAILABLE,
+            JSON_RPC_ERROR_REQUEST_TIMEOUT, JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
         },
         gateway::rpc_gateway_api::{
             ApiMessage, ApiProtocol, CallContext, JsonRpcApiRequest, JsonRpcApiResponse,
             RpcRequest, RPC_V2,
         },
-        observability::log_signal::LogSignal,
+        observability::{
+            log_signal::LogSignal,
+            recording::{RecordEntry, RecordKind},
+        },
         session::AccountSession,
     },
     extn::extn_client_message::{ExtnEvent, ExtnMessage},
     framework::RippleResponse,
-    log::{debug, error, trace},
+    log::{debug, error, info, trace, warn},
     tokio::{
         self,
         sync::mpsc::{self, Receiver, Sender},
+        task::AbortHandle,
     },
     utils::error::RippleError,
 };
+use serde::Serialize;
 use serde_json::{json, Value};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc, RwLock,
     },
+    time::Duration,
 };
 
 use crate::{
     broker::broker_utils::BrokerUtils,
     firebolt::firebolt_gateway::{FireboltGatewayCommand, JsonRpcError},
     service::extn::ripple_client::RippleClient,
-    state::{metrics_state::MetricsState, platform_state::PlatformState, session_state::Session},
+    state::{
+        liveness_state::LivenessProbe, metrics_state::MetricsState, platform_state::PlatformState,
+        recording_state::RecordingState, session_state::Session,
+    },
     utils::router_utils::{
         add_telemetry_status_code, capture_stage, get_rpc_header, return_extn_response,
     },
 };
 
 use super::{
+    endpoint_health::{EndpointHealthState, EndpointStatus},
     event_management_utility::EventManagementUtility,
     extn_broker::ExtnBroker,
     http_broker::HttpBroker,
     provider_broker_state::{ProvideBrokerState, ProviderResult},
-    rules_engine::{jq_compile, Rule, RuleEndpoint, RuleEndpointProtocol, RuleEngine},
+    response_cache::RuleResponseCache,
+    rule_context::RulePlatformContext,
+    rules_engine::{JqFilterCache, Rule, RuleEndpoint, RuleEndpointProtocol, RuleEngine},
+    thunder::thunder_plugins_status_mgr::{State, StatusManager},
     thunder_broker::ThunderBroker,
     websocket_broker::WebsocketBroker,
     workflow_broker::WorkflowBroker,
 };
 
+/// Method of the liveness probes sent through the broker output channel
+const LIVENESS_PROBE_METHOD: &str = "ripple.liveness";
+
 #[derive(Clone, Debug)]
 pub struct BrokerSender {
     pub sender: Sender<BrokerRequest>,
@@ -151,6 +168,7 @@ pub struct BrokerConnectRequest {
     pub sub_map: BrokerSubMap,
     pub session: Option<AccountSession>,
     pub reconnector: Sender<BrokerConnectRequest>,
+    pub recording_state: RecordingState,
 }
 impl Default for BrokerConnectRequest {
     fn default() -> Self {
@@ -160,6 +178,7 @@ impl Default for BrokerConnectRequest {
             sub_map: HashMap::new(),
             session: None,
             reconnector: mpsc::channel(2).0,
+            recording_state: RecordingState::default(),
         }
     }
 }
@@ -198,6 +217,7 @@ impl BrokerConnectRequest {
             sub_map: HashMap::new(),
             session: None,
             reconnector,
+            recording_state: RecordingState::default(),
         }
     }
 
@@ -213,6 +233,7 @@ impl BrokerConnectRequest {
             sub_map: HashMap::new(),
             session,
             reconnector,
+            recording_state: RecordingState::default(),
         }
     }
 }
@@ -242,6 +263,14 @@ impl BrokerRequest {
     pub fn get_id(&self) -> String {
         self.rpc.ctx.session_id.clone()
     }
+
+    /// Returns the firebolt params of the request without the call context
+    pub fn get_params(&self) -> Value {
+        match serde_json::from_str::<Vec<Value>>(&self.rpc.params_json) {
+            Ok(mut params) if params.len() > 1 => params.pop().unwrap_or(Value::Null),
+            _ => Value::Null,
+        }
+    }
 }
 
 /// BrokerCallback will be used by the communication broker to send the firebolt response
@@ -309,6 +338,10 @@ impl BrokerOutput {
         self.data.result.is_some()
     }
 
+    pub fn is_liveness_probe(&self) -> bool {
+        self.data.id.is_none() && self.data.method.as_deref() == Some(LIVENESS_PROBE_METHOD)
+    }
+
     pub fn get_event(&self) -> Option<u64> {
         if let Some(e) = &self.data.method {
             let event: Vec<&str> = e.split('.').collect();
@@ -362,17 +395,51 @@ impl BrokerSender {
     }
 }
 
+/// Outcome of [EndpointBrokerState::reload_rules]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RulesReloadSummary {
+    pub rules: usize,
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Request forwarded to an endpoint which waits for its response
+#[derive(Debug)]
+struct PendingRequest {
+    endpoint: String,
+    timeout: Option<AbortHandle>,
+}
+
+/// Request which waits for the response of its endpoint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingRequestInfo {
+    pub id: u64,
+    pub endpoint: String,
+    pub method: Option<String>,
+    pub app_id: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct EndpointBrokerState {
     endpoint_map: Arc<RwLock<HashMap<String, BrokerSender>>>,
     callback: BrokerCallback,
     request_map: Arc<RwLock<HashMap<u64, BrokerRequest>>>,
     extension_request_map: Arc<RwLock<HashMap<u64, ExtnMessage>>>,
-    rule_engine: RuleEngine,
-    cleaner_list: Arc<RwLock<Vec<BrokerCleaner>>>,
+    rule_engine: Arc<RwLock<RuleEngine>>,
+    cleaner_list: Arc<RwLock<HashMap<String, BrokerCleaner>>>,
     reconnect_tx: Sender<BrokerConnectRequest>,
     provider_broker_state: ProvideBrokerState,
     metrics_state: MetricsState,
+    response_cache: RuleResponseCache,
+    platform_context: RulePlatformContext,
+    pending_requests: Arc<RwLock<HashMap<u64, PendingRequest>>>,
+    endpoint_health: EndpointHealthState,
+    recording_state: RecordingState,
+    // plugin status of the thunder endpoints
+    status_managers: Arc<RwLock<HashMap<String, StatusManager>>>,
 }
 impl Default for EndpointBrokerState {
     fn default() -> Self {
@@ -381,11 +448,17 @@ impl Default for EndpointBrokerState {
             callback: BrokerCallback::default(),
             request_map: Arc::new(RwLock::new(HashMap::new())),
             extension_request_map: Arc::new(RwLock::new(HashMap::new())),
-            rule_engine: RuleEngine::default(),
-            cleaner_list: Arc::new(RwLock::new(Vec::new())),
+            rule_engine: Arc::new(RwLock::new(RuleEngine::default())),
+            cleaner_list: Arc::new(RwLock::new(HashMap::new())),
             reconnect_tx: mpsc::channel(2).0,
             provider_broker_state: ProvideBrokerState::default(),
             metrics_state: MetricsState::default(),
+            response_cache: RuleResponseCache::default(),
+            platform_context: RulePlatformContext::default(),
+            pending_requests: Arc::new(RwLock::new(HashMap::new())),
+            endpoint_health: EndpointHealthState::default(),
+            recording_state: RecordingState::default(),
+            status_managers: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
@@ -403,17 +476,33 @@ impl EndpointBrokerState {
             callback: BrokerCallback { sender: tx },
             request_map: Arc::new(RwLock::new(HashMap::new())),
             extension_request_map: Arc::new(RwLock::new(HashMap::new())),
-            rule_engine,
-            cleaner_list: Arc::new(RwLock::new(Vec::new())),
+            rule_engine: Arc::new(RwLock::new(rule_engine)),
+            cleaner_list: Arc::new(RwLock::new(HashMap::new())),
             reconnect_tx,
             provider_broker_state: ProvideBrokerState::default(),
             metrics_state,
+            response_cache: RuleResponseCache::default(),
+            platform_context: RulePlatformContext::default(),
+            pending_requests: Arc::new(RwLock::new(HashMap::new())),
+            endpoint_health: EndpointHealthState::default(),
+            recording_state: RecordingState::default(),
+            status_managers: Arc::new(RwLock::new(HashMap::new())),
         };
         state.reconnect_thread(rec_tr, ripple_client);
         state
     }
     pub fn with_rules_engine(mut self, rule_engine: RuleEngine) -> Self {
-        self.rule_engine = rule_engine;
+        self.rule_engine = Arc::new(RwLock::new(rule_engine));
+        self
+    }
+
+    pub fn with_platform_context(mut self, platform_context: RulePlatformContext) -> Self {
+        self.platform_context = platform_context;
+        self
+    }
+
+    pub fn with_recording_state(mut self, recording_state: RecordingState) -> Self {
+        self.recording_state = recording_state;
         self
     }
 
@@ -449,6 +538,197 @@ impl EndpointBrokerState {
         Ok(result)
     }
 
+    fn get_endpoint(&self, key: &str) -> Option<RuleEndpoint> {
+        self.rule_engine
+            .read()
+            .unwrap()
+            .rules
+            .endpoints
+            .get(key)
+            .cloned()
+    }
+
+    /// Starts tracking a request which is forwarded to its endpoint, subscriptions are not
+    /// tracked. Requests to an endpoint with an open circuit fail fast and false is returned.
+    fn start_request(&self, request: &BrokerRequest) -> bool {
+        if request.rpc.is_subscription() || request.rpc.is_unlisten() {
+            return true;
+        }
+        let id = request.rpc.ctx.call_id;
+        let endpoint = request
+            .rule
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| "thunder".to_owned());
+        let breaker = self.get_endpoint(&endpoint).and_then(|e| e.circuit_breaker);
+        if !self
+            .endpoint_health
+            .allow_request(&endpoint, breaker.as_ref())
+        {
+            debug!("circuit of endpoint {} is open for {}", endpoint, id);
+            let error = json!({
+                "code": JSON_RPC_ERROR_ENDPOINT_UNAVAILABLE,
+                "message": format!("{} is unavailable", endpoint),
+            });
+            self.handle_broker_response(JsonRpcApiResponse {
+                id: Some(id),
+                error: Some(error),
+                ..Default::default()
+            });
+            return false;
+        }
+        self.add_pending_request(id, endpoint, &request.rule);
+        true
+    }
+
+    /// Tracks a request forwarded to an endpoint until its response is forwarded.
+    /// With a timeout of the rule or its endpoint a timeout error is sent for the request
+    /// through the broker callback, unless the response arrives or the session of the app is
+    /// cleaned up before the timeout elapses.
+    fn add_pending_request(&self, id: u64, endpoint: String, rule: &Rule) {
+        let timeout_ms = rule.timeout_ms.or_else(|| {
+            self.get_endpoint(&endpoint)
+                .and_then(|endpoint| endpoint.timeout_ms)
+        });
+        let mut pending_requests = self.pending_requests.write().unwrap();
+        let timeout = timeout_ms.map(|timeout_ms| {
+            let timeout = Duration::from_millis(timeout_ms);
+            let state = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(timeout).await;
+                state.send_timeout_error(id, timeout).await;
+            })
+            .abort_handle()
+        });
+        pending_requests.insert(id, PendingRequest { endpoint, timeout });
+    }
+
+    /// The forwarder routes the error to the caller and completes the pending request
+    async fn send_timeout_error(&self, id: u64, timeout: Duration) {
+        let request = { self.request_map.read().unwrap().get(&id).cloned() };
+        match request {
+            Some(request) => {
+                warn!(
+                    "{} request {} timed out after {:?}",
+                    request.rpc.method, id, timeout
+                );
+                let error = json!({
+                    "code": JSON_RPC_ERROR_REQUEST_TIMEOUT,
+                    "message": format!("{} timed out", request.rpc.method),
+                });
+                self.callback
+                    .send_json_rpc_api_response(JsonRpcApiResponse {
+                        id: Some(id),
+                        error: Some(error),
+                        ..Default::default()
+                    })
+                    .await;
+            }
+            None => {
+                self.pending_requests.write().unwrap().remove(&id);
+            }
+        }
+    }
+
+    /// Sends a liveness probe through the broker output channel, the forwarder answers it
+    pub fn probe_forwarder(&self) {
+        self.handle_broker_response(JsonRpcApiResponse {
+            method: Some(LIVENESS_PROBE_METHOD.to_owned()),
+            ..Default::default()
+        });
+    }
+
+    /// Returns whether the broker of the endpoint is still running, None if there is no such
+    /// endpoint
+    pub fn is_endpoint_connected(&self, key: &str) -> Option<bool> {
+        self.get_sender(key)
+            .map(|broker| !broker.sender.is_closed())
+    }
+
+    pub fn has_pending_requests(&self) -> bool {
+        !self.pending_requests.read().unwrap().is_empty()
+    }
+
+    /// Returns the requests which wait for the response of their endpoint, oldest first
+    pub fn get_pending_requests(&self) -> Vec<PendingRequestInfo> {
+        let request_map = self.request_map.read().unwrap();
+        let pending_requests = self.pending_requests.read().unwrap();
+        let mut result: Vec<PendingRequestInfo> = pending_requests
+            .iter()
+            .map(|(id, pending)| {
+                let request = request_map.get(id);
+                PendingRequestInfo {
+                    id: *id,
+                    endpoint: pending.endpoint.clone(),
+                    method: request.map(|r| r.rpc.method.clone()),
+                    app_id: request.map(|r| r.rpc.ctx.app_id.clone()),
+                }
+            })
+            .collect();
+        result.sort_by_key(|info| info.id);
+        result
+    }
+
+    /// Returns the last known state of the Thunder plugins, keyed by plugin callsign
+    pub fn get_thunder_plugin_states(&self) -> HashMap<String, State> {
+        let mut result = HashMap::new();
+        for status_manager in self.status_managers.read().unwrap().values() {
+            result.extend(status_manager.get_plugin_states());
+        }
+        result
+    }
+
+    fn remove_pending_request(&self, id: u64) -> Option<PendingRequest> {
+        let pending = self.pending_requests.write().unwrap().remove(&id)?;
+        if let Some(timeout) = &pending.timeout {
+            timeout.abort();
+        }
+        Some(pending)
+    }
+
+    /// Completes the pending request of a response and updates the health of the endpoint,
+    /// timeouts count as failures and any other response as a success. Brokers report the
+    /// responses of a failing endpoint with [EndpointBrokerState::fail_pending_request] first.
+    fn complete_pending_request(&self, id: u64, response: &JsonRpcApiResponse) {
+        let Some(pending) = self.remove_pending_request(id) else {
+            return;
+        };
+        match get_error_code(response) {
+            Some(code) if code == JSON_RPC_ERROR_REQUEST_TIMEOUT as i64 => {
+                self.record_endpoint_failure(&pending.endpoint)
+            }
+            _ => self.endpoint_health.record_success(&pending.endpoint),
+        }
+    }
+
+    /// Completes a pending request which could not be delivered to its endpoint or which the
+    /// endpoint failed to process
+    pub fn fail_pending_request(&self, id: u64) {
+        if let Some(pending) = self.remove_pending_request(id) {
+            self.record_endpoint_failure(&pending.endpoint);
+        }
+    }
+
+    fn record_endpoint_failure(&self, key: &str) {
+        let breaker = self.get_endpoint(key).and_then(|e| e.circuit_breaker);
+        self.endpoint_health.record_failure(key, breaker.as_ref());
+    }
+
+    /// Returns the health of every endpoint of the active rules
+    pub fn get_endpoint_status(&self) -> BTreeMap<String, EndpointStatus> {
+        let keys = {
+            self.rule_engine
+                .read()
+                .unwrap()
+                .rules
+                .endpoints
+                .keys()
+                .cloned()
+                .collect()
+        };
+        self.endpoint_health.get_status(keys)
+    }
+
     fn update_unsubscribe_request(&self, id: u64) {
         let mut result = self.request_map.write().unwrap();
         if let Some(mut value) = result.remove(&id) {
@@ -520,7 +800,16 @@ impl EndpointBrokerState {
         )
     }
     pub fn build_thunder_endpoint(&mut self) {
-        if let Some(endpoint) = self.rule_engine.rules.endpoints.get("thunder").cloned() {
+        let endpoint = {
+            self.rule_engine
+                .read()
+                .unwrap()
+                .rules
+                .endpoints
+                .get("thunder")
+                .cloned()
+        };
+        if let Some(endpoint) = endpoint {
             let request = BrokerConnectRequest::new(
                 "thunder".to_owned(),
                 endpoint.clone(),
@@ -531,7 +820,8 @@ impl EndpointBrokerState {
     }
 
     pub fn build_other_endpoints(&mut self, ps: PlatformState, session: Option<AccountSession>) {
-        for (key, endpoint) in self.rule_engine.rules.endpoints.clone() {
+        let endpoints = { self.rule_engine.read().unwrap().rules.endpoints.clone() };
+        for (key, endpoint) in endpoints {
             // skip thunder endpoint as it is already built using build_thunder_endpoint
             if let RuleEndpointProtocol::Thunder = endpoint.protocol {
                 continue;
@@ -564,22 +854,27 @@ impl EndpointBrokerState {
         result
     }
 
-    fn build_endpoint(&mut self, ps: Option<PlatformState>, request: BrokerConnectRequest) {
+    fn build_endpoint(&mut self, ps: Option<PlatformState>, mut request: BrokerConnectRequest) {
+        request.recording_state = self.recording_state.clone();
         let endpoint = request.endpoint.clone();
         let key = request.key.clone();
         let (broker, cleaner) = match endpoint.protocol {
             RuleEndpointProtocol::Http => (
-                HttpBroker::get_broker(None, request, self.callback.clone(), self).get_sender(),
+                HttpBroker::get_broker(ps, request, self.callback.clone(), self).get_sender(),
                 None,
             ),
             RuleEndpointProtocol::Websocket => {
                 let ws_broker =
-                    WebsocketBroker::get_broker(None, request, self.callback.clone(), self);
+                    WebsocketBroker::get_broker(ps, request, self.callback.clone(), self);
                 (ws_broker.get_sender(), Some(ws_broker.get_cleaner()))
             }
             RuleEndpointProtocol::Thunder => {
                 let thunder_broker =
                     ThunderBroker::get_broker(None, request, self.callback.clone(), self);
+                self.status_managers
+                    .write()
+                    .unwrap()
+                    .insert(key.clone(), thunder_broker.get_status_manager());
                 (
                     thunder_broker.get_sender(),
                     Some(thunder_broker.get_cleaner()),
@@ -594,14 +889,90 @@ impl EndpointBrokerState {
                 None,
             ),
         };
-        self.add_endpoint(key, broker);
+        self.add_endpoint(key.clone(), broker);
 
+        let mut cleaner_list = self.cleaner_list.write().unwrap();
         if let Some(cleaner) = cleaner {
-            let mut cleaner_list = self.cleaner_list.write().unwrap();
-            cleaner_list.push(cleaner);
+            cleaner_list.insert(key, cleaner);
+        } else {
+            cleaner_list.remove(&key);
         }
     }
 
+    /// Removes the broker of an endpoint, the broker stops once its last sender is dropped.
+    fn remove_endpoint(&mut self, key: &str) {
+        self.endpoint_map.write().unwrap().remove(key);
+        self.cleaner_list.write().unwrap().remove(key);
+        self.endpoint_health.remove(key);
+        self.status_managers.write().unwrap().remove(key);
+    }
+
+    /// Replaces the active rules with the given rule engine.
+    /// All the jq filters of the new rules are compiled upfront and the active rules are left
+    /// untouched if any of them fails to compile. Brokers are started for new or changed endpoints before
+    /// the rules are swapped and brokers of removed endpoints are stopped afterwards.
+    /// The thunder endpoint is never rebuilt at runtime.
+    pub fn reload_rules(
+        &mut self,
+        ps: PlatformState,
+        rule_engine: RuleEngine,
+    ) -> Result<RulesReloadSummary, Vec<String>> {
+        let errors = rule_engine.compile_filters();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let current = { self.rule_engine.read().unwrap().rules.endpoints.clone() };
+        let mut summary = RulesReloadSummary {
+            rules: rule_engine.rules.rules.len(),
+            ..Default::default()
+        };
+        let session = ps.session_state.get_account_session();
+        for (key, endpoint) in rule_engine.rules.endpoints.iter() {
+            if let RuleEndpointProtocol::Thunder = endpoint.protocol {
+                if current.get(key) != Some(endpoint) {
+                    warn!("Thunder endpoint {} changes need a restart", key);
+                }
+                continue;
+            }
+            match current.get(key) {
+                Some(existing) if existing == endpoint => continue,
+                Some(_) => summary.updated.push(key.clone()),
+                None => summary.added.push(key.clone()),
+            }
+            let request = BrokerConnectRequest::new_with_sesssion(
+                key.clone(),
+                endpoint.clone(),
+                self.reconnect_tx.clone(),
+                session.clone(),
+            );
+            self.build_endpoint(Some(ps.clone()), request);
+        }
+
+        {
+            *self.rule_engine.write().unwrap() = rule_engine;
+        }
+        self.response_cache.clear();
+
+        let endpoints = { self.rule_engine.read().unwrap().rules.endpoints.clone() };
+        for (key, endpoint) in current {
+            if endpoints.contains_key(&key) {
+                continue;
+            }
+            if let RuleEndpointProtocol::Thunder = endpoint.protocol {
+                warn!("Thunder endpoint {} removal needs a restart", key);
+                continue;
+            }
+            self.remove_endpoint(&key);
+            summary.removed.push(key);
+        }
+        summary.added.sort();
+        summary.updated.sort();
+        summary.removed.sort();
+        info!("Rules reloaded {:?}", summary);
+        Ok(summary)
+    }
+
     fn handle_static_request(
         &self,
         rpc_request: RpcRequest,
@@ -618,14 +989,19 @@ impl EndpointBrokerState {
             workflow_callback,
             telemetry_response_listeners,
         );
-        let mut data = JsonRpcApiResponse::default();
+        capture_stage(&self.metrics_state, &rpc_request, "static_rule_request");
         // return empty result and handle the rest with jq rule
-        let jv: Value = "".into();
-        data.result = Some(jv);
-        data.id = Some(id);
-        let output = BrokerOutput::new(data);
+        Self::send_result(id, "".into(), callback);
+    }
 
-        capture_stage(&self.metrics_state, &rpc_request, "static_rule_request");
+    /// Sends a result for a registered request to the forwarder as if it came from a broker
+    fn send_result(id: u64, result: Value, callback: BrokerCallback) {
+        let data = JsonRpcApiResponse {
+            id: Some(id),
+            result: Some(result),
+            ..Default::default()
+        };
+        let output = BrokerOutput::new(data);
         tokio::spawn(async move { callback.sender.send(output).await });
     }
 
@@ -716,7 +1092,8 @@ impl EndpointBrokerState {
             rpc_request.ctx.clone(),
         )
         .emit_debug();
-        if let Some(rule) = self.rule_engine.get_rule(&rpc_request) {
+        let rule = self.get_rule(&rpc_request);
+        if let Some(rule) = rule {
             found_rule = Some(rule.clone());
 
             if let Some(endpoint) = rule.endpoint {
@@ -775,6 +1152,22 @@ impl EndpointBrokerState {
                     session,
                     telemetry_response_listeners,
                 );
+            } else if let Some(result) = rule
+                .cache
+                .as_ref()
+                .filter(|_| !rpc_request.is_subscription())
+                .and_then(|cache| self.response_cache.get(&rpc_request, cache))
+            {
+                trace!("handling cached request for {:?}", rpc_request);
+                let (id, _) = self.update_request(
+                    &rpc_request,
+                    rule,
+                    extn_message,
+                    requestor_callback,
+                    telemetry_response_listeners,
+                );
+                capture_stage(&self.metrics_state, &rpc_request, "cached_rule_request");
+                Self::send_result(id, result, callback);
             } else if broker_sender.is_some() {
                 trace!("handling not static request for {:?}", rpc_request);
                 let broker_sender = broker_sender.unwrap();
@@ -786,9 +1179,27 @@ impl EndpointBrokerState {
                     telemetry_response_listeners,
                 );
                 capture_stage(&self.metrics_state, &rpc_request, "broker_request");
+                let state = self.clone();
                 let thunder = self.get_sender("thunder");
                 let request_context = updated_request.rpc.ctx.clone();
                 tokio::spawn(async move {
+                    if !state.start_request(&updated_request) {
+                        return;
+                    }
+                    state.recording_state.record(|| {
+                        let rpc = &updated_request.rpc;
+                        RecordEntry::new(
+                            RecordKind::BrokerRequest,
+                            json!({
+                                "alias": updated_request.rule.alias,
+                                "call_id": rpc.ctx.call_id,
+                                "params": rpc.get_params(),
+                            }),
+                        )
+                        .with_app_id(&rpc.ctx.app_id)
+                        .with_request_id(&rpc.ctx.request_id)
+                        .with_method(&rpc.method)
+                    });
                     /*
                     process "unlisten" requests here - the broker layers require state, which does not exist , as the
                     state has already been deleted by the time the unlisten request is processed.
@@ -817,6 +1228,7 @@ impl EndpointBrokerState {
                             request_context.clone(),
                         )
                         .emit_error();
+                        state.fail_pending_request(updated_request.rpc.ctx.call_id);
                         callback.send_error(updated_request, e).await
                     }
                 });
@@ -843,13 +1255,46 @@ impl EndpointBrokerState {
         }
     }
 
+    /// Returns the rule for the request with the platform values bound as `$platform`
     pub fn get_rule(&self, rpc_request: &RpcRequest) -> Option<Rule> {
-        self.rule_engine.get_rule(rpc_request)
+        let mut rule = self.rule_engine.read().unwrap().get_rule(rpc_request)?;
+        rule.variables.insert(
+            "platform".to_owned(),
+            self.platform_context.get_value(&rpc_request.ctx.app_id),
+        );
+        Some(rule)
     }
 
     // Method to cleanup all subscription on App termination
     pub async fn cleanup_for_app(&self, app_id: &str) {
-        let cleaners = { self.cleaner_list.read().unwrap().clone() };
+        // pending requests of the app will not be answered anymore
+        let pending: Vec<u64> = {
+            let request_map = self.request_map.read().unwrap();
+            let pending_requests = self.pending_requests.read().unwrap();
+            pending_requests
+                .keys()
+                .filter(|id| {
+                    request_map
+                        .get(id)
+                        .map_or(false, |r| r.rpc.ctx.app_id.eq(app_id))
+                })
+                .copied()
+                .collect()
+        };
+        for id in pending {
+            self.remove_pending_request(id);
+            self.request_map.write().unwrap().remove(&id);
+            self.extension_request_map.write().unwrap().remove(&id);
+        }
+
+        let cleaners: Vec<BrokerCleaner> = {
+            self.cleaner_list
+                .read()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect()
+        };
         for cleaner in cleaners {
             cleaner.cleanup_session(app_id).await
         }
@@ -912,9 +1357,10 @@ pub trait EndpointBroker {
                 .transform
                 .get_transform_data(super::rules_engine::RuleTransformType::Request)
             {
-                let transformed_request_res = jq_compile(
+                let transformed_request_res = rpc_request.rule.filters.run(
                     last,
                     &filter,
+                    rpc_request.rule.get_variables(),
                     format!("{}_request", rpc_request.rpc.ctx.method),
                 );
 
@@ -992,6 +1438,18 @@ impl BrokerOutputForwarder {
 
         tokio::spawn(async move {
             while let Some(output) = rx.recv().await {
+                if output.is_liveness_probe() {
+                    platform_state
+                        .liveness_state
+                        .ack(LivenessProbe::BrokerForwarder);
+                    continue;
+                }
+                platform_state.recording_state.record(|| {
+                    RecordEntry::new(
+                        RecordKind::BrokerResponse,
+                        serde_json::to_value(&output.data).unwrap_or_default(),
+                    )
+                });
                 let output_c = output.clone();
                 let mut response = output.data.clone();
                 let mut is_event = false;
@@ -1004,6 +1462,11 @@ impl BrokerOutputForwarder {
                 };
 
                 if let Some(id) = id {
+                    if !is_event {
+                        platform_state
+                            .endpoint_state
+                            .complete_pending_request(id, &response);
+                    }
                     if let Ok(broker_request) = platform_state.endpoint_state.get_request(id) {
                         LogSignal::new(
                             "start_forwarder".to_string(),
@@ -1024,6 +1487,7 @@ impl BrokerOutputForwarder {
                         let session_id = rpc_request.ctx.get_id();
                         let is_subscription = rpc_request.is_subscription();
                         let mut apply_response_needed = false;
+                        let mut result_to_cache = None;
 
                         // Step 1: Create the data
                         if let Some(result) = response.result.clone() {
@@ -1035,6 +1499,10 @@ impl BrokerOutputForwarder {
                             .emit_debug();
 
                             if is_event {
+                                platform_state
+                                    .endpoint_state
+                                    .response_cache
+                                    .invalidate(&rpc_request.ctx.method);
                                 if let Some(method) = broker_request.rule.event_handler.clone() {
                                     let platform_state_c = platform_state.clone();
                                     let rpc_request_c = rpc_request.clone();
@@ -1140,6 +1608,9 @@ impl BrokerOutputForwarder {
                                 }));
                                 platform_state.endpoint_state.update_unsubscribe_request(id);
                             } else {
+                                if broker_request.rule.cache.is_some() {
+                                    result_to_cache = Some(result.clone());
+                                }
                                 apply_response_needed = true;
                             }
                         } else {
@@ -1154,7 +1625,8 @@ impl BrokerOutputForwarder {
                             apply_response_needed = true;
                         }
 
-                        if apply_response_needed {
+                        // errors raised by the broker are not transformed by the rule
+                        if apply_response_needed && !is_broker_error(&response) {
                             // Apply response rule using params if there is any; otherwise, apply response rule using main broker request's response rule
                             let mut apply_response_using_main_req_needed = true;
                             if let Some(params) = output.data.params {
@@ -1179,13 +1651,40 @@ impl BrokerOutputForwarder {
                                         super::rules_engine::RuleTransformType::Response,
                                     )
                                 {
-                                    apply_response(filter, &rule_context_name, &mut response);
+                                    apply_response_with_vars(
+                                        filter,
+                                        &rule_context_name,
+                                        broker_request.rule.get_variables(),
+                                        &broker_request.rule.filters,
+                                        &mut response,
+                                    );
                                 } else if response.result.is_none() && response.error.is_none() {
                                     response.result = Some(Value::Null);
                                 }
                             }
                         }
 
+                        // setters clear the cached responses of their getters once they succeed
+                        if !is_event && response.error.is_none() {
+                            platform_state
+                                .endpoint_state
+                                .response_cache
+                                .invalidate(&rpc_request.method);
+                        }
+
+                        // only results which are not turned into an error by the rule are cached
+                        if let (Some(result), Some(cache)) =
+                            (result_to_cache, broker_request.rule.cache.as_ref())
+                        {
+                            if response.error.is_none() {
+                                platform_state.endpoint_state.response_cache.insert(
+                                    &rpc_request,
+                                    cache,
+                                    result,
+                                );
+                            }
+                        }
+
                         let request_id = rpc_request.ctx.call_id;
                         response.id = Some(request_id);
 
@@ -1444,16 +1943,51 @@ async fn forward_extn_event(
     }
 }
 
+fn get_error_code(response: &JsonRpcApiResponse) -> Option<i64> {
+    response
+        .error
+        .as_ref()
+        .and_then(|e| e.get("code"))
+        .and_then(Value::as_i64)
+}
+
+fn is_broker_error(response: &JsonRpcApiResponse) -> bool {
+    matches!(
+        get_error_code(response),
+        Some(code) if code == JSON_RPC_ERROR_REQUEST_TIMEOUT as i64
+            || code == JSON_RPC_ERROR_ENDPOINT_UNAVAILABLE as i64
+    )
+}
+
 pub fn apply_response(
     result_response_filter: String,
     method: &str,
     response: &mut JsonRpcApiResponse,
+) {
+    apply_response_with_vars(
+        result_response_filter,
+        method,
+        Vec::new(),
+        &JqFilterCache::default(),
+        response,
+    )
+}
+
+/// Same as [apply_response], binding the given jq variables for the filter which is compiled
+/// into the given cache
+pub fn apply_response_with_vars(
+    result_response_filter: String,
+    method: &str,
+    vars: Vec<(String, Value)>,
+    filters: &JqFilterCache,
+    response: &mut JsonRpcApiResponse,
 ) {
     match serde_json::to_value(response.clone()) {
         Ok(input) => {
-            match jq_compile(
+            match filters.run(
                 input,
                 &result_response_filter,
+                vars,
                 format!("{}_response", method),
             ) {
                 Ok(jq_out) => {
@@ -1493,9 +2027,10 @@ pub fn apply_rule_for_event(
     filter: &str,
     response: &mut JsonRpcApiResponse,
 ) {
-    if let Ok(r) = jq_compile(
+    if let Ok(r) = broker_request.rule.filters.run(
         result.clone(),
         filter,
+        broker_request.rule.get_variables(),
         format!("{}_event", rpc_request.ctx.method),
     ) {
         LogSignal::new(
@@ -1520,9 +2055,10 @@ pub fn apply_rule_for_event(
 
 fn apply_filter(broker_request: &BrokerRequest, result: &Value, rpc_request: &RpcRequest) -> bool {
     if let Some(filter) = broker_request.rule.filter.clone() {
-        if let Ok(r) = jq_compile(
+        if let Ok(r) = broker_request.rule.filters.run(
             result.clone(),
             &filter,
+            broker_request.rule.get_variables(),
             format!("{}_event filter", rpc_request.ctx.method),
         ) {
             if r.is_null() {
@@ -1558,6 +2094,12 @@ mod tests {
                         filter: None,
                         event_handler: None,
                         sources: None,
+                        http: None,
+                        workflow: None,
+                        cache: None,
+                        timeout_ms: None,
+                        variables: Default::default(),
+                        filters: Default::default(),
                     },
                     subscription_processed: None,
                     workflow_callback: None,
@@ -1599,18 +2141,37 @@ mod tests {
 
     mod endpoint_broker_state {
         use ripple_sdk::{
-            api::gateway::rpc_gateway_api::RpcRequest, tokio, tokio::sync::mpsc::channel, Mockable,
+            api::{
+                firebolt::fb_capabilities::{
+                    JSON_RPC_ERROR_ENDPOINT_UNAVAILABLE, JSON_RPC_ERROR_REQUEST_TIMEOUT,
+                },
+                gateway::rpc_gateway_api::RpcRequest,
+            },
+            tokio,
+            tokio::sync::mpsc::channel,
+            Mockable,
         };
 
+        use ripple_tdk::utils::test_utils::Mockable as TdkMockable;
+        use serde_json::json;
+        use std::time::Duration;
+
         use crate::{
             broker::{
                 endpoint_broker::tests::RippleClient,
-                rules_engine::{Rule, RuleEngine, RuleSet, RuleTransform},
+                endpoint_health::CircuitState,
+                rules_engine::{Rule, RuleEngine, RuleTransform},
+            },
+            state::{
+                bootstrap_state::ChannelsState, metrics_state::MetricsState,
+                platform_state::PlatformState,
             },
-            state::{bootstrap_state::ChannelsState, metrics_state::MetricsState},
         };
 
-        use super::EndpointBrokerState;
+        use super::{
+            BrokerCallback, BrokerConnectRequest, BrokerOutput, BrokerOutputForwarder,
+            BrokerRequest, BrokerSender, EndpointBrokerState, JsonRpcApiResponse,
+        };
 
         #[tokio::test]
         async fn get_request() {
@@ -1619,9 +2180,7 @@ mod tests {
             let state = EndpointBrokerState::new(
                 MetricsState::default(),
                 tx,
-                RuleEngine {
-                    rules: RuleSet::default(),
-                },
+                RuleEngine::default(),
                 client,
             );
             let mut request = RpcRequest::mock();
@@ -1634,6 +2193,12 @@ mod tests {
                     filter: None,
                     event_handler: None,
                     sources: None,
+                    http: None,
+                    workflow: None,
+                    cache: None,
+                    timeout_ms: None,
+                    variables: Default::default(),
+                    filters: Default::default(),
                 },
                 None,
                 None,
@@ -1649,6 +2214,12 @@ mod tests {
                     filter: None,
                     event_handler: None,
                     sources: None,
+                    http: None,
+                    workflow: None,
+                    cache: None,
+                    timeout_ms: None,
+                    variables: Default::default(),
+                    filters: Default::default(),
                 },
                 None,
                 None,
@@ -1661,6 +2232,367 @@ mod tests {
             // assert!(state.get_request(2).is_ok());
             // assert!(state.get_request(1).is_ok());
         }
+
+        fn rules(endpoint: &str, response: &str) -> RuleEngine {
+            RuleEngine::load_from_string_literal(
+                json!({
+                    "endpoints": {
+                        endpoint: { "protocol": "http", "url": "http://127.0.0.1:1" }
+                    },
+                    "rules": {
+                        "module.method": {
+                            "alias": format!("{}.method", endpoint),
+                            "endpoint": endpoint,
+                            "transform": { "response": response }
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .unwrap()
+        }
+
+        #[tokio::test]
+        async fn reload_rules() {
+            let (tx, _) = channel(2);
+            let ps = PlatformState::mock();
+            let mut state = EndpointBrokerState::new(
+                MetricsState::default(),
+                tx,
+                rules("a", ".result"),
+                RippleClient::new(ChannelsState::new()),
+            );
+            state.build_other_endpoints(ps.clone(), None);
+            let mut request = RpcRequest::mock();
+            request.method = "module.method".to_owned();
+            assert!(state.get_sender("a").is_some());
+
+            // an invalid filter leaves the active rules and endpoints untouched
+            let errors = state
+                .reload_rules(ps.clone(), rules("b", "if .result then"))
+                .unwrap_err();
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].starts_with("module.method.transform.response: "));
+            assert!(state.get_sender("a").is_some());
+            assert!(state.get_sender("b").is_none());
+            assert_eq!(state.get_rule(&request).unwrap().alias, "a.method");
+
+            let summary = state.reload_rules(ps, rules("b", ".result")).unwrap();
+            assert_eq!(summary.rules, 1);
+            assert_eq!(summary.added, vec!["b".to_owned()]);
+            assert_eq!(summary.removed, vec!["a".to_owned()]);
+            assert!(summary.updated.is_empty());
+            assert!(state.get_sender("a").is_none());
+            assert!(state.get_sender("b").is_some());
+            assert_eq!(state.get_rule(&request).unwrap().alias, "b.method");
+        }
+
+        #[tokio::test]
+        async fn cached_rule() {
+            let (tx, rx) = channel(10);
+            let rule_engine = RuleEngine::load_from_string_literal(
+                json!({
+                    "endpoints": {},
+                    "rules": {
+                        "localization.countryCode": {
+                            "alias": "org.rdk.System.getTerritory",
+                            "endpoint": "mock",
+                            "transform": { "response": ".result.territory" },
+                            "cache": { "ttl": 60 }
+                        },
+                        "localization.setCountryCode": {
+                            "alias": "org.rdk.System.setTerritory",
+                            "endpoint": "mock"
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .unwrap();
+            let mut state = EndpointBrokerState::new(
+                MetricsState::default(),
+                tx,
+                rule_engine,
+                RippleClient::new(ChannelsState::new()),
+            );
+            let (broker_tx, mut broker_rx) = channel(10);
+            state.add_endpoint("mock".to_owned(), BrokerSender { sender: broker_tx });
+            let mut ps = PlatformState::mock();
+            ps.endpoint_state = state.clone();
+            BrokerOutputForwarder::start_forwarder(ps, rx);
+
+            let (callback_tx, mut callback_rx) = channel(10);
+            let callback = BrokerCallback {
+                sender: callback_tx,
+            };
+            let call = |method: &str| {
+                let mut request = RpcRequest::mock();
+                request.method = method.to_owned();
+                assert!(state.handle_brokerage(
+                    request,
+                    None,
+                    Some(callback.clone()),
+                    vec![],
+                    None,
+                    vec![]
+                ));
+            };
+            let respond = |request: BrokerRequest| {
+                state.handle_broker_response(JsonRpcApiResponse {
+                    id: Some(request.rpc.ctx.call_id),
+                    result: Some(json!({"territory": "USA"})),
+                    ..Default::default()
+                });
+            };
+
+            call("localization.countryCode");
+            respond(broker_rx.recv().await.unwrap());
+            let output = callback_rx.recv().await.unwrap();
+            assert_eq!(output.data.result, Some(json!("USA")));
+
+            // served from the cache without reaching the broker
+            call("localization.countryCode");
+            let output = callback_rx.recv().await.unwrap();
+            assert_eq!(output.data.result, Some(json!("USA")));
+            assert!(broker_rx.try_recv().is_err());
+
+            // the setter clears the cached response once it succeeds
+            call("localization.setCountryCode");
+            let setter = broker_rx.recv().await.unwrap();
+            call("localization.countryCode");
+            callback_rx.recv().await.unwrap();
+            assert!(broker_rx.try_recv().is_err());
+            respond(setter);
+            callback_rx.recv().await.unwrap();
+            call("localization.countryCode");
+            assert!(broker_rx.recv().await.is_some());
+        }
+
+        #[tokio::test]
+        async fn request_timeout() {
+            let (tx, rx) = channel(10);
+            let rule_engine = RuleEngine::load_from_string_literal(
+                json!({
+                    "endpoints": {
+                        "mock": { "protocol": "websocket", "url": "ws://127.0.0.1:1", "timeout_ms": 50 }
+                    },
+                    "rules": {
+                        "device.name": {
+                            "alias": "org.rdk.System.getFriendlyName",
+                            "endpoint": "mock",
+                            "transform": { "response": ".result.friendlyName" }
+                        },
+                        "device.model": {
+                            "alias": "org.rdk.System.getDeviceInfo",
+                            "endpoint": "mock",
+                            "timeout_ms": 60000
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .unwrap();
+            let mut state = EndpointBrokerState::new(
+                MetricsState::default(),
+                tx,
+                rule_engine,
+                RippleClient::new(ChannelsState::new()),
+            );
+            let (broker_tx, mut broker_rx) = channel(10);
+            state.add_endpoint("mock".to_owned(), BrokerSender { sender: broker_tx });
+            let mut ps = PlatformState::mock();
+            ps.endpoint_state = state.clone();
+            BrokerOutputForwarder::start_forwarder(ps, rx);
+
+            let (callback_tx, mut callback_rx) = channel(10);
+            let callback = BrokerCallback {
+                sender: callback_tx,
+            };
+            let call = |method: &str| {
+                let mut request = RpcRequest::mock();
+                request.method = method.to_owned();
+                assert!(state.handle_brokerage(
+                    request,
+                    None,
+                    Some(callback.clone()),
+                    vec![],
+                    None,
+                    vec![]
+                ));
+            };
+            let respond = |request: &BrokerRequest| {
+                state.handle_broker_response(JsonRpcApiResponse {
+                    id: Some(request.rpc.ctx.call_id),
+                    result: Some(json!({"friendlyName": "Living Room"})),
+                    ..Default::default()
+                });
+            };
+
+            // the endpoint never answers
+            call("device.name");
+            let request = broker_rx.recv().await.unwrap();
+            let output = callback_rx.recv().await.unwrap();
+            assert_eq!(
+                output.data.error.unwrap()["code"],
+                json!(JSON_RPC_ERROR_REQUEST_TIMEOUT)
+            );
+            assert!(state.get_request(request.rpc.ctx.call_id).is_err());
+            // a late response is dropped
+            respond(&request);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert!(callback_rx.try_recv().is_err());
+
+            // a response within the timeout cancels it
+            call("device.name");
+            respond(&broker_rx.recv().await.unwrap());
+            let output = callback_rx.recv().await.unwrap();
+            assert_eq!(output.data.result, Some(json!("Living Room")));
+            assert!(state.pending_requests.read().unwrap().is_empty());
+
+            // the rule timeout overrides the endpoint and is cancelled with the app session
+            call("device.model");
+            let request = broker_rx.recv().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert!(callback_rx.try_recv().is_err());
+            state.cleanup_for_app(&request.rpc.ctx.app_id).await;
+            assert!(state.pending_requests.read().unwrap().is_empty());
+            assert!(state.get_request(request.rpc.ctx.call_id).is_err());
+        }
+
+        #[tokio::test]
+        async fn circuit_breaker() {
+            let (tx, rx) = channel(10);
+            let rule_engine = RuleEngine::load_from_string_literal(
+                json!({
+                    "endpoints": {
+                        "mock": {
+                            "protocol": "websocket",
+                            "url": "ws://127.0.0.1:1",
+                            "timeout_ms": 20,
+                            "circuit_breaker": { "failure_threshold": 1, "reset_timeout_ms": 60000 }
+                        }
+                    },
+                    "rules": {
+                        "device.name": { "alias": "org.rdk.System.getFriendlyName", "endpoint": "mock" }
+                    }
+                })
+                .to_string(),
+            )
+            .unwrap();
+            let mut state = EndpointBrokerState::new(
+                MetricsState::default(),
+                tx,
+                rule_engine,
+                RippleClient::new(ChannelsState::new()),
+            );
+            let (broker_tx, mut broker_rx) = channel(10);
+            state.add_endpoint("mock".to_owned(), BrokerSender { sender: broker_tx });
+            let mut ps = PlatformState::mock();
+            ps.endpoint_state = state.clone();
+            BrokerOutputForwarder::start_forwarder(ps, rx);
+
+            let (callback_tx, mut callback_rx) = channel(10);
+            let callback = BrokerCallback {
+                sender: callback_tx,
+            };
+            let call = || {
+                let mut request = RpcRequest::mock();
+                request.method = "device.name".to_owned();
+                assert!(state.handle_brokerage(
+                    request,
+                    None,
+                    Some(callback.clone()),
+                    vec![],
+                    None,
+                    vec![]
+                ));
+            };
+            let error_code = |output: BrokerOutput| output.data.error.unwrap()["code"].clone();
+
+            assert_eq!(
+                state.get_endpoint_status()["mock"].state,
+                CircuitState::Closed
+            );
+            call();
+            assert!(broker_rx.recv().await.is_some());
+            assert_eq!(
+                error_code(callback_rx.recv().await.unwrap()),
+                json!(JSON_RPC_ERROR_REQUEST_TIMEOUT)
+            );
+            let status = state.get_endpoint_status();
+            assert_eq!(status["mock"].state, CircuitState::Open);
+            assert_eq!(status["mock"].consecutive_failures, 1);
+
+            // fails fast without reaching the endpoint
+            call();
+            assert_eq!(
+                error_code(callback_rx.recv().await.unwrap()),
+                json!(JSON_RPC_ERROR_ENDPOINT_UNAVAILABLE)
+            );
+            assert!(broker_rx.try_recv().is_err());
+        }
+
+        #[tokio::test]
+        async fn circuit_breaker_connection_refused() {
+            let (tx, rx) = channel(10);
+            // nothing listens on the port and no timeout is configured
+            let rule_engine = RuleEngine::load_from_string_literal(
+                json!({
+                    "endpoints": {
+                        "mock": {
+                            "protocol": "http",
+                            "url": "http://127.0.0.1:1",
+                            "circuit_breaker": { "failure_threshold": 2, "reset_timeout_ms": 60000 }
+                        }
+                    },
+                    "rules": {
+                        "device.name": { "alias": "/name", "endpoint": "mock" }
+                    }
+                })
+                .to_string(),
+            )
+            .unwrap();
+            let mut state = EndpointBrokerState::new(
+                MetricsState::default(),
+                tx,
+                rule_engine,
+                RippleClient::new(ChannelsState::new()),
+            );
+            let endpoint = state.get_endpoint("mock").unwrap();
+            state.build_endpoint(
+                None,
+                BrokerConnectRequest::new("mock".to_owned(), endpoint, channel(2).0),
+            );
+            let mut ps = PlatformState::mock();
+            ps.endpoint_state = state.clone();
+            BrokerOutputForwarder::start_forwarder(ps, rx);
+
+            let (callback_tx, mut callback_rx) = channel(10);
+            let callback = BrokerCallback {
+                sender: callback_tx,
+            };
+            for failures in 1..=2 {
+                let mut request = RpcRequest::mock();
+                request.method = "device.name".to_owned();
+                assert!(state.handle_brokerage(
+                    request,
+                    None,
+                    Some(callback.clone()),
+                    vec![],
+                    None,
+                    vec![]
+                ));
+                let output = callback_rx.recv().await.unwrap();
+                assert!(output.data.result.unwrap().get("error").is_some());
+                let status = state.get_endpoint_status();
+                assert_eq!(status["mock"].consecutive_failures, failures);
+            }
+            assert_eq!(
+                state.get_endpoint_status()["mock"].state,
+                CircuitState::Open
+            );
+            assert!(state.pending_requests.read().unwrap().is_empty());
+        }
     }
 
     #[tokio::test]
@@ -0,0 +1,195 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use ripple_sdk::log::{info, warn};
+use serde::Serialize;
+
+use super::rules_engine::RuleEndpointCircuitBreaker;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+    /// Requests are forwarded to the endpoint
+    #[default]
+    Closed,
+    /// Requests fail fast until the reset timeout elapses
+    Open,
+    /// A trial request was let through, its outcome closes or opens the circuit again
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub failures: u64,
+    pub successes: u64,
+    #[serde(skip)]
+    opened_at: Option<Instant>,
+}
+
+/// Health of the broker endpoints, tracked from the outcome of the requests forwarded to them.
+/// Endpoints with a `circuit_breaker` configuration stop receiving requests after
+/// `failure_threshold` consecutive failures, once `reset_timeout_ms` elapsed a single trial
+/// request decides whether the circuit closes again.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointHealthState {
+    endpoints: Arc<RwLock<HashMap<String, EndpointStatus>>>,
+}
+
+impl EndpointHealthState {
+    /// Returns false when a request to the endpoint should fail fast
+    pub fn allow_request(&self, key: &str, breaker: Option<&RuleEndpointCircuitBreaker>) -> bool {
+        let Some(breaker) = breaker else {
+            return true;
+        };
+        let mut endpoints = self.endpoints.write().unwrap();
+        let Some(status) = endpoints.get_mut(key) else {
+            return true;
+        };
+        if status.state == CircuitState::Closed {
+            return true;
+        }
+        let reset_timeout = Duration::from_millis(breaker.reset_timeout_ms);
+        if status
+            .opened_at
+            .map_or(true, |t| t.elapsed() >= reset_timeout)
+        {
+            info!("circuit of endpoint {} is half open", key);
+            status.state = CircuitState::HalfOpen;
+            // another trial is let through if this one never completes
+            status.opened_at = Some(Instant::now());
+            return true;
+        }
+        false
+    }
+
+    pub fn record_success(&self, key: &str) {
+        let mut endpoints = self.endpoints.write().unwrap();
+        let status = endpoints.entry(key.to_owned()).or_default();
+        status.consecutive_failures = 0;
+        status.successes += 1;
+        if status.state != CircuitState::Closed {
+            info!("circuit of endpoint {} is closed", key);
+            status.state = CircuitState::Closed;
+            status.opened_at = None;
+        }
+    }
+
+    pub fn record_failure(&self, key: &str, breaker: Option<&RuleEndpointCircuitBreaker>) {
+        let mut endpoints = self.endpoints.write().unwrap();
+        let status = endpoints.entry(key.to_owned()).or_default();
+        status.consecutive_failures += 1;
+        status.failures += 1;
+        let Some(breaker) = breaker else {
+            return;
+        };
+        let open = match status.state {
+            CircuitState::Closed => status.consecutive_failures >= breaker.failure_threshold,
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+        };
+        if open {
+            warn!(
+                "circuit of endpoint {} is open after {} consecutive failures",
+                key, status.consecutive_failures
+            );
+            status.state = CircuitState::Open;
+            status.opened_at = Some(Instant::now());
+        }
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.endpoints.write().unwrap().remove(key);
+    }
+
+    /// Returns the status of the given endpoints, endpoints without any request are closed
+    pub fn get_status(&self, keys: Vec<String>) -> BTreeMap<String, EndpointStatus> {
+        let endpoints = self.endpoints.read().unwrap();
+        keys.into_iter()
+            .map(|key| {
+                let status = endpoints.get(&key).cloned().unwrap_or_default();
+                (key, status)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(reset_timeout_ms: u64) -> RuleEndpointCircuitBreaker {
+        RuleEndpointCircuitBreaker {
+            failure_threshold: 2,
+            reset_timeout_ms,
+        }
+    }
+
+    fn state(health: &EndpointHealthState, key: &str) -> CircuitState {
+        health.get_status(vec![key.to_owned()])[key].state
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let health = EndpointHealthState::default();
+        let config = breaker(60000);
+        assert!(health.allow_request("thunder", Some(&config)));
+
+        health.record_failure("thunder", Some(&config));
+        health.record_success("thunder");
+        health.record_failure("thunder", Some(&config));
+        assert_eq!(state(&health, "thunder"), CircuitState::Closed);
+        health.record_failure("thunder", Some(&config));
+        assert_eq!(state(&health, "thunder"), CircuitState::Open);
+        assert!(!health.allow_request("thunder", Some(&config)));
+        // endpoints without a breaker are only tracked
+        assert!(health.allow_request("thunder", None));
+
+        let status = &health.get_status(vec!["thunder".to_owned()])["thunder"];
+        assert_eq!(status.consecutive_failures, 2);
+        assert_eq!(status.failures, 3);
+        assert_eq!(status.successes, 1);
+    }
+
+    #[test]
+    fn test_half_open() {
+        let health = EndpointHealthState::default();
+        let config = breaker(0);
+        health.record_failure("cloud", Some(&config));
+        health.record_failure("cloud", Some(&config));
+        assert_eq!(state(&health, "cloud"), CircuitState::Open);
+
+        // a failed trial opens the circuit again
+        assert!(health.allow_request("cloud", Some(&config)));
+        assert_eq!(state(&health, "cloud"), CircuitState::HalfOpen);
+        health.record_failure("cloud", Some(&config));
+        assert_eq!(state(&health, "cloud"), CircuitState::Open);
+
+        // a successful trial closes it
+        assert!(health.allow_request("cloud", Some(&config)));
+        health.record_success("cloud");
+        assert_eq!(state(&health, "cloud"), CircuitState::Closed);
+    }
+}
@@ -17,62 +17,178 @@
 
 use std::vec;
 
-use hyper::{client::HttpConnector, Body, Client, Method, Request, Response, Uri};
+use hyper::{
+    client::HttpConnector,
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Body, Client, Method, Request, Response, Uri,
+};
+use hyper_tls::HttpsConnector;
 use ripple_sdk::{
     api::{gateway::rpc_gateway_api::JsonRpcApiError, observability::log_signal::LogSignal},
     log::{debug, error},
     tokio::{self, sync::mpsc},
     utils::error::RippleError,
 };
+use serde_json::Value;
 
-use super::endpoint_broker::{
-    BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerOutputForwarder, BrokerRequest,
-    BrokerSender, EndpointBroker, EndpointBrokerState,
+use super::{
+    broker_utils::BrokerUtils,
+    endpoint_broker::{
+        BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerOutputForwarder, BrokerRequest,
+        BrokerSender, EndpointBroker, EndpointBrokerState,
+    },
+    rules_engine::{render_template, RuleEndpoint, RuleHttpMethod},
 };
 use crate::state::platform_state::PlatformState;
 use tokio_tungstenite::tungstenite::http::uri::InvalidUri;
 
+type HttpClient = Client<HttpsConnector<HttpConnector>>;
+
 pub struct HttpBroker {
     sender: BrokerSender,
     cleaner: BrokerCleaner,
 }
-/*
 
-*/
+/// Http request derived from a BrokerRequest and the `http` section of its rule
+#[derive(Debug, Default)]
+struct HttpBrokerRequest {
+    method: Method,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+}
+
+impl From<&RuleHttpMethod> for Method {
+    fn from(value: &RuleHttpMethod) -> Self {
+        match value {
+            RuleHttpMethod::Get => Method::GET,
+            RuleHttpMethod::Post => Method::POST,
+            RuleHttpMethod::Put => Method::PUT,
+            RuleHttpMethod::Delete => Method::DELETE,
+        }
+    }
+}
+
+/// Creates a client which can talk to both http and https endpoints
+fn get_http_client(endpoint: &RuleEndpoint) -> Result<HttpClient, RippleError> {
+    let tls = BrokerUtils::get_tls_connector(endpoint)?;
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    let https = HttpsConnector::from((http, tokio_native_tls::TlsConnector::from(tls)));
+    Ok(Client::builder().build(https))
+}
+
+fn prepare_http_request(
+    request: &BrokerRequest,
+    auth: Option<String>,
+) -> Result<HttpBrokerRequest, RippleError> {
+    let params = request.get_params();
+    let http = request.rule.http.clone().unwrap_or_default();
+
+    let mut path = render_template(&request.rule.alias, &params, |v| {
+        urlencoding::encode(v).into_owned()
+    })
+    .ok_or_else(|| {
+        RippleError::BrokerError(format!(
+            "missing params for path template {}",
+            request.rule.alias
+        ))
+    })?;
+
+    // optional query params are skipped when they cannot be resolved from the request
+    let query: Vec<String> = http
+        .query
+        .iter()
+        .filter_map(|(key, template)| {
+            render_template(template, &params, |v| v.to_owned()).map(|value| {
+                format!(
+                    "{}={}",
+                    urlencoding::encode(key),
+                    urlencoding::encode(&value)
+                )
+            })
+        })
+        .collect();
+    if !query.is_empty() {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        path = format!("{}{}{}", path, separator, query.join("&"));
+    }
+
+    let mut headers: Vec<(String, String)> = http
+        .headers
+        .iter()
+        .filter_map(|(key, template)| {
+            render_template(template, &params, |v| v.to_owned()).map(|value| (key.clone(), value))
+        })
+        .collect();
+    // Authorization headers from the rule take precedence over the endpoint auth
+    if let Some(auth) = auth {
+        if !headers
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case(AUTHORIZATION.as_str()))
+        {
+            headers.push((AUTHORIZATION.to_string(), auth));
+        }
+    }
+
+    let body = if http.method.has_body() {
+        match HttpBroker::apply_request_rule(request)? {
+            Value::Null => None,
+            v => {
+                if !headers
+                    .iter()
+                    .any(|(k, _)| k.eq_ignore_ascii_case(CONTENT_TYPE.as_str()))
+                {
+                    headers.push((CONTENT_TYPE.to_string(), "application/json".to_owned()));
+                }
+                Some(v.to_string())
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok(HttpBrokerRequest {
+        method: (&http.method).into(),
+        path,
+        headers,
+        body,
+    })
+}
 
 async fn send_http_request(
-    client: &Client<HttpConnector>,
-    method: Method,
+    client: &HttpClient,
     uri: &Uri,
-    path: &str,
+    request: HttpBrokerRequest,
 ) -> Result<Response<Body>, RippleError> {
     /*
-    TODO? we may need to support body for POST request in the future
+    mix endpoint url with the rendered path
     */
-    let http_request = Request::new(Body::empty());
-    let (mut parts, _) = http_request.into_parts();
-    //TODO, need to refactor to support other methods
-    parts.method = method.clone();
-    /*
-    mix endpoint url with method
-    */
-
-    let uri: Uri = format!("{}{}", uri, path)
+    let base = uri.to_string();
+    let base = if request.path.starts_with('/') {
+        base.trim_end_matches('/')
+    } else {
+        base.as_str()
+    };
+    let uri: Uri = format!("{}{}", base, request.path)
         .parse()
         .map_err(|e: InvalidUri| RippleError::BrokerError(e.to_string()))?;
-    let new_request = Request::builder()
-        .uri(uri)
-        .body(Body::empty())
-        .map_err(|e| RippleError::BrokerError(e.to_string()))?;
-    let (uri_parts, _) = new_request.into_parts();
 
-    parts.uri = uri_parts.uri;
-
-    let http_request = Request::from_parts(parts, Body::empty());
+    let mut builder = Request::builder().method(request.method.clone()).uri(uri);
+    for (key, value) in request.headers.iter() {
+        builder = builder.header(key.as_str(), value.as_str());
+    }
+    let body = match request.body {
+        Some(body) => Body::from(body),
+        None => Body::empty(),
+    };
+    let http_request = builder
+        .body(body)
+        .map_err(|e| RippleError::BrokerError(e.to_string()))?;
 
     debug!(
         "http_broker sending {} request={}",
-        method,
+        request.method,
         http_request.uri(),
     );
     match client.request(http_request).await {
@@ -115,22 +231,40 @@ async fn body_to_bytes(body: Body) -> Vec<u8> {
 
 impl EndpointBroker for HttpBroker {
     fn get_broker(
-        _ps: Option<PlatformState>,
+        ps: Option<PlatformState>,
         request: BrokerConnectRequest,
         callback: BrokerCallback,
-        _broker_state: &mut EndpointBrokerState,
+        broker_state: &mut EndpointBrokerState,
     ) -> Self {
+        let state = broker_state.clone();
         let endpoint = request.endpoint.clone();
+        let session = request.session.clone();
         let (tx, mut tr) = mpsc::channel(10);
         let broker = BrokerSender { sender: tx };
-        let client = Client::new();
 
-        let _ =  endpoint.get_url().parse().map_err(|e| error!("broker url {:?} in endpoint is invalid, cannot start http broker. error={}",endpoint,e) ).map(|uri| tokio::spawn(async move {
+        let _ =  get_http_client(&endpoint).map_err(|e| error!("tls configuration of endpoint {:?} is invalid, cannot start http broker. error={:?}",endpoint,e) )
+            .and_then(|client| endpoint.get_url().parse::<Uri>().map(|uri| (client, uri)).map_err(|e| error!("broker url {:?} in endpoint is invalid, cannot start http broker. error={}",endpoint,e) ))
+            .map(|(client, uri)| tokio::spawn(async move {
             while let Some(request) = tr.recv().await {
                 LogSignal::new("http_broker".to_string(), format!("received request - start processing request={:?}", request), request.rpc.ctx.clone())
                     .with_diagnostic_context_item("rule_alias", request.rule.alias.as_str()).emit_debug();
-                match send_http_request(&client, Method::GET, &uri, &request.clone().rule.alias)
+                let http_request = match BrokerUtils::get_auth_header(ps.as_ref(), &endpoint, session.as_ref(), Some(&request.rpc.ctx.app_id))
                     .await
+                    .and_then(|auth| prepare_http_request(&request, auth)) {
+                    Ok(v) => v,
+                    Err(err) => {
+                        let msg = format!("Error in http broker preparing request for {} {:?}", request.rule.alias, err);
+                        LogSignal::new("http_broker".to_string(), "Prepare request failed".to_string(), request.rpc.ctx.clone())
+                                .with_diagnostic_context_item("error", &msg)
+                                .emit_error();
+                        Self::send_broker_failure_response(&callback,
+                            JsonRpcApiError::default()
+                            .with_id(request.rpc.ctx.call_id)
+                            .with_message(msg).into());
+                        continue;
+                    }
+                };
+                match send_http_request(&client, &uri, http_request).await
                 {
                     Ok(response) => {
                         let (parts, body) = response.into_parts();
@@ -148,6 +282,9 @@ impl EndpointBroker for HttpBroker {
                             )
                             .emit_debug();
 
+                            if !parts.status.is_success() {
+                                state.fail_pending_request(request.rpc.ctx.call_id);
+                            }
                             send_broker_response(&callback, &request, &body).await;
                             if !parts.status.is_success() {
                                 LogSignal::new("http_broker".to_string(), "Prepare request failed".to_string(), request.rpc.ctx.clone())
@@ -160,6 +297,7 @@ impl EndpointBroker for HttpBroker {
                             LogSignal::new("http_broker".to_string(), "Prepare request failed".to_string(), request.rpc.ctx.clone())
                                 .with_diagnostic_context_item("error", &msg)
                                 .emit_error();
+                            state.fail_pending_request(request.rpc.ctx.call_id);
                             Self::send_broker_failure_response(&callback,
                                 JsonRpcApiError::default()
                                 .with_id(request.rpc.ctx.call_id)
@@ -171,6 +309,7 @@ impl EndpointBroker for HttpBroker {
                         LogSignal::new("http_broker".to_string(), "Prepare request failed".to_string(), request.rpc.ctx.clone())
                                 .with_diagnostic_context_item("error", &msg)
                                 .emit_error();
+                        state.fail_pending_request(request.rpc.ctx.call_id);
                         send_broker_response(&callback, &request,  error_string_to_json(msg.as_str()).to_string().as_bytes()).await;
                     }
                 }
@@ -195,7 +334,19 @@ impl EndpointBroker for HttpBroker {
 mod tests {
     use super::*;
 
-    use ripple_sdk::tokio::runtime::Runtime;
+    use crate::broker::{
+        endpoint_broker::BrokerOutput,
+        rules_engine::{
+            Rule, RuleEndpointAuth, RuleEndpointProtocol, RuleEndpointTls, RuleHttpRequest,
+            RuleTransform,
+        },
+        test::mock_tls_server::{MockTlsServer, TEST_CA_FILE},
+    };
+    use ripple_sdk::{
+        api::gateway::rpc_gateway_api::RpcRequest, tokio::runtime::Runtime, Mockable,
+    };
+    use serde_json::json;
+    use std::time::Duration;
 
     #[test]
     fn test_send_broker_response() {
@@ -239,4 +390,137 @@ mod tests {
         assert!(broker.get_sender().sender.is_closed());
         assert!(broker.get_cleaner().cleaner.is_none());
     }
+
+    fn http_broker_request(rule: Rule, params: Value) -> BrokerRequest {
+        let mut rpc = RpcRequest::mock();
+        rpc.ctx.app_id = "someApp".to_owned();
+        rpc.params_json = RpcRequest::prepend_ctx(Some(params), &rpc.ctx);
+        let mut rule = rule;
+        rule.apply_context(&rpc);
+        BrokerRequest {
+            rpc,
+            rule,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_prepare_http_get_request() {
+        let rule = Rule {
+            alias: "/v1/accounts/{accountId}".to_owned(),
+            http: Some(RuleHttpRequest {
+                query: [
+                    ("lang".to_owned(), "{language}".to_owned()),
+                    ("optional".to_owned(), "{notProvided}".to_owned()),
+                ]
+                .into_iter()
+                .collect(),
+                headers: [("x-app-id".to_owned(), "$context.appId".to_owned())]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let request = http_broker_request(rule, json!({"accountId": "a/b", "language": "en US"}));
+        let http_request = prepare_http_request(&request, None).unwrap();
+        assert_eq!(http_request.method, Method::GET);
+        assert_eq!(http_request.path, "/v1/accounts/a%2Fb?lang=en%20US");
+        assert_eq!(
+            http_request.headers,
+            vec![("x-app-id".to_owned(), "someApp".to_owned())]
+        );
+        assert!(http_request.body.is_none());
+    }
+
+    #[test]
+    fn test_prepare_http_post_request() {
+        let rule = Rule {
+            alias: "/v1/settings/{key}".to_owned(),
+            transform: RuleTransform {
+                request: Some("{ value: .value }".to_owned()),
+                ..Default::default()
+            },
+            http: Some(RuleHttpRequest {
+                method: RuleHttpMethod::Post,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let request = http_broker_request(rule, json!({"key": "volume", "value": 11}));
+        let http_request = prepare_http_request(&request, None).unwrap();
+        assert_eq!(http_request.method, Method::POST);
+        assert_eq!(http_request.path, "/v1/settings/volume");
+        assert_eq!(
+            http_request.headers,
+            vec![("content-type".to_owned(), "application/json".to_owned())]
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&http_request.body.unwrap()).unwrap(),
+            json!({"value": 11})
+        );
+    }
+
+    #[test]
+    fn test_prepare_http_request_missing_path_param() {
+        let rule = Rule {
+            alias: "/v1/settings/{key}".to_owned(),
+            ..Default::default()
+        };
+        let request = http_broker_request(rule, json!({}));
+        assert!(prepare_http_request(&request, None).is_err());
+    }
+
+    fn https_endpoint(port: u16, ca_file: Option<String>) -> RuleEndpoint {
+        RuleEndpoint {
+            protocol: RuleEndpointProtocol::Http,
+            url: format!("https://localhost:{}", port),
+            jsonrpc: false,
+            auth: Some(RuleEndpointAuth::Bearer {
+                token: "secret".to_owned(),
+            }),
+            tls: Some(RuleEndpointTls { ca_file }),
+            timeout_ms: None,
+            circuit_breaker: None,
+        }
+    }
+
+    async fn send_https_request(endpoint: RuleEndpoint) -> BrokerOutput {
+        let (tx, mut rx) = mpsc::channel(2);
+        let callback = BrokerCallback { sender: tx };
+        let request = BrokerConnectRequest::new("cloud".to_owned(), endpoint, mpsc::channel(2).0);
+        let broker =
+            HttpBroker::get_broker(None, request, callback, &mut EndpointBrokerState::default());
+        let rule = Rule {
+            alias: "/v1/device".to_owned(),
+            ..Default::default()
+        };
+        broker
+            .get_sender()
+            .send(http_broker_request(rule, json!({})))
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_https_request_with_bearer_auth() {
+        let (port, mut requests) = MockTlsServer::start_https(json!({"name": "device"})).await;
+        let output = send_https_request(https_endpoint(port, Some(TEST_CA_FILE.to_owned()))).await;
+        assert_eq!(output.get_result().unwrap(), json!({"name": "device"}));
+
+        let request = requests.recv().await.unwrap().to_lowercase();
+        assert!(request.starts_with("get /v1/device"));
+        assert!(request.contains("authorization: bearer secret"));
+    }
+
+    #[tokio::test]
+    async fn test_https_request_with_untrusted_certificate() {
+        let (port, _requests) = MockTlsServer::start_https(json!({"name": "device"})).await;
+        let output = send_https_request(https_endpoint(port, None)).await;
+        assert!(output.get_result().unwrap().get("error").is_some());
+    }
 }
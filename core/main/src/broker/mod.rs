@@ -16,11 +16,15 @@
 //
 pub mod broker_utils;
 pub mod endpoint_broker;
+pub mod endpoint_health;
 pub mod event_management_utility;
 pub mod extn_broker;
 pub mod http_broker;
 pub mod provider_broker_state;
+pub mod response_cache;
+pub mod rule_context;
 pub mod rules_engine;
+pub mod rules_linter;
 #[cfg(test)]
 pub mod test;
 pub mod thunder;
@@ -0,0 +1,211 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use ripple_sdk::{api::gateway::rpc_gateway_api::RpcRequest, log::trace};
+use serde_json::Value;
+
+use super::rules_engine::RuleCache;
+
+#[derive(Debug, Clone)]
+struct CachedResponse {
+    result: Value,
+    expires_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct CachedMethod {
+    invalidators: Vec<String>,
+    responses: HashMap<String, CachedResponse>,
+}
+
+/// Cache of the raw broker results of the rules which have a `cache` block.
+/// Cached results go through the response transform of the rule like any other broker
+/// result, so the same entry can serve requests with different transforms.
+#[derive(Debug, Clone, Default)]
+pub struct RuleResponseCache {
+    methods: Arc<RwLock<HashMap<String, CachedMethod>>>,
+}
+
+impl RuleResponseCache {
+    fn get_key(rpc_request: &RpcRequest, cache: &RuleCache) -> String {
+        let params = rpc_request.get_params().unwrap_or(Value::Null);
+        if cache.per_app {
+            format!("{}:{}", rpc_request.ctx.app_id, params)
+        } else {
+            params.to_string()
+        }
+    }
+
+    /// Returns the cached result for the request if there is one which has not expired
+    pub fn get(&self, rpc_request: &RpcRequest, cache: &RuleCache) -> Option<Value> {
+        let key = Self::get_key(rpc_request, cache);
+        let method = rpc_request.method.to_lowercase();
+        let methods = self.methods.read().unwrap();
+        methods
+            .get(&method)
+            .and_then(|m| m.responses.get(&key))
+            .filter(|r| r.expires_at > Instant::now())
+            .map(|r| r.result.clone())
+    }
+
+    /// Stores the result for the request unless a result which has not expired is already
+    /// cached, so that serving a cached result does not extend its lifetime.
+    pub fn insert(&self, rpc_request: &RpcRequest, cache: &RuleCache, result: Value) {
+        let key = Self::get_key(rpc_request, cache);
+        let method = rpc_request.method.to_lowercase();
+        let now = Instant::now();
+        let mut methods = self.methods.write().unwrap();
+        let cached_method = methods.entry(method.clone()).or_default();
+        cached_method.invalidators = cache.get_invalidators(&method);
+        cached_method.responses.retain(|_, r| r.expires_at > now);
+        cached_method
+            .responses
+            .entry(key)
+            .or_insert_with(|| CachedResponse {
+                result,
+                expires_at: now + Duration::from_secs(cache.ttl),
+            });
+    }
+
+    /// Clears the cached results of all the methods invalidated by the given method or event
+    pub fn invalidate(&self, method: &str) {
+        let method = method.to_lowercase();
+        let invalidates = |cached_method: &CachedMethod| {
+            cached_method.invalidators.contains(&method) && !cached_method.responses.is_empty()
+        };
+        // most responses invalidate nothing, they only need the read lock
+        if !self.methods.read().unwrap().values().any(invalidates) {
+            return;
+        }
+        let mut methods = self.methods.write().unwrap();
+        for (name, cached_method) in methods.iter_mut() {
+            if invalidates(cached_method) {
+                trace!("{} invalidated cached responses of {}", method, name);
+                cached_method.responses.clear();
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        self.methods.write().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::{api::gateway::rpc_gateway_api::RpcRequest, Mockable};
+    use serde_json::json;
+
+    fn request(method: &str, app_id: &str, params: Value) -> RpcRequest {
+        let mut request = RpcRequest::mock();
+        request.method = method.to_owned();
+        request.ctx.app_id = app_id.to_owned();
+        request.params_json = RpcRequest::prepend_ctx(Some(params), &request.ctx);
+        request
+    }
+
+    fn rule_cache(ttl: u64, per_app: bool) -> RuleCache {
+        RuleCache {
+            ttl,
+            per_app,
+            invalidate_on: None,
+        }
+    }
+
+    #[test]
+    fn test_get_and_insert() {
+        let cache = RuleResponseCache::default();
+        let config = rule_cache(60, true);
+        let req = request("Localization.countryCode", "app1", json!({}));
+        assert!(cache.get(&req, &config).is_none());
+
+        cache.insert(&req, &config, json!("US"));
+        assert_eq!(cache.get(&req, &config), Some(json!("US")));
+        // an existing entry is not replaced
+        cache.insert(&req, &config, json!("UK"));
+        assert_eq!(cache.get(&req, &config), Some(json!("US")));
+
+        // key includes the app id and params
+        assert!(cache
+            .get(
+                &request("Localization.countryCode", "app2", json!({})),
+                &config
+            )
+            .is_none());
+        assert!(cache
+            .get(
+                &request("Localization.countryCode", "app1", json!({"a": 1})),
+                &config
+            )
+            .is_none());
+
+        let shared = rule_cache(60, false);
+        cache.insert(&req, &shared, json!("US"));
+        assert_eq!(
+            cache.get(
+                &request("Localization.countryCode", "app2", json!({})),
+                &shared
+            ),
+            Some(json!("US"))
+        );
+    }
+
+    #[test]
+    fn test_expiry() {
+        let cache = RuleResponseCache::default();
+        let config = rule_cache(0, true);
+        let req = request("device.name", "app1", json!({}));
+        cache.insert(&req, &config, json!("Living Room"));
+        assert!(cache.get(&req, &config).is_none());
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let cache = RuleResponseCache::default();
+        let config = rule_cache(60, true);
+        let req = request("localization.countryCode", "app1", json!({}));
+        cache.insert(&req, &config, json!("US"));
+
+        cache.invalidate("localization.onLanguageChanged");
+        assert!(cache.get(&req, &config).is_some());
+        cache.invalidate("Localization.onCountryCodeChanged");
+        assert!(cache.get(&req, &config).is_none());
+
+        cache.insert(&req, &config, json!("US"));
+        cache.invalidate("localization.setCountryCode");
+        assert!(cache.get(&req, &config).is_none());
+
+        let custom = RuleCache {
+            ttl: 60,
+            per_app: true,
+            invalidate_on: Some(vec!["device.onDeviceNameChanged".to_owned()]),
+        };
+        let req = request("device.name", "app1", json!({}));
+        cache.insert(&req, &custom, json!("Living Room"));
+        cache.invalidate("device.onNameChanged");
+        assert!(cache.get(&req, &custom).is_some());
+        cache.invalidate("device.onDeviceNameChanged");
+        assert!(cache.get(&req, &custom).is_none());
+    }
+}
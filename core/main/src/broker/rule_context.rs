@@ -0,0 +1,116 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use serde_json::{json, Value};
+
+use crate::{
+    service::apps::delegated_launcher_handler::AppManagerState,
+    state::{metrics_state::MetricsState, session_state::SessionState},
+};
+
+/// Values of the platform state which rule filters can refer to through the `$platform`
+/// jq variable, read when a request is brokered.
+///
+/// ```json
+/// {
+///   "contentCatalog": "catalog of the calling app or null",
+///   "distributorSession": { "id": "", "token": "", "accountId": "", "deviceId": "" },
+///   "deviceSessionId": "",
+///   "locale": { "language": "", "country": "", "timezone": "" }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RulePlatformContext {
+    session_state: SessionState,
+    app_manager_state: AppManagerState,
+    metrics_state: MetricsState,
+}
+
+impl RulePlatformContext {
+    pub fn new(
+        session_state: SessionState,
+        app_manager_state: AppManagerState,
+        metrics_state: MetricsState,
+    ) -> Self {
+        Self {
+            session_state,
+            app_manager_state,
+            metrics_state,
+        }
+    }
+
+    pub fn get_value(&self, app_id: &str) -> Value {
+        let catalog = self
+            .app_manager_state
+            .get(app_id)
+            .and_then(|app| app.initial_session.app.catalog);
+        let session = self.session_state.get_account_session().map(|session| {
+            json!({
+                "id": session.id,
+                "token": session.token,
+                "accountId": session.account_id,
+                "deviceId": session.device_id,
+            })
+        });
+        let metrics = self.metrics_state.get_context();
+        json!({
+            "contentCatalog": catalog,
+            "distributorSession": session,
+            "deviceSessionId": metrics.device_session_id,
+            "locale": {
+                "language": metrics.device_language,
+                "country": metrics.country,
+                "timezone": metrics.device_timezone,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::api::session::AccountSession;
+
+    #[test]
+    fn test_get_value() {
+        let context = RulePlatformContext::default();
+        let value = context.get_value("app1");
+        assert_eq!(value["contentCatalog"], Value::Null);
+        assert_eq!(value["distributorSession"], Value::Null);
+
+        context
+            .session_state
+            .insert_account_session(AccountSession {
+                id: "id".to_owned(),
+                token: "token".to_owned(),
+                account_id: "account".to_owned(),
+                device_id: "device".to_owned(),
+            });
+        context
+            .metrics_state
+            .context
+            .write()
+            .unwrap()
+            .device_language = "en".to_owned();
+        let value = context.get_value("app1");
+        assert_eq!(
+            value["distributorSession"],
+            json!({"id": "id", "token": "token", "accountId": "account", "deviceId": "device"})
+        );
+        assert_eq!(value["locale"]["language"], json!("en"));
+    }
+}
@@ -14,11 +14,12 @@
 //
 // SPDX-License-Identifier: Apache-2.0
 //
-use jaq_interpret::{Ctx, FilterT, ParseCtx, RcIter, Val};
+use jaq_interpret::{Ctx, Filter, FilterT, ParseCtx, RcIter, Val};
 use ripple_sdk::api::{
-    gateway::rpc_gateway_api::RpcRequest, manifest::extn_manifest::ExtnManifest,
+    gateway::rpc_gateway_api::RpcRequest, manifest::extn_manifest::ExtnManifest, session::TokenType,
 };
 
+use regex::Regex;
 use ripple_sdk::{
     chrono::Utc,
     log::{debug, error, info, trace, warn},
@@ -26,7 +27,9 @@ use ripple_sdk::{
     utils::error::RippleError,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use std::{fs, path::Path};
 
 #[derive(Debug, Deserialize, Default, Clone)]
@@ -42,20 +45,70 @@ impl RuleSet {
             .rules
             .into_iter()
             .map(|(k, v)| {
-                trace!("Loading JQ Rule for {}", k.to_lowercase());
-                (k.to_lowercase(), v)
+                // regex patterns are matched case insensitive, lowercasing would change
+                // escapes like `\\D` or `\\W`
+                let key = if is_pattern(&k) { k } else { k.to_lowercase() };
+                trace!("Loading JQ Rule for {}", key);
+                (key, v)
             })
             .collect();
         self.rules.extend(rules);
     }
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
 pub struct RuleEndpoint {
     pub protocol: RuleEndpointProtocol,
     pub url: String,
     #[serde(default = "default_autostart")]
     pub jsonrpc: bool,
+    #[serde(default)]
+    pub auth: Option<RuleEndpointAuth>,
+    #[serde(default)]
+    pub tls: Option<RuleEndpointTls>,
+    /// Milliseconds to wait for the response of a request before failing it with a timeout
+    /// error, applies to every rule of the endpoint which does not set its own timeout
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub circuit_breaker: Option<RuleEndpointCircuitBreaker>,
+}
+
+/// Fails requests to an endpoint fast after consecutive failures, see EndpointHealthState
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct RuleEndpointCircuitBreaker {
+    /// Consecutive timed out or undeliverable requests which open the circuit
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Milliseconds the circuit stays open before a trial request is let through
+    #[serde(default = "default_reset_timeout_ms")]
+    pub reset_timeout_ms: u64,
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_reset_timeout_ms() -> u64 {
+    30000
+}
+
+/// Credentials added as an `Authorization: Bearer` header to requests sent to an endpoint
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuleEndpointAuth {
+    /// Static token configured in the rules file
+    Bearer { token: String },
+    /// Token fetched from the distributor token processors using a SessionTokenRequest
+    Session { token_type: TokenType },
+}
+
+/// TLS settings for https and wss endpoints
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub struct RuleEndpointTls {
+    /// Path to a PEM bundle with additional root certificates trusted for the endpoint
+    #[serde(default)]
+    pub ca_file: Option<String>,
 }
 
 impl RuleEndpoint {
@@ -69,15 +122,19 @@ impl RuleEndpoint {
         }
         self.url.clone()
     }
+
+    pub fn is_secure(&self) -> bool {
+        let url = self.url.to_lowercase();
+        url.starts_with("https://") || url.starts_with("wss://")
+    }
 }
 
 fn default_autostart() -> bool {
     true
 }
 
-#[derive(Deserialize, Debug, Clone, Default)]
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
-#[cfg_attr(test, derive(PartialEq))]
 pub enum RuleEndpointProtocol {
     #[default]
     Websocket,
@@ -94,6 +151,66 @@ pub struct JsonDataSource {
     pub params: Option<String>,
 }
 
+/// A single call of a workflow stage
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    pub method: String,
+    // key under which the step result is stored, defaults to the method name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    // jq filter building the step params from the results of the earlier stages,
+    // the params of the original request are available as `$params`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<String>,
+    // a failed optional step is left out of the results instead of failing the workflow
+    #[serde(default)]
+    pub optional: bool,
+    // value used as the step result if the step fails
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback: Option<Value>,
+    // number of additional attempts before the step is considered failed
+    #[serde(default)]
+    pub retry: u32,
+    // delay before the first retry, doubled for every further retry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_delay_ms: Option<u64>,
+}
+
+/// Delay before the first retry of a workflow step without a `retry_delay_ms`
+const DEFAULT_RETRY_DELAY_MS: u64 = 100;
+/// Longest delay between two attempts of a workflow step
+const MAX_RETRY_DELAY_MS: u64 = 5000;
+
+impl WorkflowStep {
+    /// Returns the delay before the given retry, starting at 1, backing off exponentially
+    pub fn get_retry_delay(&self, retry: u32) -> Duration {
+        let delay = self.retry_delay_ms.unwrap_or(DEFAULT_RETRY_DELAY_MS);
+        let factor = 1u64
+            .checked_shl(retry.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        Duration::from_millis(delay.saturating_mul(factor).min(MAX_RETRY_DELAY_MS))
+    }
+
+    pub fn get_namespace(&self) -> String {
+        make_name_json_safe(self.namespace.as_ref().unwrap_or(&self.method))
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct WorkflowStage {
+    pub steps: Vec<WorkflowStep>,
+}
+
+/// Stages are executed in order and the steps within a stage are executed in parallel.
+/// The final response is the `compose` filter applied to the collected results,
+/// or the collected results themselves if there is no `compose` filter.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RuleWorkflow {
+    pub stages: Vec<WorkflowStage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compose: Option<String>,
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Rule {
     pub alias: String,
@@ -108,6 +225,183 @@ pub struct Rule {
     pub endpoint: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sources: Option<Vec<JsonDataSource>>,
+    // Only used by rules which are brokered through a http endpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<RuleHttpRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflow: Option<RuleWorkflow>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<RuleCache>,
+    // milliseconds to wait for the response, overrides the timeout of the endpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    // jq variables bound when running the filters of the rule, the named capture groups of
+    // a regex rule along with `$ctx` and `$platform`
+    #[serde(skip)]
+    pub variables: BTreeMap<String, Value>,
+    // compiled jq filters, shared with the rule engine which returned the rule
+    #[serde(skip)]
+    pub filters: JqFilterCache,
+}
+
+/// Enables caching of the responses of a rule, the cache key is built from the method,
+/// the request params and the calling app id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleCache {
+    // seconds a response is served from the cache
+    pub ttl: u64,
+    // responses are shared between apps when disabled
+    #[serde(default = "default_per_app")]
+    pub per_app: bool,
+    // methods and events which clear the cached responses when they are called or fired,
+    // defaults to the `on<Name>Changed` event and the `set<Name>` setter of the method
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalidate_on: Option<Vec<String>>,
+}
+
+fn default_per_app() -> bool {
+    true
+}
+
+impl RuleCache {
+    /// Returns the lowercase names of the methods and events invalidating the cached
+    /// responses of the given method
+    pub fn get_invalidators(&self, method: &str) -> Vec<String> {
+        if let Some(invalidate_on) = &self.invalidate_on {
+            return invalidate_on.iter().map(|m| m.to_lowercase()).collect();
+        }
+        match method.split_once('.') {
+            Some((module, name)) => vec![
+                format!("{}.on{}changed", module, name).to_lowercase(),
+                format!("{}.set{}", module, name).to_lowercase(),
+            ],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RuleHttpMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+impl RuleHttpMethod {
+    pub fn has_body(&self) -> bool {
+        !matches!(self, RuleHttpMethod::Get)
+    }
+}
+
+/// Describes how a rule should be sent over a http endpoint.
+/// `headers` and `query` values are templates, `{name}` placeholders are resolved from the
+/// request params and `$context.appId` is replaced with the caller app id.
+/// The request body for methods other than GET is the output of the `transform.request` filter.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct RuleHttpRequest {
+    #[serde(default)]
+    pub method: RuleHttpMethod,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub query: BTreeMap<String, String>,
+}
+
+impl RuleHttpRequest {
+    pub fn apply_context(&mut self, rpc_request: &RpcRequest) {
+        for value in self.headers.values_mut().chain(self.query.values_mut()) {
+            *value = value.replace("$context.appId", &rpc_request.ctx.app_id);
+        }
+    }
+}
+
+impl Rule {
+    /// Binds the call context of the request as `$ctx`, with an additional `rpc_v2` flag.
+    /// `$platform` is bound to null until the broker sets the platform values.
+    pub fn apply_context(&mut self, rpc_request: &RpcRequest) {
+        self.transform.apply_context();
+        if let Some(http) = self.http.as_mut() {
+            http.apply_context(rpc_request);
+        }
+        let mut ctx = serde_json::to_value(&rpc_request.ctx).unwrap_or_default();
+        if let Some(ctx) = ctx.as_object_mut() {
+            ctx.insert("rpc_v2".to_owned(), Value::Bool(rpc_request.is_rpc_v2()));
+        }
+        self.variables.insert("ctx".to_owned(), ctx);
+        self.variables
+            .entry("platform".to_owned())
+            .or_insert(Value::Null);
+    }
+
+    /// Variables bound when running the jq filters of the rule
+    pub fn get_variables(&self) -> Vec<(String, Value)> {
+        self.variables.clone().into_iter().collect()
+    }
+
+    /// Variables bound when running the workflow filters of the rule,
+    /// the rule variables followed by the request params as `$params`
+    pub fn get_workflow_variables(&self, params: &Value) -> Vec<(String, Value)> {
+        let mut variables = self.get_variables();
+        variables.push(("params".to_owned(), params.clone()));
+        variables
+    }
+
+    /// Compiles every jq filter of the rule into the filter cache and returns the errors found,
+    /// prefixed with the location of the filter within the rule.
+    pub fn compile_filters(&self) -> Vec<String> {
+        let vars: Vec<String> = self
+            .variables
+            .keys()
+            .map(String::as_str)
+            .chain(CONTEXT_VARIABLES)
+            .collect::<BTreeSet<&str>>()
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+        let mut filters: Vec<(String, String, Vec<String>)> = self
+            .transform
+            .get_filters()
+            .into_iter()
+            .map(|(name, filter)| (format!("transform.{}", name), filter, vars.clone()))
+            .collect();
+        if let Some(filter) = &self.filter {
+            filters.push(("filter".to_owned(), filter.clone(), vars.clone()));
+        }
+        for (i, source) in self.sources.iter().flatten().enumerate() {
+            if let Some(params) = &source.params {
+                filters.push((format!("sources[{}].params", i), params.clone(), Vec::new()));
+            }
+        }
+        if let Some(workflow) = &self.workflow {
+            let mut params = vars.clone();
+            params.push("params".to_owned());
+            for (i, stage) in workflow.stages.iter().enumerate() {
+                for (j, step) in stage.steps.iter().enumerate() {
+                    if let Some(filter) = &step.params {
+                        filters.push((
+                            format!("workflow.stages[{}].steps[{}].params", i, j),
+                            filter.clone(),
+                            params.clone(),
+                        ));
+                    }
+                }
+            }
+            if let Some(compose) = &workflow.compose {
+                filters.push(("workflow.compose".to_owned(), compose.clone(), params));
+            }
+        }
+
+        let mut errors = Vec::new();
+        for (location, filter, vars) in filters {
+            if let Err(errs) = self.filters.precompile(&filter, vars) {
+                errors.extend(errs.into_iter().map(|e| format!("{}: {}", location, e)));
+            }
+        }
+        errors
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -125,14 +419,10 @@ pub struct RuleTransform {
 }
 
 impl RuleTransform {
-    fn check_and_replace(&self, input: &str, rpc_request: &RpcRequest) -> String {
-        trace!(
-            "check_and_replace: input: {}, rpc_request: {:?}",
-            input,
-            rpc_request
-        );
-
-        let mut output = input.replace("$context.appId", &rpc_request.ctx.app_id);
+    /// Rewrites the `$context.appId` placeholder of older rules into `$ctx.app_id`, within
+    /// string literals into an interpolation, and inlines the event filter for `$event`.
+    fn replace_context(&self, input: &str) -> String {
+        let mut output = replace_app_id_placeholder(input);
 
         if let Some(event) = &self.event {
             output = output.replace("$event", event);
@@ -141,23 +431,36 @@ impl RuleTransform {
         output
     }
 
-    pub fn apply_context(&mut self, rpc_request: &RpcRequest) {
+    /// Returns the filters of the transform as they would be executed after the request
+    /// context is applied, paired with the name of the transform.
+    fn get_filters(&self) -> Vec<(&'static str, String)> {
+        let mut filters = Vec::new();
+        if let Some(request) = &self.request {
+            filters.push(("request", self.replace_context(request)));
+        }
+        if let Some(response) = &self.response {
+            filters.push(("response", response.clone()));
+        }
+        if let Some(event) = &self.event {
+            filters.push(("event", self.replace_context(event)));
+        }
+        if let Some(rpcv2_event) = &self.rpcv2_event {
+            filters.push(("rpcv2_event", self.replace_context(rpcv2_event)));
+        }
+        filters
+    }
+
+    pub fn apply_context(&mut self) {
         if let Some(value) = self.request.take() {
-            let _ = self
-                .request
-                .insert(self.check_and_replace(&value, rpc_request));
+            let _ = self.request.insert(self.replace_context(&value));
         }
 
         if let Some(value) = self.event.take() {
-            let _ = self
-                .event
-                .insert(self.check_and_replace(&value, rpc_request));
+            let _ = self.event.insert(self.replace_context(&value));
         }
 
         if let Some(value) = self.rpcv2_event.take() {
-            let _ = self
-                .rpcv2_event
-                .insert(self.check_and_replace(&value, rpc_request));
+            let _ = self.rpcv2_event.insert(self.replace_context(&value));
         }
     }
 
@@ -185,6 +488,9 @@ pub enum RuleTransformType {
 #[derive(Debug, Clone, Default)]
 pub struct RuleEngine {
     pub rules: RuleSet,
+    // compiled regex rule keys and jq filters, replaced along with the engine on a reload
+    patterns: RulePatterns,
+    filters: JqFilterCache,
 }
 
 impl RuleEngine {
@@ -236,9 +542,42 @@ impl RuleEngine {
                 warn!("invalid rule path {}", path)
             }
         }
+        // compile the filters once at load time, broken filters are reported right away
+        // instead of failing the first request which uses them
+        for error in engine.compile_filters() {
+            error!("invalid rule {}", error);
+        }
         engine
     }
 
+    /// Loads the rules like [RuleEngine::build] but fails on any rules file which cannot be
+    /// read or parsed, so that a broken file never results in a partial rule set.
+    pub fn try_build(extn_manifest: &ExtnManifest) -> Result<Self, String> {
+        let mut engine = RuleEngine::default();
+        for path in extn_manifest.rules_path.iter() {
+            let path_for_rule = Self::build_path(path, &extn_manifest.default_path);
+            let contents = fs::read_to_string(&path_for_rule)
+                .map_err(|e| format!("{}: {}", path_for_rule, e))?;
+            let rule_set = serde_json::from_str::<RuleSet>(&contents)
+                .map_err(|e| format!("{}: {}", path_for_rule, e))?;
+            engine.rules.append(rule_set);
+        }
+        Ok(engine)
+    }
+
+    /// Compiles the regex keys and the jq filters of all the rules into the caches,
+    /// returns the errors prefixed with the rule name.
+    pub fn compile_filters(&self) -> Vec<String> {
+        let mut errors: Vec<String> = self
+            .rules
+            .rules
+            .iter()
+            .flat_map(|(name, rule)| self.compile_rule_filters(name, rule))
+            .collect();
+        errors.sort();
+        errors
+    }
+
     pub fn load_from_content(contents: String) -> Result<(String, RuleSet), RippleError> {
         match serde_json::from_str::<RuleSet>(&contents) {
             Ok(manifest) => Ok((contents, manifest)),
@@ -255,28 +594,110 @@ impl RuleEngine {
             .contains_key(&request.ctx.method.to_lowercase())
     }
 
-    pub fn get_rule(&self, rpc_request: &RpcRequest) -> Option<Rule> {
-        let method = rpc_request.method.to_lowercase();
-        if let Some(mut rule) = self.rules.rules.get(&method).cloned() {
-            rule.transform.apply_context(rpc_request);
-            return Some(rule);
-        } else {
-            for (key, value) in &self.rules.rules {
-                if key.ends_with(".*") && method.starts_with(&key[..key.len() - 2]) {
-                    let mut rule = value.clone();
-                    rule.transform.apply_context(rpc_request);
-                    return Some(rule);
+    /// Compiles the key of a regex rule and the filters of the rule with the variables of its
+    /// key bound, returns the errors prefixed with the key.
+    pub fn compile_rule_filters(&self, key: &str, rule: &Rule) -> Vec<String> {
+        let mut rule = rule.clone();
+        if is_pattern(key) {
+            match self.get_pattern(key) {
+                Ok(regex) => {
+                    rule.variables = regex
+                        .capture_names()
+                        .flatten()
+                        .map(|name| (name.to_owned(), Value::Null))
+                        .collect()
                 }
+                Err(e) => return vec![format!("{}: invalid pattern {}", key, e)],
             }
+        }
+        rule.filters = self.filters.clone();
+        rule.compile_filters()
+            .into_iter()
+            .map(|e| format!("{}.{}", key, e))
+            .collect()
+    }
+
+    /// Returns the compiled regex of a rule key
+    pub fn get_pattern(&self, key: &str) -> Result<Arc<Regex>, regex::Error> {
+        if let Some(regex) = self.patterns.read().unwrap().get(key) {
+            return regex.clone();
+        }
+        let regex = Regex::new(&format!("(?i){}", key)).map(Arc::new);
+        self.patterns
+            .write()
+            .unwrap()
+            .insert(key.to_owned(), regex.clone());
+        regex
+    }
+
+    /// Returns the rule for the request. An exact match wins over the longest matching
+    /// `prefix.*` wildcard, which wins over the regex rules starting with `^`.
+    /// Regex rules are tried in the order of their keys and the named capture groups of the
+    /// matching rule are bound as jq variables with the case of the request, unmatched groups
+    /// are bound to null.
+    pub fn get_rule(&self, rpc_request: &RpcRequest) -> Option<Rule> {
+        let Some(mut rule) = self.find_rule(&rpc_request.method) else {
             trace!(
                 "Rule not available for {}, hence falling back to extension handler",
                 rpc_request.method
             );
+            return None;
+        };
+        rule.apply_context(rpc_request);
+        rule.filters = self.filters.clone();
+        Some(rule)
+    }
+
+    fn find_rule(&self, method: &str) -> Option<Rule> {
+        let lowercase_method = method.to_lowercase();
+        if let Some(rule) = self.rules.rules.get(&lowercase_method) {
+            return Some(rule.clone());
+        }
+
+        let wildcard = self
+            .rules
+            .rules
+            .iter()
+            .filter_map(|(key, rule)| key.strip_suffix('*').map(|prefix| (prefix, rule)))
+            .filter(|(prefix, _)| prefix.ends_with('.') && lowercase_method.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len());
+        if let Some((_, rule)) = wildcard {
+            return Some(rule.clone());
+        }
+
+        let mut patterns: Vec<&String> = self
+            .rules
+            .rules
+            .keys()
+            .filter(|key| is_pattern(key))
+            .collect();
+        patterns.sort();
+        for key in patterns {
+            // invalid patterns are reported when the rules are loaded
+            let Ok(regex) = self.get_pattern(key) else {
+                continue;
+            };
+            if let Some(captures) = regex.captures(method) {
+                let mut rule = self.rules.rules[key].clone();
+                rule.variables = regex
+                    .capture_names()
+                    .flatten()
+                    .map(|name| {
+                        let value = captures
+                            .name(name)
+                            .map_or(Value::Null, |m| Value::String(m.as_str().to_owned()));
+                        (name.to_owned(), value)
+                    })
+                    .collect();
+                return Some(rule);
+            }
         }
         None
     }
     pub fn get_rule_by_method(&self, method: &str) -> Option<Rule> {
-        self.rules.rules.get(&method.to_lowercase()).cloned()
+        let mut rule = self.rules.rules.get(&method.to_lowercase()).cloned()?;
+        rule.filters = self.filters.clone();
+        Some(rule)
     }
 }
 /// Compiles and executes a JQ filter on a given JSON input value.
@@ -315,48 +736,159 @@ impl RuleEngine {
 /// assert_eq!(result.unwrap(), json!("SCXI11BEI_VBN_24Q2_sprint_20240620140024sdy_FG_GRT"));
 /// ```
 pub fn jq_compile(input: Value, filter: &str, reference: String) -> Result<Value, RippleError> {
-    info!(
-        "Jq rule {}  input {:?}, reference {}",
-        filter, input, reference
-    );
-    let start = Utc::now().timestamp_millis();
+    jq_compile_with_vars(input, filter, Vec::new(), reference)
+}
+
+/// Same as [jq_compile], additionally binding each of the given `vars` as a global
+/// variable which the filter can refer to as `$name`.
+pub fn jq_compile_with_vars(
+    input: Value,
+    filter: &str,
+    vars: Vec<(String, Value)>,
+    reference: String,
+) -> Result<Value, RippleError> {
+    JqFilterCache::default().run(input, filter, vars, reference)
+}
+
+type JqFilters = HashMap<(Vec<String>, String), Arc<Filter>>;
+
+/// Compiled jq filters keyed by the variable names and the filter. The rules returned by a
+/// [RuleEngine] share the cache of the engine, so a reload drops the filters of the old rules.
+#[derive(Clone, Default)]
+pub struct JqFilterCache(Arc<RwLock<JqFilters>>);
+
+impl std::fmt::Debug for JqFilterCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("JqFilterCache")
+            .field(&self.0.read().unwrap().len())
+            .finish()
+    }
+}
+
+impl JqFilterCache {
+    /// Compiles a JQ filter into the cache without running it, so that the
+    /// compilation cost is not paid on the first request using the filter.
+    /// `var_names` are the global variables which are bound when the filter is executed.
+    pub fn precompile(&self, filter: &str, var_names: Vec<String>) -> Result<(), Vec<String>> {
+        self.get(filter, var_names).map(|_| ())
+    }
+
+    fn get(&self, filter: &str, var_names: Vec<String>) -> Result<Arc<Filter>, Vec<String>> {
+        let key = (var_names, filter.to_owned());
+        if let Some(f) = self.0.read().unwrap().get(&key).cloned() {
+            return Ok(f);
+        }
+        let f = Arc::new(jq_parse(filter, key.0.clone())?);
+        self.0.write().unwrap().insert(key, f.clone());
+        Ok(f)
+    }
+
+    /// Same as [jq_compile_with_vars], compiling the filter into the cache
+    pub fn run(
+        &self,
+        input: Value,
+        filter: &str,
+        vars: Vec<(String, Value)>,
+        reference: String,
+    ) -> Result<Value, RippleError> {
+        info!(
+            "Jq rule {}  input {:?}, reference {}",
+            filter, input, reference
+        );
+        let start = Utc::now().timestamp_millis();
+        let (var_names, var_values): (Vec<String>, Vec<Val>) = vars
+            .into_iter()
+            .map(|(name, value)| (name, Val::from(value)))
+            .unzip();
+        let f = match self.get(filter, var_names) {
+            Ok(f) => f,
+            Err(errs) => {
+                error!("Error in rule {}", reference);
+                for err in errs {
+                    error!("reference={} {}", reference, err);
+                }
+                return Err(RippleError::RuleError);
+            }
+        };
+
+        let inputs = RcIter::new(core::iter::empty());
+        // iterator over the output values
+        let mut out = f.run((Ctx::new(var_values, &inputs), Val::from(input)));
+        if let Some(Ok(v)) = out.next() {
+            info!(
+                "Ripple Gateway Rule Processing Time: {},{}",
+                reference,
+                Utc::now().timestamp_millis() - start
+            );
+            return Ok(Value::from(v));
+        }
+
+        Err(RippleError::ParseError)
+    }
+}
+
+/// Names of the jq variables bound for the filters of every rule
+pub const CONTEXT_VARIABLES: [&str; 2] = ["ctx", "platform"];
+
+fn replace_app_id_placeholder(input: &str) -> String {
+    const PLACEHOLDER: &str = "$context.appId";
+    let mut output = String::with_capacity(input.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        if !escaped && rest.starts_with(PLACEHOLDER) {
+            output.push_str(if in_string {
+                "\\($ctx.app_id)"
+            } else {
+                "$ctx.app_id"
+            });
+            rest = &rest[PLACEHOLDER.len()..];
+            continue;
+        }
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = in_string;
+        } else if c == '"' {
+            in_string = !in_string;
+        }
+        output.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    output
+}
+
+fn is_pattern(key: &str) -> bool {
+    key.starts_with('^')
+}
+
+/// Compiled regex rule keys, along with the error for keys which are not valid patterns
+type RulePatterns = Arc<RwLock<HashMap<String, Result<Arc<Regex>, regex::Error>>>>;
+
+fn jq_parse(filter: &str, var_names: Vec<String>) -> Result<Filter, Vec<String>> {
     // start out only from core filters,
     // which do not include filters in the standard library
     // such as `map`, `select` etc.
-
-    let mut defs = ParseCtx::new(Vec::new());
+    let mut defs = ParseCtx::new(var_names);
     defs.insert_natives(jaq_core::core());
     defs.insert_defs(jaq_std::std());
     // parse the filter
     let (f, errs) = jaq_parse::parse(filter, jaq_parse::main());
     if !errs.is_empty() {
-        error!("Error in rule {:?}", errs);
-        return Err(RippleError::RuleError);
+        return Err(errs.iter().map(|e| e.to_string()).collect());
     }
+    let Some(f) = f else {
+        return Err(vec!["empty filter".to_owned()]);
+    };
     // compile the filter in the context of the given definitions
-    let f = defs.compile(f.unwrap());
+    let f = defs.compile(f);
     if !defs.errs.is_empty() {
-        error!("Error in rule {}", reference);
-        for (err, _) in defs.errs {
-            error!("reference={} {}", reference, err);
-        }
-        return Err(RippleError::RuleError);
-    }
-
-    let inputs = RcIter::new(core::iter::empty());
-    // iterator over the output values
-    let mut out = f.run((Ctx::new([], &inputs), Val::from(input)));
-    if let Some(Ok(v)) = out.next() {
-        info!(
-            "Ripple Gateway Rule Processing Time: {},{}",
-            reference,
-            Utc::now().timestamp_millis() - start
-        );
-        return Ok(Value::from(v));
+        return Err(defs.errs.iter().map(|(e, _)| e.to_string()).collect());
     }
-
-    Err(RippleError::ParseError)
+    Ok(f)
 }
+
 pub fn compose_json_values(values: Vec<Value>) -> Value {
     if values.len() == 1 {
         return values[0].clone();
@@ -379,10 +911,41 @@ pub fn make_name_json_safe(name: &str) -> String {
     name.replace([' ', '.', ','], "_")
 }
 
+/// Resolves `{name}` placeholders in a rule template against the given params.
+/// Nested values can be addressed with a dotted path like `{settings.language}`.
+/// Each resolved value is passed through `encode` before being substituted.
+///
+/// Returns `None` if any placeholder cannot be resolved to a scalar value.
+pub fn render_template<F>(template: &str, params: &Value, encode: F) -> Option<String>
+where
+    F: Fn(&str) -> String,
+{
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        output.push_str(&rest[..start]);
+        let mut value = params;
+        for key in rest[start + 1..end].trim().split('.') {
+            value = value.get(key)?;
+        }
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => return None,
+        };
+        output.push_str(&encode(&value));
+        rest = &rest[end + 1..];
+    }
+    output.push_str(rest);
+    Some(output)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ripple_sdk::serde_json::json;
+    use ripple_sdk::{serde_json::json, Mockable};
 
     #[test]
     fn test_jq_compile() {
@@ -485,4 +1048,289 @@ mod tests {
         .unwrap()
         .contains("nested"));
     }
+
+    #[test]
+    fn test_render_template() {
+        let params = json!({"accountId": "acc 1", "count": 5, "settings": {"lang": "en"}});
+        assert_eq!(
+            render_template("/v1/{accountId}/{count}", &params, |v| v
+                .replace(' ', "%20")),
+            Some("/v1/acc%201/5".to_owned())
+        );
+        assert_eq!(
+            render_template("{settings.lang}", &params, |v| v.to_owned()),
+            Some("en".to_owned())
+        );
+        assert_eq!(
+            render_template("/static/path", &params, |v| v.to_owned()),
+            Some("/static/path".to_owned())
+        );
+        assert!(render_template("/v1/{missing}", &params, |v| v.to_owned()).is_none());
+        assert!(render_template("/v1/{settings}", &params, |v| v.to_owned()).is_none());
+    }
+
+    #[test]
+    fn test_load_http_rule() {
+        let engine = RuleEngine::load_from_string_literal(
+            json!({
+                "endpoints": {
+                    "cloud": { "protocol": "http", "url": "http://127.0.0.1:8080" }
+                },
+                "rules": {
+                    "Settings.set": {
+                        "alias": "/v1/settings/{key}",
+                        "endpoint": "cloud",
+                        "http": {
+                            "method": "POST",
+                            "headers": { "x-app-id": "$context.appId" },
+                            "query": { "scope": "{scope}" }
+                        },
+                        "transform": { "request": "{ value: .value }" }
+                    }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let rule = engine.get_rule_by_method("settings.set").unwrap();
+        let http = rule.http.unwrap();
+        assert_eq!(http.method, RuleHttpMethod::Post);
+        assert_eq!(http.query.get("scope").unwrap(), "{scope}");
+    }
+
+    #[test]
+    fn test_load_endpoint_auth() {
+        let engine = RuleEngine::load_from_string_literal(
+            json!({
+                "endpoints": {
+                    "cloud": {
+                        "protocol": "http",
+                        "url": "https://127.0.0.1:8443",
+                        "auth": { "type": "session", "token_type": "distributor" },
+                        "tls": { "ca_file": "/etc/ripple/ca.pem" }
+                    },
+                    "static": {
+                        "protocol": "websocket",
+                        "url": "ws://127.0.0.1:9998",
+                        "auth": { "type": "bearer", "token": "secret" }
+                    }
+                },
+                "rules": {}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let cloud = engine.rules.endpoints.get("cloud").unwrap();
+        assert!(cloud.is_secure());
+        assert_eq!(
+            cloud.auth,
+            Some(RuleEndpointAuth::Session {
+                token_type: TokenType::Distributor
+            })
+        );
+        assert_eq!(
+            cloud.tls.clone().unwrap().ca_file,
+            Some("/etc/ripple/ca.pem".to_owned())
+        );
+        let ws = engine.rules.endpoints.get("static").unwrap();
+        assert!(!ws.is_secure());
+        assert_eq!(
+            ws.auth,
+            Some(RuleEndpointAuth::Bearer {
+                token: "secret".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_jq_compile_with_vars() {
+        let resp = jq_compile_with_vars(
+            json!({"id": "123"}),
+            "{ id: .id, app: $app, lang: $params.lang }",
+            vec![
+                ("app".to_owned(), json!("someApp")),
+                ("params".to_owned(), json!({"lang": "en"})),
+            ],
+            String::new(),
+        );
+        assert_eq!(
+            resp.unwrap(),
+            json!({"id": "123", "app": "someApp", "lang": "en"})
+        );
+    }
+
+    #[test]
+    fn test_get_retry_delay() {
+        let step = WorkflowStep::default();
+        assert_eq!(step.get_retry_delay(1), Duration::from_millis(100));
+        assert_eq!(step.get_retry_delay(3), Duration::from_millis(400));
+        let step = WorkflowStep {
+            retry_delay_ms: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(step.get_retry_delay(2), Duration::from_millis(2000));
+        assert_eq!(step.get_retry_delay(100), Duration::from_millis(5000));
+    }
+
+    #[test]
+    fn test_compile_filters() {
+        let engine = RuleEngine::load_from_string_literal(
+            json!({
+                "endpoints": {},
+                "rules": {
+                    "module.valid": {
+                        "alias": "some.method",
+                        "transform": {
+                            "request": "{ appId: \"$context.appId\" }",
+                            "event": ".value",
+                            "rpcv2_event": "{ value: $event }"
+                        },
+                        "workflow": {
+                            "stages": [{ "steps": [{ "method": "a.b", "params": "$params" }] }],
+                            "compose": "$params + .a_b"
+                        }
+                    },
+                    "module.invalid": {
+                        "alias": "some.method",
+                        "transform": { "response": "if .result then" },
+                        "sources": [{ "method": "a.b", "params": "$unknown" }]
+                    }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let errors = engine.compile_filters();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("module.invalid.sources[0].params: "));
+        assert!(errors[1].starts_with("module.invalid.transform.response: "));
+        assert!(JqFilterCache::default()
+            .precompile("$params.lang", vec!["params".to_owned()])
+            .is_ok());
+
+        // the compiled filters are kept on the engine and shared with its rules,
+        // a reloaded engine starts out with an empty cache
+        assert!(!engine.filters.0.read().unwrap().is_empty());
+        let rule = get_rule(&engine, "module.valid").unwrap();
+        assert!(Arc::ptr_eq(&rule.filters.0, &engine.filters.0));
+        let reloaded = RuleEngine::default();
+        assert!(reloaded.filters.0.read().unwrap().is_empty());
+    }
+
+    fn get_rule(engine: &RuleEngine, method: &str) -> Option<Rule> {
+        let mut request = RpcRequest::mock();
+        request.method = method.to_owned();
+        engine.get_rule(&request)
+    }
+
+    #[test]
+    fn test_get_rule_precedence() {
+        let engine = RuleEngine::load_from_string_literal(
+            json!({
+                "endpoints": {},
+                "rules": {
+                    "device.name": { "alias": "exact" },
+                    "device.*": { "alias": "module" },
+                    "device.info.*": { "alias": "submodule" },
+                    "^device\\.(?P<prop>[a-z]+)$": { "alias": "pattern" },
+                    "^(?P<module>[a-z]+)\\.on(?P<name>\\w+)changed$": {
+                        "alias": "event",
+                        "transform": { "event": "{ module: $module, name: $name }" }
+                    }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        assert!(engine.compile_filters().is_empty());
+
+        let alias = |method: &str| get_rule(&engine, method).map(|r| r.alias);
+        assert_eq!(alias("Device.name").as_deref(), Some("exact"));
+        assert_eq!(alias("device.info.make").as_deref(), Some("submodule"));
+        assert_eq!(alias("device.model").as_deref(), Some("module"));
+        assert_eq!(alias("devicename").as_deref(), None);
+
+        let rule = get_rule(&engine, "Localization.onLanguageChanged").unwrap();
+        assert_eq!(rule.alias, "event");
+        // captures keep the case of the requested method
+        assert_eq!(rule.variables.get("module"), Some(&json!("Localization")));
+        assert_eq!(rule.variables.get("name"), Some(&json!("Language")));
+        let filter = rule
+            .transform
+            .get_transform_data(RuleTransformType::Event(false))
+            .unwrap();
+        assert_eq!(
+            jq_compile_with_vars(json!({}), &filter, rule.get_variables(), "test".to_owned())
+                .unwrap(),
+            json!({"module": "Localization", "name": "Language"})
+        );
+    }
+
+    #[test]
+    fn test_context_variables() {
+        assert_eq!(
+            replace_app_id_placeholder(
+                r#"{ a: "$context.appId", b: "id-$context.appId", c: $context.appId, d: "\"$context.appId" }"#
+            ),
+            r#"{ a: "\($ctx.app_id)", b: "id-\($ctx.app_id)", c: $ctx.app_id, d: "\"\($ctx.app_id)" }"#
+        );
+
+        let engine = RuleEngine::load_from_string_literal(
+            json!({
+                "endpoints": {},
+                "rules": {
+                    "device.name": {
+                        "alias": "some.method",
+                        "transform": {
+                            "request": "{ namespace: \"$context.appId\", session: $ctx.session_id, rpcv2: $ctx.rpc_v2, locale: $platform.locale }"
+                        }
+                    }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        assert!(engine.compile_filters().is_empty());
+
+        let mut request = RpcRequest::mock();
+        request.method = "device.name".to_owned();
+        request.ctx.app_id = "app1".to_owned();
+        request.ctx.session_id = "session1".to_owned();
+        let mut rule = engine.get_rule(&request).unwrap();
+        assert_eq!(rule.variables.get("platform"), Some(&Value::Null));
+        rule.variables
+            .insert("platform".to_owned(), json!({"locale": "en-US"}));
+        let filter = rule
+            .transform
+            .get_transform_data(RuleTransformType::Request)
+            .unwrap();
+        assert_eq!(
+            jq_compile_with_vars(json!({}), &filter, rule.get_variables(), "test".to_owned())
+                .unwrap(),
+            json!({"namespace": "app1", "session": "session1", "rpcv2": false, "locale": "en-US"})
+        );
+    }
+
+    #[test]
+    fn test_get_rule_pattern() {
+        let engine = RuleEngine::load_from_string_literal(
+            json!({
+                "endpoints": {},
+                "rules": {
+                    "^device\\.(?P<prop>make|model)(?P<suffix>name)?$": { "alias": "pattern" },
+                    "^[": { "alias": "invalid" }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let rule = get_rule(&engine, "device.make").unwrap();
+        assert_eq!(rule.variables.get("prop"), Some(&json!("make")));
+        assert_eq!(rule.variables.get("suffix"), Some(&Value::Null));
+        assert!(get_rule(&engine, "device.sku").is_none());
+        // invalid patterns are rejected when the filters are compiled
+        let errors = engine.compile_filters();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("^[: invalid pattern "));
+    }
 }
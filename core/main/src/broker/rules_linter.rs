@@ -0,0 +1,235 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{collections::HashSet, fs, path::Path};
+
+use super::rules_engine::{RuleEngine, RuleSet};
+use crate::state::openrpc_state::OpenRpcState;
+
+const USAGE: &str =
+    "usage: ripple lint-rules <rules dir> [--openrpc <firebolt open rpc>] [--extn-sdk <open rpc>]...";
+
+/// Offline checks of broker rules files, run with `ripple lint-rules`
+pub struct RulesLinter;
+
+impl RulesLinter {
+    /// Loads all the `.json` rules files of a directory into a single rule engine.
+    /// Files which cannot be read or parsed are returned as findings.
+    pub fn load_dir(dir: &Path) -> Result<(RuleEngine, Vec<String>), String> {
+        let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        let mut paths: Vec<_> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        let mut engine = RuleEngine::default();
+        let mut findings = Vec::new();
+        for path in paths {
+            match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|c| serde_json::from_str::<RuleSet>(&c).map_err(|e| e.to_string()))
+            {
+                Ok(rule_set) => engine.rules.append(rule_set),
+                Err(e) => findings.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        Ok((engine, findings))
+    }
+
+    /// Checks every rule for endpoints which are not declared, methods without an
+    /// open rpc entry and jq filters which do not compile. The open rpc entries are only
+    /// checked when an open rpc state is given.
+    pub fn lint(engine: &RuleEngine, open_rpc_state: Option<&OpenRpcState>) -> Vec<String> {
+        let methods: Option<HashSet<String>> = open_rpc_state.map(|state| {
+            state
+                .get_method_names()
+                .into_iter()
+                .map(|m| m.to_lowercase())
+                .collect()
+        });
+        let mut names: Vec<&String> = engine.rules.rules.keys().collect();
+        names.sort();
+
+        let mut findings = Vec::new();
+        for name in names {
+            let rule = &engine.rules.rules[name];
+            if let Some(endpoint) = &rule.endpoint {
+                if !engine.rules.endpoints.contains_key(endpoint) {
+                    findings.push(format!("{}: unknown endpoint {}", name, endpoint));
+                }
+            }
+            let documented = match &methods {
+                Some(methods) => Self::is_documented(engine, name, methods),
+                None => true,
+            };
+            if !documented {
+                findings.push(format!("{}: no open rpc entry for the method", name));
+            }
+            findings.extend(engine.compile_rule_filters(name, rule));
+        }
+        findings
+    }
+
+    fn is_documented(engine: &RuleEngine, name: &str, methods: &HashSet<String>) -> bool {
+        if name.starts_with('^') {
+            // invalid patterns are reported along with the filters
+            engine
+                .get_pattern(name)
+                .map_or(true, |regex| methods.iter().any(|m| regex.is_match(m)))
+        } else {
+            match name.strip_suffix(".*") {
+                Some(module) => methods
+                    .iter()
+                    .any(|m| m.starts_with(&format!("{}.", module))),
+                None => methods.contains(name),
+            }
+        }
+    }
+
+    /// Runs the linter with the command line arguments following `lint-rules`, the findings
+    /// are printed on stdout. Returns the process exit code.
+    pub fn run(args: &[String]) -> i32 {
+        let mut dir = None;
+        let mut open_rpc = None;
+        let mut extn_sdks = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--openrpc" => open_rpc = args.next().cloned(),
+                "--extn-sdk" => extn_sdks.extend(args.next().cloned()),
+                _ if dir.is_none() && !arg.starts_with("--") => dir = Some(arg.clone()),
+                _ => {
+                    println!("{}", USAGE);
+                    return exitcode::USAGE;
+                }
+            }
+        }
+        let Some(dir) = dir else {
+            println!("{}", USAGE);
+            return exitcode::USAGE;
+        };
+
+        // the open rpc entries are only checked against a firebolt open rpc given explicitly
+        let open_rpc_state = match open_rpc {
+            Some(path) => match fs::read_to_string(&path) {
+                Ok(content) => Some(OpenRpcState::new_with_open_rpc(
+                    &content,
+                    None,
+                    extn_sdks,
+                    Vec::new(),
+                )),
+                Err(e) => {
+                    println!("{}: {}", path, e);
+                    return exitcode::NOINPUT;
+                }
+            },
+            None => None,
+        };
+
+        let (engine, mut findings) = match Self::load_dir(Path::new(&dir)) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                println!("{}", e);
+                return exitcode::NOINPUT;
+            }
+        };
+        findings.extend(Self::lint(&engine, open_rpc_state.as_ref()));
+        for finding in findings.iter() {
+            println!("{}", finding);
+        }
+        println!(
+            "{} rules checked, {} problems found",
+            engine.rules.rules.len(),
+            findings.len()
+        );
+        if findings.is_empty() {
+            exitcode::OK
+        } else {
+            exitcode::DATAERR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::serde_json::json;
+
+    #[test]
+    fn test_lint() {
+        let engine = RuleEngine::load_from_string_literal(
+            json!({
+                "endpoints": {
+                    "thunder": { "protocol": "thunder", "url": "ws://127.0.0.1:9998/jsonrpc" }
+                },
+                "rules": {
+                    "device.name": { "alias": "org.rdk.System.getFriendlyName" },
+                    "localization.*": { "alias": "org.rdk.Localization", "endpoint": "thunder" },
+                    "device.unknownMethod": { "alias": "some.method" },
+                    "^device\\.(?P<prop>make|model)$": {
+                        "alias": "some.method",
+                        "transform": { "response": "{ ($prop): .result }" }
+                    },
+                    "^device\\.unknown(": { "alias": "some.method" },
+                    "device.model": {
+                        "alias": "some.method",
+                        "endpoint": "cloud",
+                        "transform": { "response": ".result |" }
+                    }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let open_rpc_state = OpenRpcState::new(None, Vec::new(), Vec::new());
+        let findings = RulesLinter::lint(&engine, Some(&open_rpc_state));
+        assert_eq!(findings.len(), 4);
+        assert!(findings[0].starts_with("^device\\.unknown(: invalid pattern "));
+        assert_eq!(findings[1], "device.model: unknown endpoint cloud");
+        assert!(findings[2].starts_with("device.model.transform.response: "));
+        assert_eq!(
+            findings[3],
+            "device.unknownmethod: no open rpc entry for the method"
+        );
+
+        // without an open rpc only the rules themselves are checked
+        let findings = RulesLinter::lint(&engine, None);
+        assert_eq!(findings.len(), 3);
+        assert_eq!(findings[1], "device.model: unknown endpoint cloud");
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let dir = std::env::temp_dir().join(format!("rules_linter_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("valid.json"),
+            json!({"endpoints": {}, "rules": {"device.name": {"alias": "a.b"}}}).to_string(),
+        )
+        .unwrap();
+        fs::write(dir.join("invalid.json"), "{ \"rules\": ").unwrap();
+        fs::write(dir.join("notes.txt"), "not a rules file").unwrap();
+
+        let (engine, findings) = RulesLinter::load_dir(&dir).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert!(engine.rules.rules.contains_key("device.name"));
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].contains("invalid.json"));
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+use futures_util::{SinkExt, StreamExt};
+use ripple_sdk::tokio::{
+    self,
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc,
+};
+use serde_json::{json, Value};
+use tokio_native_tls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{Request, Response},
+    http::header::AUTHORIZATION,
+    Message,
+};
+
+/// Self signed certificate for `localhost` and `127.0.0.1` used by the mock servers
+pub const TEST_CA_FILE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/src/broker/test/certs/localhost.crt"
+);
+const TEST_CERT: &[u8] = include_bytes!("certs/localhost.crt");
+const TEST_KEY: &[u8] = include_bytes!("certs/localhost.key");
+
+fn get_acceptor() -> TlsAcceptor {
+    let identity = native_tls::Identity::from_pkcs8(TEST_CERT, TEST_KEY).unwrap();
+    TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap())
+}
+
+pub struct MockTlsServer;
+
+impl MockTlsServer {
+    /// Starts a https server which answers every request with the given json body.
+    /// The head of every received request is sent back on the returned receiver.
+    pub async fn start_https(response: Value) -> (u16, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let acceptor = get_acceptor();
+        let (tx, rx) = mpsc::channel(10);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    continue;
+                };
+                let mut buffer = vec![0; 8192];
+                let mut read = 0;
+                while !String::from_utf8_lossy(&buffer[..read]).contains("\r\n\r\n") {
+                    match stream.read(&mut buffer[read..]).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => read += n,
+                    }
+                }
+                let _ = tx
+                    .send(String::from_utf8_lossy(&buffer[..read]).to_string())
+                    .await;
+                let body = response.to_string();
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(reply.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+        (port, rx)
+    }
+
+    /// Starts a wss server which answers every json rpc request with a `true` result.
+    /// The `Authorization` header of each handshake is sent back on the returned receiver.
+    pub async fn start_wss() -> (u16, mpsc::Receiver<Option<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let acceptor = get_acceptor();
+        let (tx, rx) = mpsc::channel(10);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(stream) = acceptor.accept(stream).await else {
+                    continue;
+                };
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    let mut auth = None;
+                    let callback = |request: &Request, response: Response| {
+                        auth = request
+                            .headers()
+                            .get(AUTHORIZATION)
+                            .and_then(|v| v.to_str().ok())
+                            .map(|v| v.to_owned());
+                        Ok(response)
+                    };
+                    let Ok(ws_stream) = tokio_tungstenite::accept_hdr_async(stream, callback).await
+                    else {
+                        return;
+                    };
+                    let _ = tx.send(auth).await;
+                    let (mut ws_tx, mut ws_rx) = ws_stream.split();
+                    while let Some(Ok(Message::Text(t))) = ws_rx.next().await {
+                        let request: Value = serde_json::from_str(&t).unwrap_or_default();
+                        let response =
+                            json!({"jsonrpc": "2.0", "id": request["id"], "result": true});
+                        let _ = ws_tx.send(Message::Text(response.to_string())).await;
+                    }
+                });
+            }
+        });
+        (port, rx)
+    }
+}
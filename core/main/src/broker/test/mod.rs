@@ -16,3 +16,5 @@
 //
 #[cfg(test)]
 pub mod mock_thunder_lite_server;
+#[cfg(test)]
+pub mod mock_tls_server;
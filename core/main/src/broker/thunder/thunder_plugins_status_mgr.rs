@@ -228,6 +228,15 @@ impl StatusManager {
         status.get(&plugin_name).cloned()
     }
 
+    /// Returns the last known state of each plugin
+    pub fn get_plugin_states(&self) -> HashMap<String, State> {
+        let status = self.status.read().unwrap();
+        status
+            .iter()
+            .map(|(plugin_name, plugin_state)| (plugin_name.clone(), plugin_state.state.clone()))
+            .collect()
+    }
+
     pub fn generate_plugin_activation_request(&self, plugin_name: String) -> String {
         let id = EndpointBrokerState::get_next_id();
         let controller_call_sign = Self::get_controller_call_sign();
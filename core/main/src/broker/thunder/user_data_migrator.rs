@@ -472,9 +472,10 @@ impl UserDataMigrator {
             .transform
             .get_transform_data(RuleTransformType::Request)
         {
-            return crate::broker::rules_engine::jq_compile(
+            return rule.filters.run(
                 data,
                 &filter,
+                rule.get_variables(),
                 format!("{}_request", method),
             );
         }
@@ -751,7 +752,13 @@ impl UserDataMigrator {
             .transform
             .get_transform_data(RuleTransformType::Response)
         {
-            endpoint_broker::apply_response(filter, &request.rule.alias, &mut response);
+            endpoint_broker::apply_response_with_vars(
+                filter,
+                &request.rule.alias,
+                request.rule.get_variables(),
+                &request.rule.filters,
+                &mut response,
+            );
         }
 
         if let Some(result) = response.result {
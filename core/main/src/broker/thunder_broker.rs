@@ -27,7 +27,10 @@ use futures_util::{SinkExt, StreamExt};
 use ripple_sdk::{
     api::{
         gateway::rpc_gateway_api::{JsonRpcApiResponse, RpcRequest},
-        observability::log_signal::LogSignal,
+        observability::{
+            log_signal::LogSignal,
+            recording::{RecordEntry, RecordKind},
+        },
     },
     log::{debug, error, info, trace},
     tokio::{
@@ -102,6 +105,10 @@ impl ThunderBroker {
         self
     }
 
+    pub fn get_status_manager(&self) -> StatusManager {
+        self.status_manager.clone()
+    }
+
     pub fn get_default_callback(&self) -> BrokerCallback {
         self.default_callback.clone()
     }
@@ -205,6 +212,7 @@ impl ThunderBroker {
         let broker_c = thunder_broker.clone();
         let broker_for_cleanup = thunder_broker.clone();
         let broker_for_reconnect = thunder_broker.clone();
+        let recording_state = request.recording_state.clone();
         tokio::spawn(async move {
             let (ws_tx, mut ws_rx) = BrokerUtils::get_ws_broker(&endpoint.get_url(), None).await;
 
@@ -215,6 +223,8 @@ impl ThunderBroker {
                 .generate_state_change_subscribe_request();
             {
                 let mut ws_tx = ws_tx_wrap.lock().await;
+                recording_state
+                    .record(|| RecordEntry::from_text(RecordKind::ThunderRequest, &status_request));
 
                 let _feed = ws_tx
                     .feed(tokio_tungstenite::tungstenite::Message::Text(
@@ -237,6 +247,7 @@ impl ThunderBroker {
 
                                 if let tokio_tungstenite::tungstenite::Message::Text(t) = v {
                                     debug!("Broker Websocket message {:?}", t);
+                                    recording_state.record(|| RecordEntry::from_text(RecordKind::ThunderResponse, &t));
 
                                     if broker_c.status_manager.is_controller_response(broker_c.get_sender(), broker_c.get_default_callback(), t.as_bytes()).await {
                                         broker_c.status_manager.handle_controller_response(broker_c.get_sender(), broker_c.get_default_callback(), t.as_bytes()).await;
@@ -266,6 +277,7 @@ impl ThunderBroker {
                                 if !requests.is_empty() {
                                     let mut ws_tx = ws_tx_wrap.lock().await;
                                     for r in requests {
+                                        recording_state.record(|| RecordEntry::from_text(RecordKind::ThunderRequest, &r));
                                         let _feed = ws_tx.feed(tokio_tungstenite::tungstenite::Message::Text(r)).await;
                                         let _flush = ws_tx.flush().await;
                                     }
@@ -304,6 +316,7 @@ impl ThunderBroker {
                                                 let binding = ws_tx_wrap.clone();
                                                 let mut ws_tx = binding.lock().await;
                                                 for r in updated_request {
+                                                    recording_state.record(|| RecordEntry::from_text(RecordKind::ThunderRequest, &r).with_request_id(&request.rpc.ctx.request_id));
                                                     let _ = ws_tx.feed(tokio_tungstenite::tungstenite::Message::Text(r)).await;
 
                                                     let _ = ws_tx.flush().await;
@@ -676,6 +689,10 @@ mod tests {
                 protocol: RuleEndpointProtocol::Thunder,
                 url: $server_handle.get_address(),
                 jsonrpc: true,
+                auth: None,
+                tls: None,
+                timeout_ms: None,
+                circuit_breaker: None,
             };
             let (reconnect_tx, _rec_rx) = mpsc::channel(2);
 
@@ -760,6 +777,10 @@ mod tests {
             url: format!("ws://127.0.0.1:{}", port),
             protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
             jsonrpc: false,
+            auth: None,
+            tls: None,
+            timeout_ms: None,
+            circuit_breaker: None,
         };
         let (tx, _) = mpsc::channel(1);
         let request = BrokerConnectRequest::new("somekey".to_owned(), endpoint, tx);
@@ -786,6 +807,12 @@ mod tests {
                 filter: event_filter,
                 event_handler: event_handler_fn,
                 sources: None,
+                http: None,
+                workflow: None,
+                cache: None,
+                timeout_ms: None,
+                variables: Default::default(),
+                filters: Default::default(),
             },
             subscription_processed: None,
             workflow_callback: None,
@@ -1013,6 +1040,12 @@ mod tests {
                     filter: None,
                     event_handler: None,
                     sources: None,
+                    http: None,
+                    workflow: None,
+                    cache: None,
+                    timeout_ms: None,
+                    variables: Default::default(),
+                    filters: Default::default(),
                 },
                 subscription_processed: Some(false),
                 workflow_callback: None,
@@ -1070,6 +1103,12 @@ mod tests {
                     filter: None,
                     event_handler: None,
                     sources: None,
+                    http: None,
+                    workflow: None,
+                    cache: None,
+                    timeout_ms: None,
+                    variables: Default::default(),
+                    filters: Default::default(),
                 },
                 subscription_processed: Some(true),
                 workflow_callback: None,
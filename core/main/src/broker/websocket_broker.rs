@@ -21,11 +21,12 @@ use super::endpoint_broker::{
     BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerOutputForwarder, BrokerRequest,
     BrokerSender, EndpointBroker,
 };
+use super::rules_engine::RuleEndpoint;
 use crate::broker::endpoint_broker::EndpointBrokerState;
 use crate::state::platform_state::PlatformState;
 use futures_util::{SinkExt, StreamExt};
 use ripple_sdk::{
-    api::observability::log_signal::LogSignal,
+    api::{observability::log_signal::LogSignal, session::AccountSession},
     log::{debug, error},
     tokio::{self, sync::mpsc},
 };
@@ -39,8 +40,13 @@ pub struct WebsocketBroker {
 }
 
 impl WebsocketBroker {
-    fn start(request: BrokerConnectRequest, callback: BrokerCallback) -> Self {
+    fn start(
+        ps: Option<PlatformState>,
+        request: BrokerConnectRequest,
+        callback: BrokerCallback,
+    ) -> Self {
         let endpoint = request.endpoint.clone();
+        let session = request.session.clone();
         let (tx, mut tr) = mpsc::channel(10);
         let (cleaner_tx, mut cleaner_tr) = mpsc::channel::<String>(1);
         let non_json_rpc_map: Arc<RwLock<HashMap<String, Vec<mpsc::Sender<String>>>>> =
@@ -49,8 +55,24 @@ impl WebsocketBroker {
         let broker = BrokerSender { sender: tx };
         tokio::spawn(async move {
             if endpoint.jsonrpc {
-                let (mut ws_tx, mut ws_rx) =
-                    BrokerUtils::get_ws_broker(&endpoint.get_url(), None).await;
+                let (mut ws_tx, mut ws_rx) = match BrokerUtils::get_endpoint_ws_broker(
+                    ps.as_ref(),
+                    &endpoint,
+                    session.as_ref(),
+                    None,
+                )
+                .await
+                {
+                    Ok(ws) => ws,
+                    Err(e) => {
+                        error!(
+                            "Websocket broker for {} not started {:?}",
+                            endpoint.get_url(),
+                            e
+                        );
+                        return false;
+                    }
+                };
 
                 tokio::pin! {
                     let read = ws_rx.next();
@@ -77,7 +99,14 @@ impl WebsocketBroker {
                             }
 
                         },
-                        Some(request) = tr.recv() => {
+                        request = tr.recv() => {
+                            let Some(request) = request else {
+                                // all the senders are dropped when the endpoint is removed
+                                debug!("Closing websocket broker for {}", endpoint.get_url());
+                                let _feed = ws_tx.feed(tokio_tungstenite::tungstenite::Message::Close(None)).await;
+                                let _flush = ws_tx.flush().await;
+                                break true
+                            };
                             LogSignal::new(
                                 "websocket_broker".to_string(),
                                 format!("Got request from receiver for broker: {:?}", request),
@@ -121,7 +150,9 @@ impl WebsocketBroker {
                     let cleaner = WSNotificationBroker::start(
                         v.clone(),
                         callback.clone(),
-                        endpoint.get_url().clone(),
+                        endpoint.clone(),
+                        ps.clone(),
+                        session.clone(),
                     );
                     {
                         let mut map = map_clone.write().unwrap();
@@ -131,6 +162,14 @@ impl WebsocketBroker {
                     }
                 }
 
+                // close the notification connections once the endpoint is removed
+                let listeners: Vec<(String, Vec<mpsc::Sender<String>>)> =
+                    { map_clone.write().unwrap().drain().collect() };
+                for (id, senders) in listeners {
+                    for sender in senders {
+                        let _ = sender.try_send(id.clone());
+                    }
+                }
                 true
             }
         });
@@ -150,14 +189,34 @@ impl WSNotificationBroker {
     fn start(
         request_c: BrokerRequest,
         callback_c: BrokerCallback,
-        url: String,
+        endpoint: RuleEndpoint,
+        ps: Option<PlatformState>,
+        session: Option<AccountSession>,
     ) -> mpsc::Sender<String> {
         let (tx, mut tr) = mpsc::channel::<String>(1);
         tokio::spawn(async move {
             let app_id = request_c.get_id();
             let alias = request_c.rule.alias.clone();
-            let (mut ws_tx, mut ws_rx) =
-                BrokerUtils::get_ws_broker(&url, Some(alias.clone())).await;
+            let (mut ws_tx, mut ws_rx) = match BrokerUtils::get_endpoint_ws_broker(
+                ps.as_ref(),
+                &endpoint,
+                session.as_ref(),
+                Some(alias.clone()),
+            )
+            .await
+            {
+                Ok(ws) => ws,
+                Err(e) => {
+                    LogSignal::new(
+                        "websocket_broker".to_string(),
+                        "notification broker not started".to_string(),
+                        request_c.rpc.ctx.clone(),
+                    )
+                    .with_diagnostic_context_item("error", &format!("{:?}", e))
+                    .emit_error();
+                    return;
+                }
+            };
 
             tokio::pin! {
                 let read = ws_rx.next();
@@ -207,12 +266,12 @@ impl WSNotificationBroker {
 
 impl EndpointBroker for WebsocketBroker {
     fn get_broker(
-        _ps: Option<PlatformState>,
+        ps: Option<PlatformState>,
         request: BrokerConnectRequest,
         callback: BrokerCallback,
         _broker_state: &mut EndpointBrokerState,
     ) -> Self {
-        Self::start(request, callback)
+        Self::start(ps, request, callback)
     }
 
     fn get_sender(&self) -> BrokerSender {
@@ -231,7 +290,8 @@ mod tests {
     use crate::{
         broker::{
             endpoint_broker::{BrokerOutput, BrokerRequest},
-            rules_engine::{Rule, RuleEndpoint, RuleTransform},
+            rules_engine::{Rule, RuleEndpoint, RuleEndpointAuth, RuleEndpointTls, RuleTransform},
+            test::mock_tls_server::{MockTlsServer, TEST_CA_FILE},
         },
         utils::test_utils::{MockWebsocket, WSMockData},
     };
@@ -253,12 +313,16 @@ mod tests {
             url: format!("ws://127.0.0.1:{}", port),
             protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
             jsonrpc: false,
+            auth: None,
+            tls: None,
+            timeout_ms: None,
+            circuit_breaker: None,
         };
         let (tx, _) = mpsc::channel(1);
         let request = BrokerConnectRequest::new("somekey".to_owned(), endpoint, tx);
         let callback = BrokerCallback { sender };
         // Setup websocket broker
-        WebsocketBroker::start(request, callback)
+        WebsocketBroker::start(None, request, callback)
     }
 
     #[tokio::test]
@@ -278,6 +342,12 @@ mod tests {
                 filter: None,
                 event_handler: None,
                 sources: None,
+                http: None,
+                workflow: None,
+                cache: None,
+                timeout_ms: None,
+                variables: Default::default(),
+                filters: Default::default(),
             },
             workflow_callback: None,
             subscription_processed: None,
@@ -325,6 +395,12 @@ mod tests {
                 filter: None,
                 event_handler: None,
                 sources: None,
+                http: None,
+                workflow: None,
+                cache: None,
+                timeout_ms: None,
+                variables: Default::default(),
+                filters: Default::default(),
             },
             workflow_callback: None,
             subscription_processed: None,
@@ -355,6 +431,12 @@ mod tests {
                 filter: None,
                 event_handler: None,
                 sources: None,
+                http: None,
+                workflow: None,
+                cache: None,
+                timeout_ms: None,
+                variables: Default::default(),
+                filters: Default::default(),
             },
             workflow_callback: None,
             subscription_processed: None,
@@ -382,6 +464,10 @@ mod tests {
             url: format!("ws://127.0.0.1:{}", port),
             protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
             jsonrpc: false,
+            auth: None,
+            tls: None,
+            timeout_ms: None,
+            circuit_breaker: None,
         };
 
         let request = BrokerRequest {
@@ -393,12 +479,18 @@ mod tests {
                 filter: None,
                 event_handler: None,
                 sources: None,
+                http: None,
+                workflow: None,
+                cache: None,
+                timeout_ms: None,
+                variables: Default::default(),
+                filters: Default::default(),
             },
             workflow_callback: None,
             subscription_processed: None,
             telemetry_response_listeners: vec![],
         };
-        WSNotificationBroker::start(request, callback, endpoint.get_url().clone())
+        WSNotificationBroker::start(request, callback, endpoint, None, None)
     }
 
     #[tokio::test]
@@ -471,6 +563,12 @@ mod tests {
                 filter: None,
                 event_handler: None,
                 sources: None,
+                http: None,
+                workflow: None,
+                cache: None,
+                timeout_ms: None,
+                variables: Default::default(),
+                filters: Default::default(),
             },
             workflow_callback: None,
             subscription_processed: None,
@@ -481,10 +579,60 @@ mod tests {
             url: format!("ws://127.0.0.1:{}", port),
             protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
             jsonrpc: false,
+            auth: None,
+            tls: None,
+            timeout_ms: None,
+            circuit_breaker: None,
         };
-        let sender = WSNotificationBroker::start(request, callback, endpoint.get_url().clone());
+        let sender = WSNotificationBroker::start(request, callback, endpoint, None, None);
         sender.send("test".to_owned()).await.unwrap();
         let v = tokio::time::timeout(Duration::from_secs(2), rec.recv()).await;
         assert!(v.is_err());
     }
+
+    #[tokio::test]
+    async fn test_wss_broker_with_bearer_auth() {
+        let (port, mut handshakes) = MockTlsServer::start_wss().await;
+        let endpoint = RuleEndpoint {
+            url: format!("wss://localhost:{}", port),
+            protocol: crate::broker::rules_engine::RuleEndpointProtocol::Websocket,
+            jsonrpc: true,
+            auth: Some(RuleEndpointAuth::Bearer {
+                token: "secret".to_owned(),
+            }),
+            tls: Some(RuleEndpointTls {
+                ca_file: Some(TEST_CA_FILE.to_owned()),
+            }),
+            timeout_ms: None,
+            circuit_breaker: None,
+        };
+        let (tx, _) = mpsc::channel(1);
+        let request = BrokerConnectRequest::new("somekey".to_owned(), endpoint, tx);
+        let (sender, mut rec) = mpsc::channel(10);
+        let broker = WebsocketBroker::start(None, request, BrokerCallback { sender });
+
+        let mut rpc = RpcRequest::get_new_internal("some_method".to_owned(), None);
+        rpc.ctx.call_id = 7;
+        let request = BrokerRequest {
+            rpc,
+            rule: Rule {
+                alias: "module.method".to_owned(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        broker.sender.send(request).await.unwrap();
+
+        let auth = tokio::time::timeout(Duration::from_secs(5), handshakes.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(auth, Some("Bearer secret".to_owned()));
+        let output = tokio::time::timeout(Duration::from_secs(5), rec.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(output.data.id, Some(7));
+        assert_eq!(output.get_result(), Some(json!(true)));
+    }
 }
@@ -2,19 +2,19 @@ use super::endpoint_broker::{
     BrokerCallback, BrokerCleaner, BrokerConnectRequest, BrokerRequest, BrokerSender,
     EndpointBroker,
 };
-use super::rules_engine::JsonDataSource;
+use super::rules_engine::{JsonDataSource, RuleWorkflow, WorkflowStep};
 use crate::broker::endpoint_broker::{BrokerOutput, EndpointBrokerState};
-use crate::broker::rules_engine::{compose_json_values, make_name_json_safe};
+use crate::broker::rules_engine::{compose_json_values, make_name_json_safe, JqFilterCache};
 use crate::state::platform_state::PlatformState;
 use futures::future::{join_all, BoxFuture};
 use futures::FutureExt;
-use serde_json::json;
+use serde_json::{json, Map, Value};
 
 use ripple_sdk::api::gateway::rpc_gateway_api::{JsonRpcApiError, JsonRpcApiResponse, RpcRequest};
 use ripple_sdk::utils::error::RippleError;
 use ripple_sdk::{
     api::observability::log_signal::LogSignal,
-    log::{error, trace},
+    log::{debug, error, trace},
     tokio::{self, sync::mpsc},
 };
 pub struct WorkflowBroker {
@@ -29,11 +29,10 @@ pub enum SubBrokerErr {
 }
 pub type SubBrokerResult = Result<JsonRpcApiResponse, SubBrokerErr>;
 
-async fn subbroker_call(
+async fn brokered_call(
     endpoint_broker: EndpointBrokerState,
     rpc_request: RpcRequest,
-    source: JsonDataSource,
-) -> Result<serde_json::Value, SubBrokerErr> {
+) -> Result<Option<Value>, SubBrokerErr> {
     let (brokered_tx, mut brokered_rx) = mpsc::channel::<BrokerOutput>(10);
     endpoint_broker.handle_brokerage(
         rpc_request,
@@ -53,12 +52,7 @@ async fn subbroker_call(
                     msg.get_error_string(),
                 )))
             } else {
-                Ok(json!({make_name_json_safe(
-                    &source
-                        .clone()
-                        .namespace
-                        .unwrap_or(source.method.to_string()),
-                ): msg.data.result.unwrap_or(json!({}))}))
+                Ok(msg.data.result)
             }
         }
         None => {
@@ -70,6 +64,22 @@ async fn subbroker_call(
     }
 }
 
+async fn subbroker_call(
+    endpoint_broker: EndpointBrokerState,
+    rpc_request: RpcRequest,
+    source: JsonDataSource,
+) -> Result<serde_json::Value, SubBrokerErr> {
+    let result = brokered_call(endpoint_broker, rpc_request)
+        .await?
+        .unwrap_or(json!({}));
+    Ok(json!({make_name_json_safe(
+        &source
+            .clone()
+            .namespace
+            .unwrap_or(source.method.to_string()),
+    ): result}))
+}
+
 impl WorkflowBroker {
     pub fn create_the_futures(
         sources: Vec<JsonDataSource>,
@@ -124,10 +134,151 @@ impl WorkflowBroker {
         futures
     }
 
+    /// Builds the request for a workflow step. The `params` filter of the step is applied to the
+    /// results of the earlier stages with the workflow `variables` bound, without a filter the
+    /// params of the original request are used.
+    fn get_step_request(
+        step: &WorkflowStep,
+        rpc_request: &RpcRequest,
+        params: &Value,
+        variables: &[(String, Value)],
+        filters: &JqFilterCache,
+        results: &Value,
+    ) -> Result<RpcRequest, RippleError> {
+        let step_params = match &step.params {
+            Some(filter) => filters.run(
+                results.clone(),
+                filter,
+                variables.to_vec(),
+                format!("{}_workflow_params", step.method),
+            )?,
+            None => params.clone(),
+        };
+        let mut request = rpc_request.clone();
+        request.method = step.method.clone();
+        request.params_json = RpcRequest::prepend_ctx(
+            (!step_params.is_null()).then_some(step_params),
+            &rpc_request.ctx,
+        );
+        Ok(request)
+    }
+
+    /// Runs a single step applying its retry, fallback and optional policies.
+    /// Returns `None` for an optional step which failed.
+    async fn run_step(
+        step: &WorkflowStep,
+        rpc_request: &RpcRequest,
+        params: &Value,
+        variables: &[(String, Value)],
+        filters: &JqFilterCache,
+        results: &Value,
+        endpoint_broker: EndpointBrokerState,
+    ) -> Result<Option<Value>, SubBrokerErr> {
+        let mut result = Err(SubBrokerErr::RpcError(RippleError::NoResponse));
+        for attempt in 0..=step.retry {
+            if attempt > 0 {
+                // give a failing endpoint some time before it is called again
+                tokio::time::sleep(step.get_retry_delay(attempt)).await;
+            }
+            result = match Self::get_step_request(
+                step,
+                rpc_request,
+                params,
+                variables,
+                filters,
+                results,
+            ) {
+                Ok(request) => brokered_call(endpoint_broker.clone(), request)
+                    .await
+                    .map(|v| v.unwrap_or(Value::Null)),
+                Err(e) => Err(SubBrokerErr::RpcError(e)),
+            };
+            if result.is_ok() {
+                break;
+            }
+            debug!(
+                "workflow step {} failed on attempt {} {:?}",
+                step.method, attempt, result
+            );
+        }
+        match result {
+            Ok(v) => Ok(Some(v)),
+            Err(_) if step.fallback.is_some() => Ok(step.fallback.clone()),
+            Err(_) if step.optional => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Runs the stages of a workflow in order, the steps of a stage run in parallel and
+    /// each step can use the results of all the earlier stages.
+    pub async fn run_staged_workflow(
+        broker_request: &BrokerRequest,
+        workflow: &RuleWorkflow,
+        endpoint_broker: EndpointBrokerState,
+    ) -> SubBrokerResult {
+        let params = broker_request.get_params();
+        let variables = broker_request.rule.get_workflow_variables(&params);
+        let mut results = Map::new();
+        for stage in workflow.stages.iter() {
+            let input = Value::Object(results.clone());
+            let stage_results = join_all(stage.steps.iter().map(|step| {
+                Self::run_step(
+                    step,
+                    &broker_request.rpc,
+                    &params,
+                    &variables,
+                    &broker_request.rule.filters,
+                    &input,
+                    endpoint_broker.clone(),
+                )
+            }))
+            .await;
+            for (step, result) in stage.steps.iter().zip(stage_results) {
+                match result {
+                    Ok(Some(value)) => {
+                        results.insert(step.get_namespace(), value);
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        return Err(SubBrokerErr::JsonRpcApiError(
+                            JsonRpcApiError::default()
+                                .with_code(-32001)
+                                .with_message(format!(
+                                    "workflow step {} failed {:?}: for api {}",
+                                    step.method, e, broker_request.rpc.method
+                                ))
+                                .with_id(broker_request.rpc.ctx.call_id),
+                        ));
+                    }
+                }
+            }
+        }
+
+        let results = Value::Object(results);
+        let result = match &workflow.compose {
+            Some(filter) => broker_request
+                .rule
+                .filters
+                .run(
+                    results,
+                    filter,
+                    variables,
+                    format!("{}_workflow_compose", broker_request.rpc.method),
+                )
+                .map_err(SubBrokerErr::RpcError)?,
+            None => results,
+        };
+        let composed: JsonRpcApiResponse = broker_request.clone().into();
+        Ok(composed.with_result(Some(result)))
+    }
+
     pub async fn run_workflow(
         broker_request: &BrokerRequest,
         endpoint_broker: EndpointBrokerState,
     ) -> SubBrokerResult {
+        if let Some(workflow) = &broker_request.rule.workflow {
+            return Self::run_staged_workflow(broker_request, workflow, endpoint_broker).await;
+        }
         let mut futures = Self::create_the_futures(
             broker_request.rule.sources.clone().unwrap_or_default(),
             broker_request.rpc.clone(),
@@ -268,12 +419,30 @@ write exhaustive tests for the WorkflowBroker
 #[cfg(test)]
 pub mod tests {
 
-    use ripple_sdk::{api::gateway::rpc_gateway_api::RpcRequest, tokio, Mockable};
-    use serde_json::json;
+    use ripple_sdk::{
+        api::gateway::rpc_gateway_api::RpcRequest,
+        tokio::{self, sync::mpsc},
+        Mockable,
+    };
+    use ripple_tdk::utils::test_utils::Mockable as TdkMockable;
+    use serde_json::{json, Value};
 
-    use crate::broker::{
-        endpoint_broker::{BrokerCallback, BrokerRequest, EndpointBrokerState},
-        rules_engine::{JsonDataSource, Rule, RuleEngine},
+    use crate::{
+        broker::{
+            endpoint_broker::{
+                BrokerCallback, BrokerOutputForwarder, BrokerRequest, EndpointBrokerState,
+            },
+            rules_engine::{
+                JqFilterCache, JsonDataSource, Rule, RuleEngine, RuleWorkflow, WorkflowStage,
+                WorkflowStep,
+            },
+            workflow_broker::{SubBrokerErr, WorkflowBroker},
+        },
+        service::extn::ripple_client::RippleClient,
+        state::{
+            bootstrap_state::ChannelsState, metrics_state::MetricsState,
+            platform_state::PlatformState,
+        },
     };
     pub fn broker_request(callback: BrokerCallback) -> BrokerRequest {
         let mut rule = Rule {
@@ -346,4 +515,171 @@ pub mod tests {
         let foo = foo.await;
         assert!(foo.is_ok());
     }
+
+    fn staged_rule_engine() -> RuleEngine {
+        RuleEngine::load_from_string_literal(
+            json!({
+                "endpoints": {},
+                "rules": {
+                    "device.make": {
+                        "alias": "static",
+                        "transform": { "response": "\"Arris\"" }
+                    },
+                    "device.model": {
+                        "alias": "static",
+                        "transform": { "response": "\"XG1\"" }
+                    }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap()
+    }
+
+    /// Endpoint broker with a running forwarder, so that brokered responses reach the workflow
+    fn started_endpoint_broker_state() -> EndpointBrokerState {
+        let (tx, rx) = mpsc::channel(10);
+        let state = EndpointBrokerState::new(
+            MetricsState::default(),
+            tx,
+            staged_rule_engine(),
+            RippleClient::new(ChannelsState::new()),
+        );
+        let mut platform_state = PlatformState::mock();
+        platform_state.endpoint_state = state.clone();
+        BrokerOutputForwarder::start_forwarder(platform_state, rx);
+        state
+    }
+
+    fn staged_broker_request(workflow: RuleWorkflow, params: Value) -> BrokerRequest {
+        let mut rpc = RpcRequest::mock();
+        rpc.params_json = RpcRequest::prepend_ctx(Some(params), &rpc.ctx);
+        BrokerRequest {
+            rpc,
+            rule: Rule {
+                alias: "workflow".to_string(),
+                workflow: Some(workflow),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn step(method: &str) -> WorkflowStep {
+        WorkflowStep {
+            method: method.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    pub async fn test_run_staged_workflow() {
+        let workflow = RuleWorkflow {
+            stages: vec![
+                WorkflowStage {
+                    steps: vec![step("device.make"), step("device.model")],
+                },
+                WorkflowStage {
+                    steps: vec![
+                        WorkflowStep {
+                            optional: true,
+                            ..step("device.unknown")
+                        },
+                        WorkflowStep {
+                            namespace: Some("name".to_string()),
+                            fallback: Some(json!("Living Room")),
+                            retry: 1,
+                            ..step("device.name")
+                        },
+                    ],
+                },
+            ],
+            compose: Some(
+                "{ make: .device_make, model: .device_model, name: .name, unknown: .device_unknown, app: $params.app }"
+                    .to_string(),
+            ),
+        };
+        let request = staged_broker_request(workflow, json!({"app": "someApp"}));
+        let response =
+            WorkflowBroker::run_workflow(&request, started_endpoint_broker_state()).await;
+        assert_eq!(
+            response.unwrap().result.unwrap(),
+            json!({
+                "make": "Arris",
+                "model": "XG1",
+                "name": "Living Room",
+                "unknown": null,
+                "app": "someApp"
+            })
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_run_staged_workflow_without_compose() {
+        let workflow = RuleWorkflow {
+            stages: vec![WorkflowStage {
+                steps: vec![step("device.make")],
+            }],
+            compose: None,
+        };
+        let request = staged_broker_request(workflow, json!({}));
+        let response =
+            WorkflowBroker::run_workflow(&request, started_endpoint_broker_state()).await;
+        assert_eq!(
+            response.unwrap().result.unwrap(),
+            json!({"device_make": "Arris"})
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_run_staged_workflow_required_step_fails() {
+        let workflow = RuleWorkflow {
+            stages: vec![
+                WorkflowStage {
+                    steps: vec![step("device.unknown")],
+                },
+                WorkflowStage {
+                    steps: vec![step("device.make")],
+                },
+            ],
+            compose: None,
+        };
+        let request = staged_broker_request(workflow, json!({}));
+        let response =
+            WorkflowBroker::run_workflow(&request, started_endpoint_broker_state()).await;
+        assert!(matches!(response, Err(SubBrokerErr::JsonRpcApiError(_))));
+    }
+
+    #[test]
+    pub fn test_get_step_request() {
+        let model = WorkflowStep {
+            params: Some("{ make: .device_make, lang: $params.lang }".to_string()),
+            ..step("device.model")
+        };
+        let rpc = RpcRequest::mock();
+        let request = WorkflowBroker::get_step_request(
+            &model,
+            &rpc,
+            &json!({"lang": "en"}),
+            &[("params".to_owned(), json!({"lang": "en"}))],
+            &JqFilterCache::default(),
+            &json!({"device_make": "Arris"}),
+        )
+        .unwrap();
+        assert_eq!(request.method, "device.model");
+        let params: Vec<Value> = serde_json::from_str(&request.params_json).unwrap();
+        assert_eq!(params[1], json!({"make": "Arris", "lang": "en"}));
+
+        let request = WorkflowBroker::get_step_request(
+            &step("device.make"),
+            &rpc,
+            &Value::Null,
+            &[],
+            &JqFilterCache::default(),
+            &json!({}),
+        )
+        .unwrap();
+        let params: Vec<Value> = serde_json::from_str(&request.params_json).unwrap();
+        assert_eq!(params.len(), 1);
+    }
 }
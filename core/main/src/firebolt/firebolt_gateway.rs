@@ -21,7 +21,9 @@ use jsonrpsee::{core::server::rpc_module::Methods, types::TwoPointZero};
 use ripple_sdk::{
     api::{
         firebolt::{
-            fb_capabilities::JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
+            fb_capabilities::{
+                JSON_RPC_ERROR_RATE_LIMITED, JSON_RPC_STANDARD_ERROR_INVALID_PARAMS,
+            },
             fb_openrpc::FireboltOpenRpcMethod,
         },
         gateway::{
@@ -46,8 +48,9 @@ use crate::{
         telemetry_builder::TelemetryBuilder,
     },
     state::{
-        bootstrap_state::BootstrapState, openrpc_state::OpenRpcState,
-        platform_state::PlatformState, session_state::Session,
+        bootstrap_state::BootstrapState, liveness_state::LivenessProbe,
+        openrpc_state::OpenRpcState, platform_state::PlatformState,
+        rate_limit_state::RateLimitState, session_state::Session,
     },
     utils::router_utils::{capture_stage, get_rpc_header_with_status},
 };
@@ -93,6 +96,8 @@ pub enum FireboltGatewayCommand {
     HandleResponse {
         response: JsonRpcApiResponse,
     },
+    /// Liveness probe answered by the command loop
+    Ping,
     StopServer,
 }
 
@@ -147,6 +152,11 @@ impl FireboltGateway {
                 HandleResponse { response } => {
                     self.handle_response(response);
                 }
+                Ping => self
+                    .state
+                    .platform_state
+                    .liveness_state
+                    .ack(LivenessProbe::Gateway),
                 StopServer => {
                     error!("Stopping server");
                     break;
@@ -276,6 +286,11 @@ impl FireboltGateway {
             }
         }
         let mut platform_state = self.state.platform_state.clone();
+        // extn protocol means its an internal Ripple request which is not rate limited
+        let rate_limited = !extn_request
+            && !platform_state
+                .rate_limit_state
+                .try_acquire(&request.ctx.app_id, &request.method);
 
         /*
          * The reason for spawning a new thread is that when request-1 comes, and it waits for
@@ -308,6 +323,38 @@ impl FireboltGateway {
 
         tokio::spawn(async move {
             capture_stage(&platform_state.metrics, &request_c, "context_ready");
+            if rate_limited {
+                TelemetryBuilder::stop_and_send_firebolt_metrics_timer(
+                    &platform_state.clone(),
+                    metrics_timer,
+                    format!("{}", JSON_RPC_ERROR_RATE_LIMITED),
+                )
+                .await;
+
+                warn!(
+                    "Rate limit exceeded app_id={} method={}",
+                    request.ctx.app_id, request.method
+                );
+                LogSignal::new(
+                    "firebolt_gateway".into(),
+                    "rate_limited".into(),
+                    request.clone(),
+                )
+                .with_diagnostic_context_item(
+                    "family",
+                    &RateLimitState::get_method_family(&request.method),
+                )
+                .emit_error();
+
+                let json_rpc_error = JsonRpcError {
+                    code: JSON_RPC_ERROR_RATE_LIMITED,
+                    message: "Rate limit exceeded".to_owned(),
+                    data: None,
+                };
+
+                send_json_rpc_error(&mut platform_state, &request, json_rpc_error).await;
+                return;
+            }
             // Validate incoming request parameters.
             if let Err(error_string) = validate_request(open_rpc_state, &request_c, fail_open) {
                 TelemetryBuilder::stop_and_send_firebolt_metrics_timer(
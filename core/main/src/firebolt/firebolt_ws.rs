@@ -16,8 +16,10 @@
 //
 
 use std::{
-    net::SocketAddr,
-    sync::{Arc, RwLock},
+    collections::HashMap,
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
 };
 
 use super::firebolt_gateway::FireboltGatewayCommand;
@@ -28,6 +30,7 @@ use crate::{
         session_state::Session,
     },
 };
+use futures::future::pending;
 use futures::SinkExt;
 use futures::StreamExt;
 use jsonrpsee::types::{error::INVALID_REQUEST_CODE, ErrorObject, ErrorResponse, Id};
@@ -36,17 +39,24 @@ use ripple_sdk::{
         gateway::rpc_gateway_api::{
             ApiMessage, ApiProtocol, ClientContext, JsonRpcApiResponse, RpcRequest, RPC_V2,
         },
-        observability::log_signal::LogSignal,
+        manifest::device_manifest::{UnixSocketPeers, WsConfiguration},
+        observability::{
+            log_signal::LogSignal,
+            recording::{RecordEntry, RecordKind},
+        },
     },
-    log::{error, info, trace},
+    log::{error, info, trace, warn},
     tokio::{
-        net::{TcpListener, TcpStream},
+        io::{AsyncRead, AsyncWrite},
+        net::{TcpListener, UnixListener, UnixStream},
         sync::{mpsc, oneshot},
+        time::{interval, Interval},
     },
     utils::channel_utils::oneshot_send_and_log,
     uuid::Uuid,
 };
 use ripple_sdk::{log::debug, tokio};
+use serde_json::Value;
 use tokio_tungstenite::{
     tungstenite::{self, Message},
     WebSocketStream,
@@ -67,9 +77,297 @@ struct ConnectionCallbackConfig {
     pub app_state: AppManagerState,
     pub secure: bool,
     pub internal_app_id: Option<String>,
+    /// App id resolved from the peer credentials of a unix socket client
+    pub peer_app_id: Option<String>,
 }
 pub struct ConnectionCallback(ConnectionCallbackConfig);
 
+#[derive(Debug, Clone, PartialEq)]
+enum BatchEntry {
+    /// Request id of a request whose response is part of the batch response
+    Request(String),
+    /// Request id of a notification, its response is dropped
+    Notification(String),
+    /// Response known when the batch is received, like the error for an invalid entry
+    Response(String),
+}
+
+/// How long the response to a notification of a batch is awaited before the notification is
+/// forgotten
+const NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default)]
+struct PendingBatch {
+    responses: Vec<Option<String>>,
+    slots: HashMap<String, usize>,
+    request_ids: Vec<String>,
+}
+
+impl PendingBatch {
+    fn get_frame(&self) -> String {
+        let responses: Vec<&str> = self
+            .responses
+            .iter()
+            .flatten()
+            .map(|r| r.as_str())
+            .collect();
+        format!("[{}]", responses.join(","))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Batches {
+    pending: Vec<PendingBatch>,
+    // request ids of the notifications whose responses are dropped, with when they were received
+    notifications: HashMap<String, Instant>,
+}
+
+impl Batches {
+    fn evict_notifications(&mut self, now: Instant) {
+        self.notifications
+            .retain(|_, received| now.saturating_duration_since(*received) < NOTIFICATION_TIMEOUT);
+    }
+}
+
+/// JSON-RPC batches received on a connection. Every request of a batch is handled on its own
+/// by the gateway, the responses are held back until all of them are available and then sent
+/// as a single array in the order of the batch. Responses to notifications are dropped when
+/// they arrive within the [NOTIFICATION_TIMEOUT], the batch does not wait for them.
+#[derive(Debug, Default, Clone)]
+struct BatchResponses {
+    batches: Arc<Mutex<Batches>>,
+}
+
+impl BatchResponses {
+    /// Returns the entries of a batch request, None if the text is not a non empty array
+    fn get_entries(text: &str) -> Option<Vec<Value>> {
+        if !text.trim_start().starts_with('[') {
+            return None;
+        }
+        serde_json::from_str::<Vec<Value>>(text)
+            .ok()
+            .filter(|entries| !entries.is_empty())
+    }
+
+    /// Adds a batch, returns the frame to send right away when the batch has no request
+    /// awaiting a response
+    fn add(&self, entries: Vec<BatchEntry>) -> Option<String> {
+        self.add_at(entries, Instant::now())
+    }
+
+    fn add_at(&self, entries: Vec<BatchEntry>, now: Instant) -> Option<String> {
+        let mut batches = self.batches.lock().unwrap();
+        batches.evict_notifications(now);
+        let mut batch = PendingBatch::default();
+        for entry in entries {
+            match entry {
+                BatchEntry::Request(request_id) => {
+                    batch.slots.insert(request_id, batch.responses.len());
+                    batch.responses.push(None);
+                }
+                BatchEntry::Notification(request_id) => {
+                    batches.notifications.insert(request_id, now);
+                }
+                BatchEntry::Response(response) => batch.responses.push(Some(response)),
+            }
+        }
+        if !batch.slots.is_empty() {
+            batches.pending.push(batch);
+            None
+        } else if !batch.responses.is_empty() {
+            Some(batch.get_frame())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the frame to send for a message of the session along with the request ids it
+    /// answers, None while the message is held back for a batch or dropped
+    fn take(&self, api_message: ApiMessage) -> Option<(String, Vec<String>)> {
+        let mut batches = self.batches.lock().unwrap();
+        if batches
+            .notifications
+            .remove(&api_message.request_id)
+            .is_some()
+        {
+            return None;
+        }
+        let Some(index) = batches
+            .pending
+            .iter()
+            .position(|batch| batch.slots.contains_key(&api_message.request_id))
+        else {
+            return Some((api_message.jsonrpc_msg, vec![api_message.request_id]));
+        };
+        let batch = &mut batches.pending[index];
+        if let Some(slot) = batch.slots.remove(&api_message.request_id) {
+            batch.responses[slot] = Some(api_message.jsonrpc_msg);
+        }
+        batch.request_ids.push(api_message.request_id);
+        if !batch.slots.is_empty() {
+            return None;
+        }
+        let batch = batches.pending.remove(index);
+        Some((batch.get_frame(), batch.request_ids))
+    }
+}
+
+const MIN_HEARTBEAT_CHECK_MS: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+enum HeartbeatAction {
+    None,
+    Ping,
+    Close(&'static str),
+}
+
+/// Ping and idle timeouts of a connection configured in the [WsConfiguration]. A quiet client
+/// gets pinged and its connection is closed when no frame arrives within the pong timeout, which
+/// detects half open connections of crashed app runtimes.
+#[derive(Debug, Clone)]
+struct ConnectionHeartbeat {
+    ping_interval: Option<Duration>,
+    pong_timeout: Duration,
+    idle_timeout: Option<Duration>,
+    last_frame: Instant,
+    last_request: Instant,
+    ping_sent: Option<Instant>,
+}
+
+impl ConnectionHeartbeat {
+    fn new(config: &WsConfiguration, now: Instant) -> Self {
+        let ping_interval = config.ping_interval_ms.map(Duration::from_millis);
+        Self {
+            ping_interval,
+            pong_timeout: config
+                .pong_timeout_ms
+                .map(Duration::from_millis)
+                .or(ping_interval)
+                .unwrap_or_default(),
+            idle_timeout: config.idle_timeout_ms.map(Duration::from_millis),
+            last_frame: now,
+            last_request: now,
+            ping_sent: None,
+        }
+    }
+
+    /// Returns how often the connection is checked, None without pings and idle timeout
+    fn get_check_interval(&self) -> Option<Duration> {
+        let pong_timeout = self.ping_interval.map(|_| self.pong_timeout);
+        [self.ping_interval, pong_timeout, self.idle_timeout]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(|shortest| (shortest / 2).max(Duration::from_millis(MIN_HEARTBEAT_CHECK_MS)))
+    }
+
+    fn on_frame(&mut self, now: Instant, is_request: bool) {
+        self.last_frame = now;
+        self.ping_sent = None;
+        if is_request {
+            self.last_request = now;
+        }
+    }
+
+    fn check(&mut self, now: Instant) -> HeartbeatAction {
+        if let Some(idle_timeout) = self.idle_timeout {
+            if now.saturating_duration_since(self.last_request) >= idle_timeout {
+                return HeartbeatAction::Close("idle timeout");
+            }
+        }
+        let Some(ping_interval) = self.ping_interval else {
+            return HeartbeatAction::None;
+        };
+        match self.ping_sent {
+            Some(sent) if now.saturating_duration_since(sent) >= self.pong_timeout => {
+                HeartbeatAction::Close("pong timeout")
+            }
+            Some(_) => HeartbeatAction::None,
+            None if now.saturating_duration_since(self.last_frame) >= ping_interval => {
+                self.ping_sent = Some(now);
+                HeartbeatAction::Ping
+            }
+            None => HeartbeatAction::None,
+        }
+    }
+}
+
+/// Resolves the app id of a unix socket client from its peer credentials, returns a description
+/// of the client along with the app id. Only what the kernel reports is trusted, the uid and the
+/// executable of the process, a process can name itself as it likes.
+fn get_peer_app_id(stream: &UnixStream, peers: &UnixSocketPeers) -> Option<(String, String)> {
+    let cred = match stream.peer_cred() {
+        Ok(cred) => cred,
+        Err(e) => {
+            warn!("Unable to get peer credentials {:?}", e);
+            return None;
+        }
+    };
+    let executable = cred
+        .pid()
+        .and_then(|pid| std::fs::read_link(format!("/proc/{}/exe", pid)).ok())
+        .map(|exe| exe.to_string_lossy().into_owned());
+    let client = format!(
+        "uid={} pid={:?} executable={:?}",
+        cred.uid(),
+        cred.pid(),
+        executable
+    );
+    match peers.get_app_id(cred.uid(), executable.as_deref()) {
+        Some(app_id) => Some((client, app_id)),
+        None => {
+            warn!("Rejecting unix socket client {}, no app id mapped", client);
+            None
+        }
+    }
+}
+
+/// Device and inode of a file
+fn get_file_id(path: &str) -> Option<(u64, u64)> {
+    std::fs::symlink_metadata(path)
+        .ok()
+        .map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+/// Removes the socket at `path` when nothing listens on it anymore, fails for any other file and
+/// for sockets in use
+fn remove_stale_socket(path: &str) -> Result<(), String> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("{:?}", e)),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err("the path is not a socket".to_owned());
+    }
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => Err("the socket is in use".to_owned()),
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            std::fs::remove_file(path).map_err(|e| format!("{:?}", e))
+        }
+        Err(e) => Err(format!("{:?}", e)),
+    }
+}
+
+async fn tick(ticker: &mut Option<Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => pending().await,
+    }
+}
+
+const REDACTED_QUERY_KEYS: [&str; 2] = ["token", "session"];
+
+fn get_invalid_request_error() -> String {
+    let err = ErrorResponse::owned(
+        ErrorObject::owned::<()>(INVALID_REQUEST_CODE, "invalid request".to_owned(), None),
+        Id::Null,
+    );
+    serde_json::to_string(&err).unwrap()
+}
+
 /**
  * Gets a query parameter from the request at the given key.
  * If required=true, then return an error if the param is missing
@@ -105,6 +403,25 @@ fn get_query(
     Ok(found_q.map(|q| String::from(q.1)))
 }
 
+/// Returns the query of a connection request for logging, with the credentials removed
+fn get_redacted_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if REDACTED_QUERY_KEYS.contains(&key) => format!("{}=<redacted>", key),
+            _ => pair.to_owned(),
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+fn get_forbidden_response(message: String) -> tungstenite::handshake::server::ErrorResponse {
+    tungstenite::http::response::Builder::new()
+        .status(403)
+        .body(Some(message))
+        .unwrap()
+}
+
 impl tungstenite::handshake::server::Callback for ConnectionCallback {
     fn on_request(
         self,
@@ -114,19 +431,41 @@ impl tungstenite::handshake::server::Callback for ConnectionCallback {
         tungstenite::handshake::server::Response,
         tungstenite::handshake::server::ErrorResponse,
     > {
-        info!("New firebolt connection {:?}", request.uri().query());
+        info!(
+            "New firebolt connection {:?}",
+            request.uri().query().map(get_redacted_query)
+        );
         let cfg = self.0;
-        let app_id_opt = match cfg.secure {
-            true => None,
-            false => match get_query(request, "appId", false)? {
-                Some(a) => Some(a),
-                None => cfg.internal_app_id,
-            },
-        };
-        let session_id = match app_id_opt {
-            Some(_) => Uuid::new_v4().to_string(),
+        let (app_id_opt, token_session_id) =
+            match (cfg.peer_app_id, get_query(request, "token", false)?) {
+                (Some(a), _) => (Some(a), None),
+                (None, Some(token)) => match cfg.app_state.verify_session_token(&token) {
+                    Ok((app_id, session_id)) => (Some(app_id), Some(session_id)),
+                    Err(e) => {
+                        error!("Rejecting session token {:?}", e);
+                        return Err(get_forbidden_response(format!(
+                            "Invalid session token {:?}",
+                            e
+                        )));
+                    }
+                },
+                (None, None) if cfg.secure => (None, None),
+                (None, None) => match get_query(request, "appId", false)? {
+                    Some(a) if cfg.app_state.is_session_token_dev_mode() => (Some(a), None),
+                    Some(a) => {
+                        error!("Rejecting unsigned appId={} outside of dev mode", a);
+                        return Err(get_forbidden_response(
+                            "appId requires dev mode, connect with a session token".to_owned(),
+                        ));
+                    }
+                    None => (cfg.internal_app_id, None),
+                },
+            };
+        let session_id = match (token_session_id, &app_id_opt) {
+            (Some(session_id), _) => session_id,
+            (None, Some(_)) => Uuid::new_v4().to_string(),
             // can unwrap here because if session is not given, then error will be returned
-            None => get_query(request, "session", true)?.unwrap(),
+            (None, None) => get_query(request, "session", true)?.unwrap(),
         };
         let app_id = match app_id_opt {
             Some(a) => a,
@@ -180,55 +519,140 @@ impl tungstenite::handshake::server::Callback for ConnectionCallback {
 
 impl FireboltWs {
     pub async fn start(
-        server_addr: &str,
+        config: WsConfiguration,
         state: PlatformState,
         secure: bool,
         internal_app_id: Option<String>,
     ) {
+        let server_addr = config.gateway.as_str();
         // Create the event loop and TCP listener we'll accept connections on.
         let try_socket = TcpListener::bind(&server_addr).await; //create the server on the address
         let listener = try_socket.unwrap_or_else(|_| panic!("Failed to bind {:?}", server_addr));
         info!("Listening on: {} secure={}", server_addr, secure);
-        let state_for_connection = state.clone();
-        let app_state = state.app_manager_state.clone();
+        let shutdown_state = state.shutdown_state.clone();
         // Let's spawn the handling of each connection in a separate task.
-        while let Ok((stream, client_addr)) = listener.accept().await {
-            let (connect_tx, connect_rx) = oneshot::channel::<ClientIdentity>();
-            let cfg = ConnectionCallbackConfig {
-                next: connect_tx,
-                app_state: app_state.clone(),
-                secure,
-                internal_app_id: internal_app_id.clone(),
-            };
-            match tokio_tungstenite::accept_hdr_async(stream, ConnectionCallback(cfg)).await {
-                Err(e) => {
-                    error!("websocket connection error {:?}", e);
+        loop {
+            let (stream, client_addr) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(_) => break,
+                },
+                _ = shutdown_state.wait() => {
+                    info!("Stopped accepting connections on {}", server_addr);
+                    break;
                 }
-                Ok(ws_stream) => {
-                    trace!("websocket connection success");
-                    let state_for_connection_c = state_for_connection.clone();
-                    tokio::spawn(async move {
-                        FireboltWs::handle_connection(
-                            client_addr,
-                            ws_stream,
-                            connect_rx,
-                            state_for_connection_c.clone(),
-                            secure,
-                        )
-                        .await;
-                    });
+            };
+            FireboltWs::accept(
+                stream,
+                client_addr.to_string(),
+                None,
+                &config,
+                state.clone(),
+                secure,
+                internal_app_id.clone(),
+            )
+            .await;
+        }
+    }
+
+    /// Listens on the unix socket of the configuration. The app id of a client is resolved from
+    /// its peer credentials through the `unix_socket_peers`, clients which cannot be resolved are
+    /// rejected.
+    pub async fn start_unix(
+        config: WsConfiguration,
+        state: PlatformState,
+        secure: bool,
+        internal_app_id: Option<String>,
+    ) {
+        let Some(path) = config.unix_socket.clone() else {
+            return;
+        };
+        // a socket file left behind by a previous run would fail the bind
+        if let Err(e) = remove_stale_socket(&path) {
+            panic!("Failed to bind {:?} {}", path, e);
+        }
+        let listener = UnixListener::bind(&path)
+            .unwrap_or_else(|e| panic!("Failed to bind {:?} {:?}", path, e));
+        let socket_id = get_file_id(&path);
+        info!("Listening on: {} secure={}", path, secure);
+        let shutdown_state = state.shutdown_state.clone();
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(_) => break,
+                },
+                _ = shutdown_state.wait() => {
+                    info!("Stopped accepting connections on {}", path);
+                    break;
                 }
+            };
+            let Some((client, app_id)) = get_peer_app_id(&stream, &config.unix_socket_peers) else {
+                continue;
+            };
+            FireboltWs::accept(
+                stream,
+                client,
+                Some(app_id),
+                &config,
+                state.clone(),
+                secure,
+                internal_app_id.clone(),
+            )
+            .await;
+        }
+        // the path may have been taken over by another socket in the meantime
+        if socket_id.is_some() && get_file_id(&path) == socket_id {
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    async fn accept<S>(
+        stream: S,
+        client: String,
+        peer_app_id: Option<String>,
+        config: &WsConfiguration,
+        state: PlatformState,
+        secure: bool,
+        internal_app_id: Option<String>,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (connect_tx, connect_rx) = oneshot::channel::<ClientIdentity>();
+        let cfg = ConnectionCallbackConfig {
+            next: connect_tx,
+            app_state: state.app_manager_state.clone(),
+            secure,
+            internal_app_id,
+            peer_app_id,
+        };
+        match tokio_tungstenite::accept_hdr_async(stream, ConnectionCallback(cfg)).await {
+            Err(e) => {
+                error!("websocket connection error {:?}", e);
+            }
+            Ok(ws_stream) => {
+                trace!("websocket connection success");
+                let heartbeat = ConnectionHeartbeat::new(config, Instant::now());
+                tokio::spawn(async move {
+                    FireboltWs::handle_connection(
+                        client, ws_stream, connect_rx, state, secure, heartbeat,
+                    )
+                    .await;
+                });
             }
         }
     }
 
-    async fn handle_connection(
-        _client_addr: SocketAddr,
-        ws_stream: WebSocketStream<TcpStream>,
+    async fn handle_connection<S>(
+        peer: String,
+        ws_stream: WebSocketStream<S>,
         connect_rx: oneshot::Receiver<ClientIdentity>,
         state: PlatformState,
         gateway_secure: bool,
-    ) {
+        mut heartbeat: ConnectionHeartbeat,
+    ) where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
         let identity = connect_rx.await.unwrap();
         let client = state.get_client();
         let app_id = identity.app_id.clone();
@@ -244,12 +668,8 @@ impl FireboltWs {
 
         let connection_id = Uuid::new_v4().to_string();
         info!(
-            "Creating new connection_id={} app_id={} session_id={}, gateway_secure={}, port={}",
-            connection_id,
-            app_id_c,
-            session_id_c,
-            gateway_secure,
-            _client_addr.port()
+            "Creating new connection_id={} app_id={} session_id={}, gateway_secure={}, peer={}",
+            connection_id, app_id_c, session_id_c, gateway_secure, peer
         );
 
         let connection_id_c = connection_id.clone();
@@ -280,16 +700,42 @@ impl FireboltWs {
         let mut platform_state = state.clone();
         let context_clone = ctx.clone();
 
+        let batch_responses = BatchResponses::default();
+        let batch_responses_c = batch_responses.clone();
+        // pings and close frames of the heartbeat
+        let (control_tx, mut control_rx) = mpsc::channel::<Message>(2);
+
         tokio::spawn(async move {
-            while let Some(api_message) = resp_rx.recv().await {
-                let send_result = sender
-                    .send(Message::Text(api_message.jsonrpc_msg.clone()))
-                    .await;
+            loop {
+                let api_message = tokio::select! {
+                    api_message = resp_rx.recv() => match api_message {
+                        Some(api_message) => api_message,
+                        None => break,
+                    },
+                    Some(control) = control_rx.recv() => {
+                        if let Err(e) = sender.send(control).await {
+                            error!("{:?}", e);
+                        }
+                        continue;
+                    }
+                };
+                let Some((frame, request_ids)) = batch_responses_c.take(api_message) else {
+                    continue;
+                };
+                let send_result = sender.send(Message::Text(frame.clone())).await;
                 match send_result {
                     Ok(_) => {
-                        platform_state
-                            .metrics
-                            .update_api_stage(&api_message.request_id, "response");
+                        for request_id in &request_ids {
+                            platform_state
+                                .metrics
+                                .update_api_stage(request_id, "response");
+                        }
+                        platform_state.recording_state.record(|| {
+                            RecordEntry::from_text(RecordKind::FireboltResponse, &frame)
+                                .with_app_id(&app_id_c)
+                                .with_connection_id(&connection_id_c)
+                                .with_request_id(&request_ids.join(","))
+                        });
 
                         LogSignal::new(
                             "sent_firebolt_response".to_string(),
@@ -297,31 +743,28 @@ impl FireboltWs {
                             context_clone.clone(),
                         )
                         .with_diagnostic_context_item("cid", &connection_id_c.clone())
-                        .with_diagnostic_context_item("result", &api_message.jsonrpc_msg.clone())
+                        .with_diagnostic_context_item("result", &frame)
                         .emit_debug();
-                        if let Some(stats) = platform_state
-                            .metrics
-                            .get_api_stats(&api_message.request_id)
-                        {
-                            info!(
-                                "Sending Firebolt response: {:?},{}",
-                                stats.stats_ref,
-                                stats.stats.get_total_time()
-                            );
-                            debug!(
-                                "Full Firebolt Split: {:?},{}",
-                                stats.stats_ref,
-                                stats.stats.get_stage_durations()
-                            );
-                            platform_state
-                                .metrics
-                                .remove_api_stats(&api_message.request_id);
+                        for request_id in &request_ids {
+                            if let Some(stats) = platform_state.metrics.get_api_stats(request_id) {
+                                info!(
+                                    "Sending Firebolt response: {:?},{}",
+                                    stats.stats_ref,
+                                    stats.stats.get_total_time()
+                                );
+                                debug!(
+                                    "Full Firebolt Split: {:?},{}",
+                                    stats.stats_ref,
+                                    stats.stats.get_stage_durations()
+                                );
+                                platform_state.metrics.remove_api_stats(request_id);
+                            }
                         }
 
                         info!(
                             "Sent Firebolt response cid={} msg={}",
                             connection_id_c.clone(),
-                            api_message.jsonrpc_msg
+                            frame
                         );
                     }
                     Err(err) => error!("{:?}", err),
@@ -337,14 +780,99 @@ impl FireboltWs {
 
         let session_id_c = identity.session_id.clone();
         let app_id_c = identity.app_id.clone();
-        while let Some(msg) = receiver.next().await {
+        let mut ticker = heartbeat.get_check_interval().map(interval);
+        loop {
+            let msg = tokio::select! {
+                msg = receiver.next() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                _ = tick(&mut ticker) => {
+                    match heartbeat.check(Instant::now()) {
+                        HeartbeatAction::Ping => {
+                            let _ = control_tx.send(Message::Ping(Vec::new())).await;
+                        }
+                        HeartbeatAction::Close(reason) => {
+                            info!("Closing connection cid={} reason={}", connection_id, reason);
+                            let _ = control_tx.send(Message::Close(None)).await;
+                            break;
+                        }
+                        HeartbeatAction::None => {}
+                    }
+                    continue;
+                }
+            };
+            if let Ok(msg) = &msg {
+                heartbeat.on_frame(Instant::now(), msg.is_text());
+            }
             match msg {
                 Ok(msg) => {
                     if msg.is_text() && !msg.is_empty() {
                         let req_text = String::from(msg.to_text().unwrap());
                         let req_id = Uuid::new_v4().to_string();
+                        state.recording_state.record(|| {
+                            RecordEntry::from_text(RecordKind::FireboltRequest, &req_text)
+                                .with_app_id(&app_id_c)
+                                .with_connection_id(&connection_id)
+                                .with_request_id(&req_id)
+                        });
                         let context = { rpc_context.read().unwrap().clone() };
-                        if let Ok(request) = RpcRequest::parse(
+                        if let Some(entries) = BatchResponses::get_entries(&req_text) {
+                            let mut batch = Vec::new();
+                            let mut commands = Vec::new();
+                            for entry in entries {
+                                let entry_id = Uuid::new_v4().to_string();
+                                let notification = entry.get("id").is_none();
+                                let entry_text = entry.to_string();
+                                if let Ok(request) = RpcRequest::parse(
+                                    entry_text.clone(),
+                                    app_id_c.clone(),
+                                    session_id_c.clone(),
+                                    entry_id.clone(),
+                                    Some(connection_id.clone()),
+                                    gateway_secure,
+                                    context.clone(),
+                                ) {
+                                    info!(
+                                        "Received Firebolt batch request {}",
+                                        request.params_json
+                                    );
+                                    batch.push(match notification {
+                                        true => BatchEntry::Notification(entry_id),
+                                        false => BatchEntry::Request(entry_id),
+                                    });
+                                    commands.push(FireboltGatewayCommand::HandleRpc { request });
+                                } else if let Some(response) =
+                                    JsonRpcApiResponse::get_response(&entry_text)
+                                {
+                                    commands
+                                        .push(FireboltGatewayCommand::HandleResponse { response });
+                                } else {
+                                    error!("invalid batch entry {}", entry_text);
+                                    batch.push(BatchEntry::Response(get_invalid_request_error()));
+                                }
+                            }
+                            // the batch is added before its requests are routed so that no
+                            // response can get ahead of it
+                            if let Some(frame) = batch_responses.add(batch) {
+                                if let Some(session) = &state
+                                    .session_state
+                                    .get_session_for_connection_id(&connection_id)
+                                {
+                                    let api_msg = ApiMessage::new(
+                                        ApiProtocol::JsonRpc,
+                                        frame,
+                                        req_id.clone(),
+                                    );
+                                    let _ = session.send_json_rpc(api_msg).await;
+                                }
+                            }
+                            for msg in commands {
+                                if let Err(e) = client.clone().send_gateway_command(msg) {
+                                    error!("failed to send request {:?}", e);
+                                }
+                            }
+                        } else if let Ok(request) = RpcRequest::parse(
                             req_text.clone(),
                             app_id_c.clone(),
                             session_id_c.clone(),
@@ -368,16 +896,7 @@ impl FireboltWs {
                                 .session_state
                                 .get_session_for_connection_id(&connection_id)
                             {
-                                let err = ErrorResponse::owned(
-                                    ErrorObject::owned::<()>(
-                                        INVALID_REQUEST_CODE,
-                                        "invalid request".to_owned(),
-                                        None,
-                                    ),
-                                    Id::Null,
-                                );
-
-                                let msg = serde_json::to_string(&err).unwrap();
+                                let msg = get_invalid_request_error();
                                 let api_msg =
                                     ApiMessage::new(ApiProtocol::JsonRpc, msg, req_id.clone());
                                 let _ = session.send_json_rpc(api_msg).await;
@@ -401,3 +920,229 @@ impl FireboltWs {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::api::manifest::device_manifest::UnixSocketExecutable;
+
+    fn message(request_id: &str, msg: &str) -> ApiMessage {
+        ApiMessage::new(ApiProtocol::JsonRpc, msg.to_owned(), request_id.to_owned())
+    }
+
+    #[test]
+    fn test_get_entries() {
+        assert!(BatchResponses::get_entries(r#"{"jsonrpc":"2.0","id":1}"#).is_none());
+        assert!(BatchResponses::get_entries("[]").is_none());
+        assert!(BatchResponses::get_entries("[1,").is_none());
+        assert_eq!(
+            BatchResponses::get_entries(r#" [{"id":1},2]"#)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_batch_responses() {
+        let batch_responses = BatchResponses::default();
+        assert!(batch_responses
+            .add(vec![
+                BatchEntry::Request("1".to_owned()),
+                BatchEntry::Notification("2".to_owned()),
+                BatchEntry::Response("{\"id\":null}".to_owned()),
+                BatchEntry::Request("3".to_owned()),
+            ])
+            .is_none());
+
+        assert!(batch_responses.take(message("3", "{\"id\":3}")).is_none());
+        // messages outside of the batch are passed through
+        assert_eq!(
+            batch_responses.take(message("4", "{\"id\":4}")),
+            Some(("{\"id\":4}".to_owned(), vec!["4".to_owned()]))
+        );
+        // the batch does not wait for the notification
+        assert_eq!(
+            batch_responses.take(message("1", "{\"id\":1}")),
+            Some((
+                "[{\"id\":1},{\"id\":null},{\"id\":3}]".to_owned(),
+                vec!["3".to_owned(), "1".to_owned()]
+            ))
+        );
+        assert!(batch_responses.take(message("2", "{\"id\":0}")).is_none());
+        // later messages for a request of a completed batch, like events, are passed through
+        assert!(batch_responses.take(message("1", "{}")).is_some());
+        assert!(batch_responses.take(message("2", "{}")).is_some());
+    }
+
+    #[test]
+    fn test_evict_notifications() {
+        let batch_responses = BatchResponses::default();
+        let start = Instant::now();
+        assert!(batch_responses
+            .add_at(vec![BatchEntry::Notification("1".to_owned())], start)
+            .is_none());
+        assert!(batch_responses
+            .add_at(
+                vec![BatchEntry::Notification("2".to_owned())],
+                start + NOTIFICATION_TIMEOUT
+            )
+            .is_none());
+        // the notification which was never answered is forgotten
+        let batches = batch_responses.batches.lock().unwrap();
+        assert_eq!(batches.notifications.keys().collect::<Vec<_>>(), vec!["2"]);
+    }
+
+    #[test]
+    fn test_heartbeat_ping() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut heartbeat = ConnectionHeartbeat::new(
+            &WsConfiguration {
+                ping_interval_ms: Some(1000),
+                pong_timeout_ms: Some(500),
+                ..Default::default()
+            },
+            start,
+        );
+        assert_eq!(
+            heartbeat.get_check_interval(),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(heartbeat.check(at(500)), HeartbeatAction::None);
+        assert_eq!(heartbeat.check(at(1000)), HeartbeatAction::Ping);
+        assert_eq!(heartbeat.check(at(1250)), HeartbeatAction::None);
+        // the pong is a frame from the client
+        heartbeat.on_frame(at(1300), false);
+        assert_eq!(heartbeat.check(at(2000)), HeartbeatAction::None);
+        assert_eq!(heartbeat.check(at(2300)), HeartbeatAction::Ping);
+        assert_eq!(
+            heartbeat.check(at(2800)),
+            HeartbeatAction::Close("pong timeout")
+        );
+    }
+
+    #[test]
+    fn test_heartbeat_idle_timeout() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut heartbeat = ConnectionHeartbeat::new(
+            &WsConfiguration {
+                idle_timeout_ms: Some(1000),
+                ..Default::default()
+            },
+            start,
+        );
+        heartbeat.on_frame(at(600), true);
+        assert_eq!(heartbeat.check(at(1200)), HeartbeatAction::None);
+        // frames which are not requests keep the connection idle
+        heartbeat.on_frame(at(1400), false);
+        assert_eq!(
+            heartbeat.check(at(1600)),
+            HeartbeatAction::Close("idle timeout")
+        );
+
+        let heartbeat = ConnectionHeartbeat::new(&WsConfiguration::default(), start);
+        assert_eq!(heartbeat.get_check_interval(), None);
+    }
+
+    #[test]
+    fn test_batch_without_requests() {
+        let batch_responses = BatchResponses::default();
+        assert!(batch_responses
+            .add(vec![BatchEntry::Notification("1".to_owned())])
+            .is_none());
+        assert!(batch_responses.take(message("1", "{}")).is_none());
+        assert_eq!(
+            batch_responses.add(vec![
+                BatchEntry::Notification("2".to_owned()),
+                BatchEntry::Response("{}".to_owned())
+            ]),
+            Some("[{}]".to_owned())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_peer_app_id() {
+        let uid = std::fs::metadata("/proc/self").unwrap().uid();
+        let (stream, _peer) = UnixStream::pair().unwrap();
+        assert!(get_peer_app_id(&stream, &UnixSocketPeers::default()).is_none());
+
+        let mut peers = UnixSocketPeers {
+            uids: HashMap::from([(uid, "app1".to_owned())]),
+            executables: Vec::new(),
+        };
+        let (client, app_id) = get_peer_app_id(&stream, &peers).unwrap();
+        assert_eq!(app_id, "app1");
+        assert!(client.starts_with(&format!("uid={} pid=Some(", uid)));
+
+        let exe = std::env::current_exe().unwrap();
+        peers.executables.push(UnixSocketExecutable {
+            path: exe.to_string_lossy().into_owned(),
+            uid,
+            app_id: "app2".to_owned(),
+        });
+        let (_, app_id) = get_peer_app_id(&stream, &peers).unwrap();
+        assert_eq!(app_id, "app2");
+    }
+
+    #[test]
+    fn test_get_redacted_query() {
+        assert_eq!(
+            get_redacted_query("appId=app&token=secret&RPCv2=true"),
+            "appId=app&token=<redacted>&RPCv2=true"
+        );
+        assert_eq!(get_redacted_query("session=abc"), "session=<redacted>");
+        assert_eq!(get_redacted_query("appId=app"), "appId=app");
+    }
+
+    #[test]
+    fn test_remove_stale_socket() {
+        let dir = std::env::temp_dir().join(format!("ripple-ws-test-{}", Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("ripple.sock");
+        let path_str = path.to_str().unwrap();
+        assert!(remove_stale_socket(path_str).is_ok());
+
+        // regular files are kept
+        std::fs::write(&path, "data").unwrap();
+        assert!(remove_stale_socket(path_str).is_err());
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+
+        // sockets in use are kept, stale ones removed
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert!(remove_stale_socket(path_str).is_err());
+        assert!(path.exists());
+        drop(listener);
+        assert!(remove_stale_socket(path_str).is_ok());
+        assert!(!path.exists());
+        std::fs::remove_dir(&dir).unwrap();
+    }
+
+    fn handshake(app_state: &AppManagerState, query: &str) -> Result<ClientIdentity, u16> {
+        let (next, mut identity_rx) = oneshot::channel::<ClientIdentity>();
+        let callback = ConnectionCallback(ConnectionCallbackConfig {
+            next,
+            app_state: app_state.clone(),
+            secure: false,
+            internal_app_id: None,
+            peer_app_id: None,
+        });
+        let request = tungstenite::handshake::server::Request::builder()
+            .uri(format!("ws://127.0.0.1:3474/?{}", query))
+            .body(())
+            .unwrap();
+        let response = tungstenite::handshake::server::Response::new(());
+        tungstenite::handshake::server::Callback::on_request(callback, &request, response)
+            .map(|_| identity_rx.try_recv().unwrap())
+            .map_err(|e| e.status().as_u16())
+    }
+
+    #[test]
+    fn test_on_request_requires_session_token() {
+        let app_state = AppManagerState::default();
+        assert_eq!(handshake(&app_state, "appId=app1").err(), Some(403));
+        assert_eq!(handshake(&app_state, "token=forged.token").err(), Some(403));
+    }
+}
@@ -0,0 +1,381 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::collections::HashMap;
+
+use jsonrpsee::{core::RpcResult, proc_macros::rpc, RpcModule};
+use ripple_sdk::{
+    api::{
+        firebolt::{
+            fb_capabilities::{FireboltCap, FireboltPermission},
+            fb_openrpc::CapabilitySet,
+        },
+        gateway::rpc_gateway_api::CallContext,
+        status_update::ExtnStatus,
+    },
+    async_trait::async_trait,
+};
+use serde::Serialize;
+
+use crate::{
+    broker::{endpoint_broker::PendingRequestInfo, thunder::thunder_plugins_status_mgr::State},
+    service::apps::provider_broker::ProviderBroker,
+    state::{extn_state::ExtnState, platform_state::PlatformState},
+};
+
+/// Capability an app needs to manage to call the admin methods
+pub const ADMIN_CAPABILITY: &str = "xrn:firebolt:capability:ripple:admin";
+const ADMIN_METHOD_PREFIX: &str = "ripple.admin.";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminSession {
+    pub session_id: String,
+    pub app_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminListener {
+    pub event: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    pub app_id: String,
+    pub session_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminExtension {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<ExtnStatus>,
+}
+
+/// Introspection of a running Ripple for on-device triage, the methods are only served on the
+/// internal gateway to apps managing [ADMIN_CAPABILITY].
+#[rpc(server)]
+pub trait Admin {
+    #[method(name = "ripple.admin.sessions")]
+    async fn sessions(&self, ctx: CallContext) -> RpcResult<Vec<AdminSession>>;
+    #[method(name = "ripple.admin.listeners")]
+    async fn listeners(&self, ctx: CallContext) -> RpcResult<Vec<AdminListener>>;
+    #[method(name = "ripple.admin.providers")]
+    async fn providers(&self, ctx: CallContext) -> RpcResult<HashMap<String, Vec<String>>>;
+    #[method(name = "ripple.admin.brokerRequests")]
+    async fn broker_requests(&self, ctx: CallContext) -> RpcResult<Vec<PendingRequestInfo>>;
+    #[method(name = "ripple.admin.extensions")]
+    async fn extensions(&self, ctx: CallContext) -> RpcResult<Vec<AdminExtension>>;
+    #[method(name = "ripple.admin.thunderPlugins")]
+    async fn thunder_plugins(&self, ctx: CallContext) -> RpcResult<HashMap<String, State>>;
+    #[method(name = "ripple.admin.permissions")]
+    async fn permissions(
+        &self,
+        ctx: CallContext,
+    ) -> RpcResult<HashMap<String, Vec<FireboltPermission>>>;
+}
+
+pub struct AdminImpl {
+    pub state: PlatformState,
+    pub extn_state: ExtnState,
+}
+
+#[async_trait]
+impl AdminServer for AdminImpl {
+    async fn sessions(&self, _ctx: CallContext) -> RpcResult<Vec<AdminSession>> {
+        let mut sessions: Vec<AdminSession> = self
+            .state
+            .session_state
+            .get_sessions()
+            .into_iter()
+            .map(|(session_id, app_id)| AdminSession { session_id, app_id })
+            .collect();
+        sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        Ok(sessions)
+    }
+
+    async fn listeners(&self, _ctx: CallContext) -> RpcResult<Vec<AdminListener>> {
+        let mut result = Vec::new();
+        let listeners = self.state.app_events_state.listeners.read().unwrap();
+        for (event, context_map) in listeners.iter() {
+            for (context, event_listeners) in context_map.iter() {
+                result.extend(event_listeners.iter().map(|listener| AdminListener {
+                    event: event.clone(),
+                    context: context.clone(),
+                    app_id: listener.call_ctx.app_id.clone(),
+                    session_id: listener.call_ctx.session_id.clone(),
+                }));
+            }
+        }
+        result.sort_by(|a, b| a.event.cmp(&b.event).then(a.app_id.cmp(&b.app_id)));
+        Ok(result)
+    }
+
+    async fn providers(&self, _ctx: CallContext) -> RpcResult<HashMap<String, Vec<String>>> {
+        Ok(ProviderBroker::get_provider_methods(&self.state).entries)
+    }
+
+    async fn broker_requests(&self, _ctx: CallContext) -> RpcResult<Vec<PendingRequestInfo>> {
+        Ok(self.state.endpoint_state.get_pending_requests())
+    }
+
+    async fn extensions(&self, _ctx: CallContext) -> RpcResult<Vec<AdminExtension>> {
+        let mut statuses = self.extn_state.get_extn_statuses();
+        let mut result = Vec::new();
+        for library in self.extn_state.loaded_libraries.read().unwrap().iter() {
+            for symbol in &library.entry.symbols {
+                result.push(AdminExtension {
+                    id: symbol.id.clone(),
+                    path: Some(library.entry.path.clone()),
+                    status: statuses.remove(&symbol.id),
+                });
+            }
+        }
+        // optional extensions which were skipped
+        for entry in self.extn_state.excluded_extns.read().unwrap().iter() {
+            for symbol in &entry.symbols {
+                result.push(AdminExtension {
+                    id: symbol.id.clone(),
+                    path: Some(entry.path.clone()),
+                    status: statuses.remove(&symbol.id),
+                });
+            }
+        }
+        // extensions which reported a status without being loaded from a library
+        let mut others: Vec<AdminExtension> = statuses
+            .into_iter()
+            .map(|(id, status)| AdminExtension {
+                id,
+                path: None,
+                status: Some(status),
+            })
+            .collect();
+        others.sort_by(|a, b| a.id.cmp(&b.id));
+        result.extend(others);
+        Ok(result)
+    }
+
+    async fn thunder_plugins(&self, _ctx: CallContext) -> RpcResult<HashMap<String, State>> {
+        Ok(self.state.endpoint_state.get_thunder_plugin_states())
+    }
+
+    async fn permissions(
+        &self,
+        _ctx: CallContext,
+    ) -> RpcResult<HashMap<String, Vec<FireboltPermission>>> {
+        Ok(self.state.cap_state.permitted_state.get_all_permissions())
+    }
+}
+
+/// Returns the capabilities of the admin methods of a module, all of them are managed by
+/// [ADMIN_CAPABILITY].
+fn get_methods_caps<'a>(
+    method_names: impl Iterator<Item = &'a str>,
+) -> HashMap<String, CapabilitySet> {
+    method_names
+        .filter(|method| method.starts_with(ADMIN_METHOD_PREFIX))
+        .map(|method| {
+            (
+                method.to_owned(),
+                CapabilitySet {
+                    use_caps: None,
+                    provide_cap: None,
+                    manage_caps: Some(vec![FireboltCap::Full(ADMIN_CAPABILITY.to_owned())]),
+                },
+            )
+        })
+        .collect()
+}
+
+pub struct AdminRPCProvider;
+impl AdminRPCProvider {
+    /// Provides the admin module and registers its methods on the internal gateway surface, the
+    /// external gateway does not resolve them.
+    pub fn provide(state: PlatformState, extn_state: ExtnState) -> RpcModule<AdminImpl> {
+        let module = (AdminImpl {
+            state: state.clone(),
+            extn_state,
+        })
+        .into_rpc();
+        state
+            .open_rpc_state
+            .extend_ripple_caps(get_methods_caps(module.method_names()));
+        module
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service::apps::app_events::AppEvents,
+        state::{
+            bootstrap_state::ChannelsState, openrpc_state::ApiSurface, session_state::Session,
+        },
+    };
+    use ripple_sdk::{
+        api::{
+            firebolt::{fb_capabilities::CapabilityRole, fb_general::ListenRequest},
+            manifest::extn_manifest::{
+                ExtnKind, ExtnManifestEntry, ExtnResolutionEntry, ExtnSymbol,
+            },
+        },
+        extn::extn_id::{ExtnClassId, ExtnId},
+        tokio,
+    };
+    use ripple_tdk::utils::test_utils::Mockable;
+
+    fn admin() -> AdminImpl {
+        let state = PlatformState::mock();
+        let extn_state = ExtnState::new(ChannelsState::new(), state.get_manifest());
+        AdminImpl { state, extn_state }
+    }
+
+    #[tokio::test]
+    async fn test_provide_registers_internal_methods() {
+        let state = PlatformState::mock();
+        let extn_state = ExtnState::new(ChannelsState::new(), state.get_manifest());
+        let _ = AdminRPCProvider::provide(state.clone(), extn_state);
+        let perms = state
+            .open_rpc_state
+            .get_perms_for_method("ripple.admin.sessions", vec![ApiSurface::Ripple])
+            .unwrap();
+        assert_eq!(
+            perms,
+            vec![FireboltPermission {
+                cap: FireboltCap::Full(ADMIN_CAPABILITY.to_owned()),
+                role: CapabilityRole::Manage,
+            }]
+        );
+        assert!(state
+            .open_rpc_state
+            .get_perms_for_method("ripple.admin.sessions", vec![ApiSurface::Firebolt])
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sessions_and_listeners() {
+        let admin = admin();
+        let ctx = CallContext::mock();
+        admin.state.session_state.add_session(
+            ctx.session_id.clone(),
+            Session::new(ctx.app_id.clone(), None),
+        );
+        AppEvents::add_listener(
+            &admin.state,
+            "device.onNameChanged".to_owned(),
+            ctx.clone(),
+            ListenRequest {
+                listen: true,
+                replay: None,
+            },
+        );
+
+        let sessions = admin.sessions(ctx.clone()).await.unwrap();
+        assert_eq!(
+            sessions,
+            vec![AdminSession {
+                session_id: ctx.session_id.clone(),
+                app_id: ctx.app_id.clone(),
+            }]
+        );
+        let listeners = admin.listeners(ctx.clone()).await.unwrap();
+        assert_eq!(
+            listeners,
+            vec![AdminListener {
+                event: "device.onNameChanged".to_owned(),
+                context: None,
+                app_id: ctx.app_id.clone(),
+                session_id: ctx.session_id.clone(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extensions() {
+        let admin = admin();
+        let id = ExtnId::new_channel(ExtnClassId::Device, "thunder".to_owned());
+        admin
+            .extn_state
+            .update_extn_status(id.clone(), ExtnStatus::Ready);
+        let extensions = admin.extensions(CallContext::mock()).await.unwrap();
+        assert_eq!(
+            extensions,
+            vec![AdminExtension {
+                id: id.to_string(),
+                path: None,
+                status: Some(ExtnStatus::Ready),
+            }]
+        );
+        assert!(admin
+            .broker_requests(CallContext::mock())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_excluded_extensions() {
+        let admin = admin();
+        let id = ExtnId::new_channel(ExtnClassId::Device, "thunder".to_owned());
+        let mut entry = ExtnManifestEntry {
+            path: "libthunder".to_owned(),
+            symbols: vec![ExtnSymbol {
+                id: id.to_string(),
+                uses: Vec::new(),
+                fulfills: vec!["device_info".to_owned()],
+                config: None,
+            }],
+            resolution: Some(vec![ExtnResolutionEntry {
+                capability: "device_info".to_owned(),
+                priority: Some(1),
+                exclusion: None,
+            }]),
+            kind: ExtnKind::default(),
+            optional: true,
+        };
+        entry.exclude();
+        admin.extn_state.excluded_extns.write().unwrap().push(entry);
+        admin
+            .extn_state
+            .update_extn_status(id.clone(), ExtnStatus::Incompatible);
+        let extensions = admin.extensions(CallContext::mock()).await.unwrap();
+        assert_eq!(
+            extensions,
+            vec![AdminExtension {
+                id: id.to_string(),
+                path: Some("libthunder".to_owned()),
+                status: Some(ExtnStatus::Incompatible),
+            }]
+        );
+
+        // the resolution excludes the skipped entry in place of the one of the manifest
+        let mut manifest = admin.state.get_manifest();
+        manifest.extns = vec![ExtnManifestEntry {
+            optional: true,
+            ..admin.extn_state.excluded_extns.read().unwrap()[0].clone()
+        }];
+        manifest.extns[0].resolution.as_mut().unwrap()[0].exclusion = None;
+        let entries = admin.extn_state.get_extn_entries(&manifest);
+        assert_eq!(
+            entries[0].resolution.as_ref().unwrap()[0].exclusion,
+            Some(true)
+        );
+    }
+}
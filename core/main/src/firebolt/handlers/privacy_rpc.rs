@@ -394,7 +394,10 @@ impl PrivacyImpl {
             state,
             event_name.to_owned(),
             ctx.clone(),
-            ListenRequest { listen },
+            ListenRequest {
+                listen,
+                replay: None,
+            },
             event_context,
             dec,
         );
@@ -648,6 +651,7 @@ impl PrivacyImpl {
             PrivacySettingsStorageType::Cloud | PrivacySettingsStorageType::Sync => {
                 if let Some(dist_session) = platform_state.session_state.get_account_session() {
                     if let Some(privacy_setting) = property.as_privacy_setting() {
+                        let _write = platform_state.shutdown_state.start_write();
                         let request = PrivacyCloudRequest::SetProperty(SetPropertyParams {
                             setting: privacy_setting,
                             value,
@@ -0,0 +1,81 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::collections::BTreeMap;
+
+use crate::{
+    broker::{
+        endpoint_broker::RulesReloadSummary, endpoint_health::EndpointStatus,
+        rules_engine::RuleEngine,
+    },
+    firebolt::rpc::RippleRPCProvider,
+    state::platform_state::PlatformState,
+    utils::rpc_utils::rpc_err,
+};
+use jsonrpsee::{core::RpcResult, proc_macros::rpc, RpcModule};
+use ripple_sdk::{
+    api::gateway::rpc_gateway_api::CallContext, async_trait::async_trait, log::error,
+};
+
+/// Internal api to manage the broker rules, the methods are not part of the Firebolt
+/// open rpc so they are only reachable from extensions and Ripple itself.
+#[rpc(server)]
+pub trait Rules {
+    #[method(name = "ripple.reloadRules")]
+    async fn reload_rules(&self, ctx: CallContext) -> RpcResult<RulesReloadSummary>;
+    #[method(name = "ripple.getEndpointStatus")]
+    async fn get_endpoint_status(
+        &self,
+        ctx: CallContext,
+    ) -> RpcResult<BTreeMap<String, EndpointStatus>>;
+}
+
+pub struct RulesImpl {
+    state: PlatformState,
+}
+
+#[async_trait]
+impl RulesServer for RulesImpl {
+    async fn reload_rules(&self, _ctx: CallContext) -> RpcResult<RulesReloadSummary> {
+        let rule_engine = RuleEngine::try_build(&self.state.get_manifest()).map_err(|e| {
+            error!("Unable to load rules {}", e);
+            rpc_err(e)
+        })?;
+        self.state
+            .endpoint_state
+            .clone()
+            .reload_rules(self.state.clone(), rule_engine)
+            .map_err(|errors| {
+                error!("Invalid rules {:?}", errors);
+                rpc_err(errors.join("\n"))
+            })
+    }
+
+    async fn get_endpoint_status(
+        &self,
+        _ctx: CallContext,
+    ) -> RpcResult<BTreeMap<String, EndpointStatus>> {
+        Ok(self.state.endpoint_state.get_endpoint_status())
+    }
+}
+
+pub struct RulesRPCProvider;
+impl RippleRPCProvider<RulesImpl> for RulesRPCProvider {
+    fn provide(state: PlatformState) -> RpcModule<RulesImpl> {
+        (RulesImpl { state }).into_rpc()
+    }
+}
@@ -20,6 +20,7 @@
 pub mod handlers {
     pub mod accessory_rpc;
     pub mod account_rpc;
+    pub mod admin_rpc;
     pub mod advertising_rpc;
     pub mod audio_description_rpc;
     pub mod authentication_rpc;
@@ -37,6 +38,7 @@ pub mod handlers {
     pub mod privacy_rpc;
     pub mod profile_rpc;
     pub mod provider_registrar;
+    pub mod rules_rpc;
     pub mod second_screen_rpc;
     pub mod secure_storage_rpc;
     pub mod user_grants_rpc;
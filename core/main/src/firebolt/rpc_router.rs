@@ -43,7 +43,7 @@ use std::sync::{Arc, RwLock};
 
 use crate::{
     firebolt::firebolt_gateway::JsonRpcMessage,
-    service::telemetry_builder::TelemetryBuilder,
+    service::{apps::app_events::AppEvents, telemetry_builder::TelemetryBuilder},
     state::{platform_state::PlatformState, session_state::Session},
     utils::router_utils::{
         add_telemetry_status_code, capture_stage, get_rpc_header, return_extn_response,
@@ -239,6 +239,7 @@ impl RpcRouter {
                 TelemetryBuilder::send_fb_tt(&state, req.clone(), now - start, success, &msg);
                 let _ = session.send_json_rpc(msg).await;
             }
+            AppEvents::replay(&state, &req.ctx.request_id).await;
         });
     }
 
@@ -258,9 +259,11 @@ impl RpcRouter {
         )
         .emit_debug();
         tokio::spawn(async move {
+            let request_id = req.ctx.request_id.clone();
             if let Ok(msg) = resolve_route(&mut platform_state, methods, resources, req).await {
                 return_extn_response(msg, extn_msg);
             }
+            AppEvents::replay(&platform_state, &request_id).await;
         });
     }
 }
@@ -16,6 +16,9 @@
 //
 
 use crate::bootstrap::boot::boot;
+use crate::broker::rules_linter::RulesLinter;
+use crate::service::extn::extn_process::{ExtnProcess, EXTN_HOST_COMMAND};
+use crate::service::shutdown_coordinator::ShutdownCoordinator;
 use ripple_sdk::{
     log::{error, info},
     tokio,
@@ -33,6 +36,14 @@ include!(concat!(env!("OUT_DIR"), "/version.rs"));
 
 #[tokio::main(worker_threads = 2)]
 async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("lint-rules") {
+        std::process::exit(RulesLinter::run(&args[2..]));
+    }
+    if args.get(1).map(String::as_str) == Some(EXTN_HOST_COMMAND) {
+        let _ = init_and_configure_logger(SEMVER_LIGHTWEIGHT, EXTN_HOST_COMMAND.into(), None);
+        std::process::exit(ExtnProcess::host(&args[2..]).await);
+    }
     // Init logger
     if let Err(e) = init_and_configure_logger(SEMVER_LIGHTWEIGHT, "gateway".into(), None) {
         println!("{:?} logger init error", e);
@@ -40,16 +51,32 @@ async fn main() {
     }
     info!("version {}", SEMVER_LIGHTWEIGHT);
     let bootstate = BootstrapState::build().expect("Failure to init state for bootstrap");
+    let platform_state = bootstate.platform_state.clone();
 
-    // bootstrap
-    match boot(bootstate).await {
-        Ok(_) => {
+    // bootstrap, the gateway started by the boot keeps serving the apps while they are drained
+    // on a shutdown, so it runs in its own task
+    let mut boot_task = tokio::spawn(boot(bootstate));
+    tokio::select! {
+        result = &mut boot_task => match result {
+            Ok(Ok(_)) => {
+                info!("Ripple Exited gracefully!");
+                std::process::exit(exitcode::OK);
+            }
+            Ok(Err(e)) => {
+                error!("Ripple failed with Error: {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+            Err(e) => {
+                error!("Ripple boot task failed {:?}", e);
+                std::process::exit(exitcode::SOFTWARE);
+            }
+        },
+        _ = ShutdownCoordinator::wait_for_signal() => {
+            ShutdownCoordinator::shutdown(&platform_state).await;
+            // the gateway is only stopped once the apps, requests and extensions are drained
+            boot_task.abort();
             info!("Ripple Exited gracefully!");
             std::process::exit(exitcode::OK);
         }
-        Err(e) => {
-            error!("Ripple failed with Error: {:?}", e);
-            std::process::exit(exitcode::SOFTWARE);
-        }
     }
 }
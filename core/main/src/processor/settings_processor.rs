@@ -184,7 +184,10 @@ impl SettingsProcessor {
             state,
             event_name.to_string(),
             ctx,
-            ListenRequest { listen: true },
+            ListenRequest {
+                listen: true,
+                replay: None,
+            },
             Some(Box::new(SettingsChangeEventDecorator { request })),
         );
         true
@@ -413,6 +413,8 @@ impl StorageManager {
         event_names: Option<&'static [&'static str]>,
         context: Option<Value>,
     ) -> Result<StorageManagerResponse<()>, StorageManagerError> {
+        // privacy and settings writes complete before Ripple shuts down
+        let _write = state.shutdown_state.start_write();
         if let Ok(ExtnResponse::StorageData(storage_data)) =
             StorageManager::get(state, &namespace, &key, scope.clone()).await
         {
@@ -583,6 +585,7 @@ impl StorageManager {
         scope: Option<String>,
     ) -> Result<ExtnResponse, RippleError> {
         trace!("delete: namespace={}, key={}", namespace, key);
+        let _write = state.shutdown_state.start_write();
         let data = DeleteStorageProperty {
             namespace: namespace.clone(),
             key: key.clone(),
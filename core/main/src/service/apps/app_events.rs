@@ -29,7 +29,7 @@ use ripple_sdk::{
 };
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     sync::{Arc, RwLock},
 };
 
@@ -71,10 +71,92 @@ impl Clone for Box<dyn AppEventDecorator + Send + Sync> {
 pub struct AppEvents {}
 
 type ListenersMap = Arc<RwLock<HashMap<String, HashMap<Option<String>, Vec<EventListener>>>>>;
+type LastEventsMap = Arc<RwLock<RecentEvents<Vec<(Option<String>, LastEvent)>>>>;
+type AppLastEventsMap = Arc<RwLock<HashMap<String, RecentEvents<Option<LastEvent>>>>>;
+
+/// Most event contexts for which the last payload of an event is kept
+const MAX_STICKY_EVENT_CONTEXTS: usize = 32;
+/// Most events which are not sticky whose last payload is kept for listeners asking for a
+/// replay, the least recently emitted one is forgotten first
+const MAX_REPLAYABLE_EVENTS: usize = 64;
+
+/// Last payload emitted for an event, kept to replay it to late listeners
+#[derive(Clone, Debug)]
+struct LastEvent {
+    result: Value,
+    context: Option<Value>,
+}
+
+/// Last payloads kept per event name. Sticky events are always kept, the other events are
+/// bounded by [MAX_REPLAYABLE_EVENTS].
+#[derive(Debug)]
+struct RecentEvents<T> {
+    events: HashMap<String, T>,
+    // events which are not sticky, least recently emitted first
+    recent: VecDeque<String>,
+}
+
+impl<T> Default for RecentEvents<T> {
+    fn default() -> Self {
+        Self {
+            events: HashMap::new(),
+            recent: VecDeque::new(),
+        }
+    }
+}
+
+impl<T: Default> RecentEvents<T> {
+    fn get(&self, event_name: &str) -> Option<&T> {
+        self.events.get(event_name)
+    }
+
+    fn entry(&mut self, event_name: &str, sticky: bool) -> &mut T {
+        if !sticky {
+            self.recent.retain(|e| e != event_name);
+            self.recent.push_back(event_name.to_owned());
+            if self.recent.len() > MAX_REPLAYABLE_EVENTS {
+                if let Some(evicted) = self.recent.pop_front() {
+                    self.events.remove(&evicted);
+                }
+            }
+        }
+        self.events.entry(event_name.to_owned()).or_default()
+    }
+}
+
+/// Replay waiting for the response of the listen request which registered the listener
+#[derive(Clone)]
+struct PendingReplay {
+    listener: EventListener,
+    event_name: String,
+    has_context: bool,
+    last_event: LastEvent,
+}
 
 #[derive(Clone, Default)]
 pub struct AppEventsState {
     pub listeners: ListenersMap,
+    sticky_events: Arc<Vec<String>>,
+    last_events: LastEventsMap,
+    // keyed by app id, then event name
+    app_last_events: AppLastEventsMap,
+    // keyed by the request id of the listen request
+    pending_replays: Arc<RwLock<HashMap<String, PendingReplay>>>,
+}
+
+impl AppEventsState {
+    pub fn new(sticky_events: Vec<String>) -> Self {
+        Self {
+            sticky_events: Arc::new(sticky_events),
+            ..Default::default()
+        }
+    }
+
+    fn is_sticky_event(&self, event_name: &str) -> bool {
+        self.sticky_events
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(event_name))
+    }
 }
 
 impl std::fmt::Debug for AppEventsState {
@@ -240,15 +322,26 @@ impl AppEvents {
         let event_ctx_string = event_context.map(|x| x.to_string());
 
         if listen_request.listen {
-            let event_listeners =
-                AppEvents::get_or_create_listener_vec(&mut listeners, event_name, event_ctx_string);
-            //The last listener wins if there is already a listener exists with same session id
-            AppEvents::remove_session_from_events(event_listeners, &call_ctx.session_id);
-            event_listeners.push(EventListener {
+            let replay = listen_request.replay.unwrap_or(false)
+                || app_events_state.is_sticky_event(&event_name);
+            let listener = EventListener {
                 call_ctx,
                 session_tx: session.get_sender(),
                 decorator,
-            });
+            };
+            if replay {
+                AppEvents::add_pending_replay(
+                    app_events_state,
+                    &event_name,
+                    event_ctx_string.clone(),
+                    &listener,
+                );
+            }
+            let event_listeners =
+                AppEvents::get_or_create_listener_vec(&mut listeners, event_name, event_ctx_string);
+            //The last listener wins if there is already a listener exists with same session id
+            AppEvents::remove_session_from_events(event_listeners, &listener.call_ctx.session_id);
+            event_listeners.push(listener);
         } else if let Some(entry) = listeners.get_mut(&event_name) {
             if let Some(event_listeners) = entry.get_mut(&event_ctx_string) {
                 AppEvents::remove_session_from_events(event_listeners, &call_ctx.session_id);
@@ -256,6 +349,136 @@ impl AppEvents {
         }
     }
 
+    fn add_pending_replay(
+        state: &AppEventsState,
+        event_name: &str,
+        event_context: Option<String>,
+        listener: &EventListener,
+    ) {
+        let has_context = event_context.is_some();
+        // an event emitted to the app itself is more specific than one emitted to every app
+        let app_last_event = if has_context {
+            None
+        } else {
+            state
+                .app_last_events
+                .read()
+                .unwrap()
+                .get(&listener.call_ctx.app_id)
+                .and_then(|events| events.get(event_name).cloned().flatten())
+        };
+        let last_event = match app_last_event.or_else(|| {
+            state
+                .last_events
+                .read()
+                .unwrap()
+                .get(event_name)
+                .and_then(|contexts| contexts.iter().find(|(k, _)| *k == event_context))
+                .map(|(_, last_event)| last_event.clone())
+        }) {
+            Some(last_event) => last_event,
+            None => return,
+        };
+        state.pending_replays.write().unwrap().insert(
+            listener.call_ctx.request_id.clone(),
+            PendingReplay {
+                listener: listener.clone(),
+                event_name: event_name.to_owned(),
+                has_context,
+                last_event,
+            },
+        );
+    }
+
+    /// Replays the last payload of an event to the listener registered by the given request.
+    /// Called once the response of the listen request is sent, so that the replayed event
+    /// reaches the app after it.
+    pub async fn replay(state: &PlatformState, request_id: &str) {
+        let pending = state
+            .app_events_state
+            .pending_replays
+            .write()
+            .unwrap()
+            .remove(request_id);
+        if let Some(pending) = pending {
+            debug!("Replaying last {} event", pending.event_name);
+            let LastEvent { result, context } = pending.last_event;
+            if pending.has_context {
+                AppEvents::send_event(&pending.listener, &result).await;
+            } else {
+                AppEvents::send_to_listener(
+                    state,
+                    &pending.listener,
+                    &pending.event_name,
+                    &result,
+                    &context,
+                )
+                .await;
+            }
+        }
+    }
+
+    fn set_last_event(
+        state: &AppEventsState,
+        event_name: &str,
+        result: &Value,
+        context: &Option<Value>,
+    ) {
+        let sticky = state.is_sticky_event(event_name);
+        let mut last_events = state.last_events.write().unwrap();
+        let contexts = last_events.entry(event_name, sticky);
+        // listeners without a context get every payload of the event, along with its context
+        AppEvents::insert_last_event(
+            contexts,
+            None,
+            LastEvent {
+                result: result.clone(),
+                context: context.clone(),
+            },
+        );
+        if let Some(ctx) = context {
+            AppEvents::insert_last_event(
+                contexts,
+                Some(ctx.to_string()),
+                LastEvent {
+                    result: result.clone(),
+                    context: Some(ctx.clone()),
+                },
+            );
+        }
+    }
+
+    fn insert_last_event(
+        contexts: &mut Vec<(Option<String>, LastEvent)>,
+        event_context: Option<String>,
+        last_event: LastEvent,
+    ) {
+        contexts.retain(|(k, _)| *k != event_context);
+        contexts.push((event_context, last_event));
+        if contexts.len() > MAX_STICKY_EVENT_CONTEXTS {
+            contexts.remove(0);
+        }
+    }
+
+    fn set_app_last_event(state: &AppEventsState, app_id: &str, event_name: &str, result: &Value) {
+        let sticky = state.is_sticky_event(event_name);
+        *state
+            .app_last_events
+            .write()
+            .unwrap()
+            .entry(app_id.to_owned())
+            .or_default()
+            .entry(event_name, sticky) = Some(LastEvent {
+            result: result.clone(),
+            context: None,
+        });
+    }
+
+    /// Forgets the events emitted to an app, called once the app is gone.
+    pub fn clear_app_events(state: &AppEventsState, app_id: &str) {
+        state.app_last_events.write().unwrap().remove(app_id);
+    }
+
     fn get_rpc_v2_result(event: &str, input: Value) -> Value {
         // FIXME: This is a temporary hack to get the event field name from the event string.
         // We'll need to extract this from the firebolt schema when it's available.
@@ -349,27 +572,12 @@ impl AppEvents {
         result: &Value,
         context: Option<Value>,
     ) {
+        AppEvents::set_last_event(&state.app_events_state, event_name, result, &context);
         // Notify all the default listners by providing the context data as part of the result when context
         // is present. Otherwise event result without context.
         let listeners = AppEvents::get_listeners(&state.app_events_state, event_name, None);
         for i in listeners {
-            let decorated_res = i.decorate(state, event_name, result).await;
-            if decorated_res.is_err() {
-                error!("could not generate event for '{}'", event_name);
-                continue;
-            }
-            if context.is_some() {
-                AppEvents::send_event(
-                    &i,
-                    &json!({
-                        "context": context.clone(),
-                        "value"  : &decorated_res.unwrap(),
-                    }),
-                )
-                .await;
-            } else {
-                AppEvents::send_event(&i, &decorated_res.unwrap()).await;
-            }
+            AppEvents::send_to_listener(state, &i, event_name, result, &context).await;
         }
 
         // Now Notify events to the context based listeners. Context info is not included as part of the result
@@ -386,12 +594,39 @@ impl AppEvents {
         }
     }
 
+    async fn send_to_listener(
+        state: &PlatformState,
+        listener: &EventListener,
+        event_name: &str,
+        result: &Value,
+        context: &Option<Value>,
+    ) {
+        let decorated_res = listener.decorate(state, event_name, result).await;
+        if decorated_res.is_err() {
+            error!("could not generate event for '{}'", event_name);
+            return;
+        }
+        if context.is_some() {
+            AppEvents::send_event(
+                listener,
+                &json!({
+                    "context": context.clone(),
+                    "value"  : &decorated_res.unwrap(),
+                }),
+            )
+            .await;
+        } else {
+            AppEvents::send_event(listener, &decorated_res.unwrap()).await;
+        }
+    }
+
     pub async fn emit_to_app(
         state: &PlatformState,
         app_id: String,
         event_name: &str,
         result: &Value,
     ) {
+        AppEvents::set_app_last_event(&state.app_events_state, &app_id, event_name, result);
         let listeners_vec = AppEvents::get_listeners(&state.app_events_state, event_name, None)
             .into_iter()
             .filter(|listener| listener.call_ctx.app_id.eq(&app_id))
@@ -427,6 +662,12 @@ impl AppEvents {
 
     pub fn remove_session(state: &PlatformState, session_id: String) {
         state.session_state.clear_session(&session_id);
+        state
+            .app_events_state
+            .pending_replays
+            .write()
+            .unwrap()
+            .retain(|_, pending| pending.listener.call_ctx.session_id != session_id);
         let mut listeners = state.app_events_state.listeners.write().unwrap();
         let all_events = listeners.keys().cloned().collect::<Vec<String>>();
         for event_name in all_events {
@@ -452,7 +693,10 @@ pub mod tests {
     pub async fn test_add_listener() {
         let platform_state = PlatformState::mock();
         let call_context = CallContext::mock();
-        let listen_request = ListenRequest { listen: true };
+        let listen_request = ListenRequest {
+            listen: true,
+            replay: None,
+        };
         Session::new(call_context.clone().app_id, None);
         let session = Session::new(call_context.clone().app_id, None);
         platform_state
@@ -478,4 +722,131 @@ pub mod tests {
             AppEvents::get_listeners(&platform_state.app_events_state, "test_event", None);
         assert!(listeners.len() == 1);
     }
+
+    #[tokio::test]
+    pub async fn test_replay_last_event() {
+        let mut platform_state = PlatformState::mock();
+        platform_state.app_events_state = AppEventsState::new(vec!["test_event".to_owned()]);
+        let call_context = CallContext::mock();
+        let (session_tx, mut session_rx) = mpsc::channel(4);
+        let session = Session::new(call_context.clone().app_id, Some(session_tx));
+        platform_state
+            .session_state
+            .add_session(call_context.clone().session_id, session);
+
+        // events which are not sticky are only replayed when the listener asks for it
+        AppEvents::emit(&platform_state, "other_event", &json!("other")).await;
+        let listen_other = |replay| {
+            AppEvents::add_listener(
+                &platform_state,
+                "other_event".to_string(),
+                call_context.clone(),
+                ListenRequest {
+                    listen: true,
+                    replay,
+                },
+            )
+        };
+        listen_other(None);
+        AppEvents::replay(&platform_state, &call_context.request_id).await;
+        assert!(session_rx.try_recv().is_err());
+        listen_other(Some(true));
+        AppEvents::replay(&platform_state, &call_context.request_id).await;
+        let message = session_rx.try_recv().unwrap();
+        assert!(message.jsonrpc_msg.contains("\"other\""));
+
+        AppEvents::emit(&platform_state, "test_event", &json!("first")).await;
+        AppEvents::emit(&platform_state, "test_event", &json!("last")).await;
+        AppEvents::add_listener(
+            &platform_state,
+            "test_event".to_string(),
+            call_context.clone(),
+            ListenRequest {
+                listen: true,
+                replay: None,
+            },
+        );
+        AppEvents::replay(&platform_state, &call_context.request_id).await;
+        let message = session_rx.try_recv().unwrap();
+        assert!(message.jsonrpc_msg.contains("\"last\""));
+        // the replay happens only once
+        AppEvents::replay(&platform_state, &call_context.request_id).await;
+        assert!(session_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    pub async fn test_replay_app_event() {
+        let mut platform_state = PlatformState::mock();
+        platform_state.app_events_state = AppEventsState::new(vec!["test_event".to_owned()]);
+        let call_context = CallContext::mock();
+        let (session_tx, mut session_rx) = mpsc::channel(4);
+        let session = Session::new(call_context.clone().app_id, Some(session_tx));
+        platform_state
+            .session_state
+            .add_session(call_context.clone().session_id, session);
+        let listen = |call_context: CallContext| {
+            AppEvents::add_listener(
+                &platform_state,
+                "test_event".to_string(),
+                call_context,
+                ListenRequest {
+                    listen: true,
+                    replay: None,
+                },
+            )
+        };
+
+        AppEvents::emit_to_app(
+            &platform_state,
+            call_context.app_id.clone(),
+            "test_event",
+            &json!("mine"),
+        )
+        .await;
+        AppEvents::emit_to_app(
+            &platform_state,
+            "other_app".to_owned(),
+            "test_event",
+            &json!("other"),
+        )
+        .await;
+        listen(call_context.clone());
+        AppEvents::replay(&platform_state, &call_context.request_id).await;
+        let message = session_rx.try_recv().unwrap();
+        assert!(message.jsonrpc_msg.contains("\"mine\""));
+
+        AppEvents::clear_app_events(&platform_state.app_events_state, &call_context.app_id);
+        listen(call_context.clone());
+        AppEvents::replay(&platform_state, &call_context.request_id).await;
+        assert!(session_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    pub async fn test_last_event_contexts_bounded() {
+        let mut platform_state = PlatformState::mock();
+        platform_state.app_events_state = AppEventsState::new(vec!["test_event".to_owned()]);
+        for i in 0..MAX_STICKY_EVENT_CONTEXTS * 2 {
+            AppEvents::emit_with_context(&platform_state, "test_event", &json!(i), Some(json!(i)))
+                .await;
+        }
+        let last_events = platform_state.app_events_state.last_events.read().unwrap();
+        let contexts = last_events.get("test_event").unwrap();
+        assert_eq!(contexts.len(), MAX_STICKY_EVENT_CONTEXTS);
+        assert!(contexts.iter().any(|(k, _)| k.is_none()));
+    }
+
+    #[tokio::test]
+    pub async fn test_replayable_events_bounded() {
+        let mut platform_state = PlatformState::mock();
+        platform_state.app_events_state = AppEventsState::new(vec!["test_event".to_owned()]);
+        AppEvents::emit(&platform_state, "test_event", &json!("sticky")).await;
+        for i in 0..MAX_REPLAYABLE_EVENTS + 1 {
+            AppEvents::emit(&platform_state, &format!("event_{}", i), &json!(i)).await;
+        }
+        let last_events = platform_state.app_events_state.last_events.read().unwrap();
+        assert_eq!(last_events.events.len(), MAX_REPLAYABLE_EVENTS + 1);
+        // the least recently emitted event is forgotten, sticky events are kept
+        assert!(last_events.get("event_0").is_none());
+        assert!(last_events.get("test_event").is_some());
+    }
 }
@@ -41,6 +41,7 @@ use ripple_sdk::{
             fb_secondscreen::SECOND_SCREEN_EVENT_ON_LAUNCH_REQUEST,
         },
         gateway::rpc_gateway_api::{AppIdentification, CallerSession},
+        manifest::device_manifest::SessionTokenConfiguration,
     },
     log::{debug, error, trace, warn},
     serde_json::{self},
@@ -73,7 +74,10 @@ use serde_json::{json, Value};
 use crate::{
     processor::metrics_processor::send_metric_for_app_state_change,
     service::{
-        apps::app_events::AppEvents,
+        apps::{
+            app_events::AppEvents,
+            session_tokens::{SessionTokenError, SessionTokens},
+        },
         extn::ripple_client::RippleClient,
         telemetry_builder::TelemetryBuilder,
         user_grants::{GrantHandler, GrantPolicyEnforcer, GrantState},
@@ -116,10 +120,11 @@ pub struct AppManagerState {
     // This is a map <app_id, app_migrated_state>
     migrated_apps: Arc<RwLock<HashMap<String, Vec<String>>>>,
     migrated_apps_persist_path: String,
+    session_tokens: SessionTokens,
 }
 
 impl AppManagerState {
-    pub fn new(saved_dir: &str) -> Self {
+    pub fn new(saved_dir: &str, session_tokens: &SessionTokenConfiguration) -> Self {
         let app_title_persist_path = Self::get_storage_path(saved_dir, APP_ID_TITLE_DIR_NAME);
         let persisted_app_titles =
             Self::load_persisted_data::<String>(&app_title_persist_path, APP_ID_TITLE_FILE_NAME);
@@ -137,6 +142,7 @@ impl AppManagerState {
             app_title_persist_path,
             migrated_apps: Arc::new(RwLock::new(persisted_migrated_apps)),
             migrated_apps_persist_path,
+            session_tokens: SessionTokens::new(session_tokens),
         }
     }
 
@@ -258,6 +264,10 @@ impl AppManagerState {
         self.apps.read().unwrap().contains_key(app_id)
     }
 
+    pub fn get_app_ids(&self) -> Vec<String> {
+        self.apps.read().unwrap().keys().cloned().collect()
+    }
+
     pub fn get_app_id_from_session_id(&self, session_id: &str) -> Option<String> {
         {
             debug!("apps and sessions {:?}", self.apps.read().unwrap());
@@ -275,6 +285,25 @@ impl AppManagerState {
         }
     }
 
+    /// Mints a session token for the current session of the app
+    pub fn mint_session_token(&self, app: &App) -> String {
+        self.session_tokens.mint(&app.app_id, &app.session_id)
+    }
+
+    /// Returns the app id and session id of a session token, as long as the token was signed
+    /// by this device, has not expired and the app still has the session
+    pub fn verify_session_token(&self, token: &str) -> Result<(String, String), SessionTokenError> {
+        let claims = self.session_tokens.verify(token)?;
+        match self.get_app_id_from_session_id(&claims.session_id) {
+            Some(app_id) if app_id == claims.app_id => Ok((app_id, claims.session_id)),
+            _ => Err(SessionTokenError::UnknownSession),
+        }
+    }
+
+    pub fn is_session_token_dev_mode(&self) -> bool {
+        self.session_tokens.is_dev_mode()
+    }
+
     fn set_session(&self, app_id: &str, session: AppSession) {
         let mut apps = self.apps.write().unwrap();
         if let Some(app) = apps.get_mut(app_id) {
@@ -734,6 +763,7 @@ impl DelegatedLauncherHandler {
                 } else {
                     Self::new_active_session(platform_state, session, emit_completed).await;
                     SessionResponse::Completed(Self::to_completed_session(
+                        &platform_state.app_manager_state,
                         &platform_state.app_manager_state.get(&app_id).unwrap(),
                     ))
                 }
@@ -765,7 +795,10 @@ impl DelegatedLauncherHandler {
                 self.platform_state
                     .app_manager_state
                     .update_active_session(&app_id, None);
-                return SessionResponse::Completed(Self::to_completed_session(&app));
+                return SessionResponse::Completed(Self::to_completed_session(
+                    &self.platform_state.app_manager_state,
+                    &app,
+                ));
             }
             session_id = Some(app.session_id.clone());
             loaded_session_id = Some(app.loaded_session_id);
@@ -889,7 +922,7 @@ impl DelegatedLauncherHandler {
         platform_state
             .app_manager_state
             .insert(app_id.clone(), app.clone());
-        let sess = Self::to_completed_session(&app);
+        let sess = Self::to_completed_session(&platform_state.app_manager_state, &app);
         if emit_event {
             Self::emit_completed(platform_state, &app_id).await;
         }
@@ -972,13 +1005,14 @@ impl DelegatedLauncherHandler {
         None
     }
 
-    fn to_completed_session(app: &App) -> CompletedSessionResponse {
+    fn to_completed_session(state: &AppManagerState, app: &App) -> CompletedSessionResponse {
         CompletedSessionResponse {
             app_id: app.app_id.clone(),
             session_id: app.session_id.clone(),
             loaded_session_id: app.loaded_session_id.clone(),
             active_session_id: app.active_session_id.clone(),
             transition_pending: false,
+            session_token: Some(state.mint_session_token(app)),
         }
     }
 
@@ -988,7 +1022,10 @@ impl DelegatedLauncherHandler {
             Some(app) => app,
             None => return,
         };
-        let sr = SessionResponse::Completed(Self::to_completed_session(&app));
+        let sr = SessionResponse::Completed(Self::to_completed_session(
+            &platform_state.app_manager_state,
+            &app,
+        ));
         AppEvents::emit(
             platform_state,
             LCM_EVENT_ON_SESSION_TRANSITION_COMPLETED,
@@ -1010,6 +1047,7 @@ impl DelegatedLauncherHandler {
     async fn end_session(&mut self, app_id: &str) -> Result<AppManagerResponse, AppError> {
         debug!("end_session: entry: app_id={}", app_id);
         let app = self.platform_state.app_manager_state.remove(app_id);
+        AppEvents::clear_app_events(&self.platform_state.app_events_state, app_id);
         if app.is_some() {
             if let Some(timer) = self.timer_map.remove(app_id) {
                 timer.cancel();
@@ -1564,4 +1602,35 @@ mod tests {
             LifecycleState::Initializing
         ),);
     }
+
+    #[test]
+    fn test_verify_session_token() {
+        let state = AppManagerState::default();
+        let app = App {
+            initial_session: AppSession::default(),
+            current_session: AppSession::default(),
+            session_id: "session1".to_owned(),
+            state: LifecycleState::Initializing,
+            loaded_session_id: "loaded1".to_owned(),
+            active_session_id: None,
+            internal_state: None,
+            app_id: "app1".to_owned(),
+            app_metrics_version: None,
+            is_app_init_params_invoked: false,
+        };
+        state.insert("app1".to_owned(), app.clone());
+        let token = state.mint_session_token(&app);
+        assert_eq!(
+            state.verify_session_token(&token),
+            Ok(("app1".to_owned(), "session1".to_owned()))
+        );
+        assert!(!state.is_session_token_dev_mode());
+
+        // tokens do not outlive the session they were minted for
+        state.remove("app1");
+        assert_eq!(
+            state.verify_session_token(&token),
+            Err(SessionTokenError::UnknownSession)
+        );
+    }
 }
@@ -18,3 +18,4 @@
 pub mod app_events;
 pub mod delegated_launcher_handler;
 pub mod provider_broker;
+pub mod session_tokens;
@@ -0,0 +1,202 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{
+    fmt,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use ripple_sdk::{
+    api::manifest::device_manifest::SessionTokenConfiguration,
+    log::{error, info},
+};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionClaims {
+    pub app_id: String,
+    pub session_id: String,
+    /// Expiry in milliseconds since the epoch
+    pub exp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionTokenError {
+    Malformed,
+    InvalidSignature,
+    Expired,
+    /// The token is for a session the app no longer has
+    UnknownSession,
+}
+
+/// Mints and verifies the session tokens apps present to the gateway. A token is the base64
+/// encoded claims followed by their HMAC-SHA256 signature with the device key.
+#[derive(Clone)]
+pub struct SessionTokens {
+    key: Arc<Vec<u8>>,
+    ttl_ms: u64,
+    dev_mode: bool,
+}
+
+impl fmt::Debug for SessionTokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionTokens")
+            .field("ttl_ms", &self.ttl_ms)
+            .field("dev_mode", &self.dev_mode)
+            .finish()
+    }
+}
+
+impl Default for SessionTokens {
+    fn default() -> Self {
+        Self::new(&SessionTokenConfiguration::default())
+    }
+}
+
+impl SessionTokens {
+    pub fn new(config: &SessionTokenConfiguration) -> Self {
+        let key = match &config.key_path {
+            Some(path) => match std::fs::read(path) {
+                Ok(key) if !key.is_empty() => Some(key),
+                Ok(_) => {
+                    error!("Session token key {} is empty", path);
+                    None
+                }
+                Err(e) => {
+                    error!("Unable to read session token key {} {:?}", path, e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let key = key.unwrap_or_else(|| {
+            info!("Signing session tokens with a generated key");
+            rand::thread_rng().gen::<[u8; 32]>().to_vec()
+        });
+        Self {
+            key: Arc::new(key),
+            ttl_ms: config.ttl_ms,
+            dev_mode: config.dev_mode,
+        }
+    }
+
+    pub fn is_dev_mode(&self) -> bool {
+        self.dev_mode
+    }
+
+    pub fn mint(&self, app_id: &str, session_id: &str) -> String {
+        self.mint_at(app_id, session_id, Self::now_ms())
+    }
+
+    pub fn verify(&self, token: &str) -> Result<SessionClaims, SessionTokenError> {
+        self.verify_at(token, Self::now_ms())
+    }
+
+    fn mint_at(&self, app_id: &str, session_id: &str, now_ms: u64) -> String {
+        let claims = SessionClaims {
+            app_id: app_id.to_owned(),
+            session_id: session_id.to_owned(),
+            exp: now_ms + self.ttl_ms,
+        };
+        // serializing a struct of strings and numbers cannot fail
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).unwrap());
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    fn verify_at(&self, token: &str, now_ms: u64) -> Result<SessionClaims, SessionTokenError> {
+        let (payload, signature) = token.split_once('.').ok_or(SessionTokenError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| SessionTokenError::Malformed)?;
+        self.mac(payload)
+            .verify_slice(&signature)
+            .map_err(|_| SessionTokenError::InvalidSignature)?;
+        let claims = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|p| serde_json::from_slice::<SessionClaims>(&p).ok())
+            .ok_or(SessionTokenError::Malformed)?;
+        if claims.exp <= now_ms {
+            return Err(SessionTokenError::Expired);
+        }
+        Ok(claims)
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        // HMAC accepts keys of any length
+        let mut mac = HmacSha256::new_from_slice(&self.key).unwrap();
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    fn now_ms() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::api::manifest::device_manifest::SESSION_TOKEN_TTL_MS_DEFAULT;
+
+    #[test]
+    fn test_mint_and_verify() {
+        let tokens = SessionTokens::default();
+        let token = tokens.mint_at("app1", "session1", 1000);
+        let claims = tokens.verify_at(&token, 2000).unwrap();
+        assert_eq!(claims.app_id, "app1");
+        assert_eq!(claims.session_id, "session1");
+        assert_eq!(
+            tokens.verify_at(&token, 1000 + SESSION_TOKEN_TTL_MS_DEFAULT),
+            Err(SessionTokenError::Expired)
+        );
+        assert_eq!(
+            tokens.verify_at("app1", 2000),
+            Err(SessionTokenError::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_forged_tokens() {
+        let tokens = SessionTokens::default();
+        let token = tokens.mint_at("app1", "session1", 1000);
+        // a token signed with another key
+        assert_eq!(
+            SessionTokens::default().verify_at(&token, 2000),
+            Err(SessionTokenError::InvalidSignature)
+        );
+        // claims swapped under the original signature
+        let (_, signature) = token.split_once('.').unwrap();
+        let other = tokens.mint_at("app2", "session1", 1000);
+        let (payload, _) = other.split_once('.').unwrap();
+        assert_eq!(
+            tokens.verify_at(&format!("{}.{}", payload, signature), 2000),
+            Err(SessionTokenError::InvalidSignature)
+        );
+    }
+}
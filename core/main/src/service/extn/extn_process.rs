@@ -0,0 +1,355 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{
+    os::unix::fs::DirBuilderExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use ripple_sdk::{
+    api::{manifest::extn_manifest::ExtnSymbol, status_update::ExtnStatus},
+    async_channel::Receiver as CReceiver,
+    extn::{
+        client::extn_sender::ExtnSender,
+        extn_id::ExtnId,
+        ffi::{
+            ffi_channel::load_channel_builder,
+            ffi_library::{get_sdk_version, load_extn_library_metadata},
+            ffi_message::CExtnMessage,
+        },
+        ipc::{
+            ipc_channel::{bridge, host_channel},
+            ipc_message::{ExtnIpcFrame, ExtnIpcHello, EXTN_IPC_SOCKET_ENV},
+        },
+    },
+    libloading::Library,
+    log::{debug, error, info, warn},
+    tokio::{
+        self,
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::{UnixListener, UnixStream},
+        process::Command,
+        sync::{mpsc, oneshot},
+        time::timeout,
+    },
+    utils::error::RippleError,
+    uuid::Uuid,
+};
+
+/// Argument of the Ripple binary which hosts an extension channel instead of the gateway
+pub const EXTN_HOST_COMMAND: &str = "extn-host";
+
+const EXTN_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Stops the process of an extension started with [ExtnProcess::start]
+#[derive(Debug, Clone)]
+pub struct ExtnProcessStop(mpsc::Sender<oneshot::Sender<()>>);
+
+impl ExtnProcessStop {
+    /// Kills the process, returns once the messages of the extension are not read for it anymore
+    pub async fn stop(&self) {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        // the process already exited when the stop cannot be sent
+        if self.0.send(ack_tx).await.is_ok() {
+            let _ = ack_rx.await;
+        }
+    }
+}
+
+/// Runs extension channels in child processes of Ripple. The child is the Ripple binary started
+/// with [EXTN_HOST_COMMAND] and the path of the extension library, it connects back to a Unix
+/// socket whose messages are bridged to the channels of the extension. The socket lives in a
+/// directory only the user of Ripple can access and only the child is accepted on it. The
+/// extension reports an [ExtnStatus::Error] once its process exits unless it was stopped.
+pub struct ExtnProcess;
+
+impl ExtnProcess {
+    /// Starts the process hosting the channel, the returned stop kills the process so it can be
+    /// restarted.
+    pub fn start(
+        path: String,
+        symbol: ExtnSymbol,
+        extn_sender: ExtnSender,
+        extn_rx: CReceiver<CExtnMessage>,
+    ) -> Result<ExtnProcessStop, RippleError> {
+        let extn_id = extn_sender.get_cap();
+        let socket = Self::get_socket_path(&extn_id)?;
+        let listener = UnixListener::bind(&socket).map_err(|e| {
+            error!("Unable to bind {:?} for {} {:?}", socket, extn_id, e);
+            Self::remove_socket(&socket);
+            RippleError::BootstrapError
+        })?;
+        let exe = std::env::current_exe().map_err(|_| RippleError::BootstrapError)?;
+        let mut child = Command::new(exe)
+            .arg(EXTN_HOST_COMMAND)
+            .arg(&path)
+            .env(EXTN_IPC_SOCKET_ENV, &socket)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                error!("Unable to start the process of {} {:?}", extn_id, e);
+                Self::remove_socket(&socket);
+                RippleError::BootstrapError
+            })?;
+        let Some(pid) = child.id() else {
+            Self::remove_socket(&socket);
+            return Err(RippleError::BootstrapError);
+        };
+        info!("Started {} from {} in process {}", extn_id, path, pid);
+
+        let (stop_tx, mut stop_rx) = mpsc::channel::<oneshot::Sender<()>>(1);
+        tokio::spawn(async move {
+            let ack = tokio::select! {
+                _ = Self::serve(listener, pid, symbol, extn_sender.tx.clone(), extn_rx) => {
+                    warn!("{} disconnected", extn_id);
+                    None
+                }
+                status = child.wait() => {
+                    error!("Process of {} exited {:?}", extn_id, status);
+                    None
+                }
+                Some(ack) = stop_rx.recv() => {
+                    warn!("Stopping the process of {}", extn_id);
+                    Some(ack)
+                }
+            };
+            Self::remove_socket(&socket);
+            if let Err(e) = child.kill().await {
+                debug!("Process of {} already exited {:?}", extn_id, e);
+            }
+            match ack {
+                // the status is up to whoever stopped the process
+                Some(ack) => {
+                    let _ = ack.send(());
+                }
+                None => {
+                    if let Err(e) = extn_sender.send_event(ExtnStatus::Error, None) {
+                        error!("Unable to report the status of {} {:?}", extn_id, e);
+                    }
+                }
+            }
+        });
+        Ok(ExtnProcessStop(stop_tx))
+    }
+
+    /// Creates a directory with an unguessable name which only the user of Ripple can access, the
+    /// creation fails when the directory already exists.
+    fn get_socket_path(extn_id: &ExtnId) -> Result<PathBuf, RippleError> {
+        let dir = std::env::temp_dir().join(format!("ripple-extn-{}", Uuid::new_v4()));
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .map_err(|e| {
+                error!("Unable to create {:?} for {} {:?}", dir, extn_id, e);
+                RippleError::BootstrapError
+            })?;
+        Ok(dir.join(format!("{}.sock", extn_id.to_string().replace(':', "-"))))
+    }
+
+    fn remove_socket(socket: &Path) {
+        if let Some(dir) = socket.parent() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+
+    /// Accepts the connection of the process `pid`, connections of other processes are closed
+    async fn accept(listener: &UnixListener, pid: u32) -> std::io::Result<UnixStream> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            match stream.peer_cred().map(|cred| cred.pid()) {
+                Ok(Some(peer)) if peer as u32 == pid => return Ok(stream),
+                peer => warn!("Rejecting a connection of {:?}, expected {}", peer, pid),
+            }
+        }
+    }
+
+    async fn serve(
+        listener: UnixListener,
+        pid: u32,
+        symbol: ExtnSymbol,
+        main_sender: ripple_sdk::async_channel::Sender<CExtnMessage>,
+        extn_rx: CReceiver<CExtnMessage>,
+    ) {
+        let stream = match timeout(EXTN_CONNECT_TIMEOUT, Self::accept(&listener, pid)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => {
+                error!("Unable to accept {} {:?}", symbol.id, e);
+                return;
+            }
+            Err(_) => {
+                error!(
+                    "{} did not connect in {:?}",
+                    symbol.id, EXTN_CONNECT_TIMEOUT
+                );
+                return;
+            }
+        };
+        let (reader, mut writer) = stream.into_split();
+        let hello = ExtnIpcFrame::Hello(ExtnIpcHello::from(symbol)).to_line();
+        if let Err(e) = writer.write_all(hello.as_bytes()).await {
+            error!("Unable to send the hello {:?}", e);
+            return;
+        }
+        bridge(BufReader::new(reader).lines(), writer, extn_rx, main_sender).await;
+    }
+
+    /// Entry point of [EXTN_HOST_COMMAND], returns the exit code of the process
+    pub async fn host(args: &[String]) -> i32 {
+        let Some(path) = args.first() else {
+            eprintln!("Usage: ripple {} <extension library>", EXTN_HOST_COMMAND);
+            return exitcode::USAGE;
+        };
+        // the library has to outlive the channel
+        let library = match unsafe { Library::new(path) } {
+            Ok(library) => library,
+            Err(e) => {
+                error!("Unable to load {} {:?}", path, e);
+                return exitcode::SOFTWARE;
+            }
+        };
+        if let Some(metadata) = unsafe { load_extn_library_metadata(&library) } {
+            let sdk_version = get_sdk_version();
+            let mismatches: Vec<_> = metadata
+                .symbols
+                .iter()
+                .filter_map(|symbol| symbol.check_sdk_version(&sdk_version).err())
+                .collect();
+            if !mismatches.is_empty() {
+                for mismatch in mismatches {
+                    error!("Refusing to host {}: {}", path, mismatch);
+                }
+                return exitcode::CONFIG;
+            }
+        }
+        let builder = match unsafe { load_channel_builder(&library) } {
+            Ok(builder) => builder,
+            Err(_) => return exitcode::SOFTWARE,
+        };
+        match host_channel(&builder).await {
+            Ok(_) => exitcode::OK,
+            Err(e) => {
+                error!("Unable to host {} {:?}", path, e);
+                exitcode::SOFTWARE
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::{
+        async_channel::unbounded,
+        extn::{extn_id::ExtnClassId, ipc::ipc_message::ExtnIpcMessage},
+        tokio::io::AsyncReadExt,
+    };
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn test_stop() {
+        let (stop_tx, mut stop_rx) = mpsc::channel::<oneshot::Sender<()>>(1);
+        let stop = ExtnProcessStop(stop_tx);
+        let (done_tx, done_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let ack = stop_rx.recv().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let _ = done_tx.send(());
+            let _ = ack.send(());
+        });
+        // returns once the process task is done
+        stop.stop().await;
+        assert!(done_rx.await.is_ok());
+        // and right away when the task is gone
+        timeout(Duration::from_millis(100), stop.stop())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_socket_path() {
+        let extn_id = ExtnId::new_channel(ExtnClassId::Device, "test".to_owned());
+        let socket = ExtnProcess::get_socket_path(&extn_id).unwrap();
+        let dir = socket.parent().unwrap();
+        let mode = std::fs::metadata(dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        assert_ne!(socket, ExtnProcess::get_socket_path(&extn_id).unwrap());
+        ExtnProcess::remove_socket(&socket);
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_accept() {
+        let extn_id = ExtnId::new_channel(ExtnClassId::Device, "test".to_owned());
+        let socket = ExtnProcess::get_socket_path(&extn_id).unwrap();
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        // connections of other processes are closed
+        let mut other = UnixStream::connect(&socket).await.unwrap();
+        let accept = ExtnProcess::accept(&listener, std::process::id() + 1);
+        assert!(timeout(Duration::from_millis(100), accept).await.is_err());
+        assert_eq!(other.read(&mut [0; 1]).await.unwrap(), 0);
+
+        let _stream = UnixStream::connect(&socket).await.unwrap();
+        let accept = ExtnProcess::accept(&listener, std::process::id());
+        assert!(timeout(Duration::from_millis(100), accept).await.is_ok());
+        ExtnProcess::remove_socket(&socket);
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let extn_id = ExtnId::new_channel(ExtnClassId::Device, "test".to_owned());
+        let socket = ExtnProcess::get_socket_path(&extn_id).unwrap();
+        let listener = UnixListener::bind(&socket).unwrap();
+        let symbol = ExtnSymbol {
+            id: extn_id.to_string(),
+            uses: vec![],
+            fulfills: vec!["device_info".to_owned()],
+            config: None,
+        };
+        let (main_tx, main_rx) = unbounded();
+        let (_extn_tx, extn_rx) = unbounded();
+        tokio::spawn(ExtnProcess::serve(
+            listener,
+            std::process::id(),
+            symbol.clone(),
+            main_tx,
+            extn_rx,
+        ));
+
+        let (reader, mut writer) = UnixStream::connect(&socket).await.unwrap().into_split();
+        ExtnProcess::remove_socket(&socket);
+        let mut lines = BufReader::new(reader).lines();
+        let hello = lines.next_line().await.unwrap().unwrap();
+        assert_eq!(
+            ExtnIpcFrame::from_line(&hello).unwrap(),
+            ExtnIpcFrame::Hello(ExtnIpcHello::from(symbol))
+        );
+
+        let message = ExtnIpcMessage {
+            id: "1".to_owned(),
+            requestor: extn_id.to_string(),
+            target: "\"device_info\"".to_owned(),
+            target_id: String::new(),
+            payload: "{}".to_owned(),
+            ts: 0,
+        };
+        let line = ExtnIpcFrame::Message(message).to_line();
+        writer.write_all(line.as_bytes()).await.unwrap();
+        assert_eq!(main_rx.recv().await.unwrap().id, "1");
+    }
+}
@@ -0,0 +1,175 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use ripple_sdk::{
+    api::status_update::ExtnStatus,
+    extn::{client::extn_client::ExtnClient, extn_id::ExtnId},
+    log::{debug, error, info, warn},
+    tokio,
+};
+
+use crate::state::{extn_state::ExtnState, platform_state::PlatformState};
+
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+const PING_TIMEOUT_MS: u64 = 2000;
+const MAX_MISSED_PINGS: u32 = 3;
+
+/// Change in the health of an extension after a ping
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtnHealthChange {
+    Unhealthy,
+    Recovered,
+}
+
+/// Health of the extension channels as seen by the supervisor
+#[derive(Debug, Default)]
+pub struct ExtnHealth {
+    missed_pings: HashMap<String, u32>,
+    unhealthy: HashSet<String>,
+}
+
+impl ExtnHealth {
+    /// Records the outcome of a ping, an extension becomes unhealthy after [MAX_MISSED_PINGS]
+    /// missed pings in a row and recovers with the next answered ping.
+    pub fn record(&mut self, id: &str, answered: bool) -> Option<ExtnHealthChange> {
+        let missed = self.missed_pings.entry(id.to_owned()).or_default();
+        if answered {
+            *missed = 0;
+            self.unhealthy
+                .remove(id)
+                .then_some(ExtnHealthChange::Recovered)
+        } else {
+            *missed += 1;
+            if *missed == MAX_MISSED_PINGS {
+                self.unhealthy.insert(id.to_owned());
+                Some(ExtnHealthChange::Unhealthy)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Gives a restarted extension as many pings to come up as a new one, it stays unhealthy
+    /// until it answers.
+    pub fn reset(&mut self, id: &str) {
+        self.missed_pings.remove(id);
+    }
+}
+
+/// Pings every extension channel on an interval. An extension which misses
+/// [MAX_MISSED_PINGS] pings is marked unhealthy in the [ExtnClient], its contracts then resolve
+/// to the next extension by resolution priority or fail with `ExtnUnhealthy` instead of timing
+/// out. Channels hosted in a process are restarted, library channels recover once they answer.
+/// Libraries built against a `ripple_sdk` older than
+/// [ripple_sdk::extn::ffi::ffi_library::HEALTH_CHECK_SDK_VERSION] cannot answer and are not pinged.
+pub struct ExtnSupervisor;
+
+impl ExtnSupervisor {
+    pub fn start(state: PlatformState, extn_state: ExtnState) {
+        let client = state.get_client().get_extn_client();
+        info!("Starting extension supervisor");
+        tokio::spawn(async move {
+            let mut health = ExtnHealth::default();
+            let mut ticker = tokio::time::interval(PING_INTERVAL);
+            // the first tick completes immediately
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                for id in client.get_extn_ids() {
+                    if !extn_state.is_supervised(&id) {
+                        continue;
+                    }
+                    let answered = client.ping(&id, PING_TIMEOUT_MS).await.is_ok();
+                    if !answered {
+                        debug!("{} missed a ping", id);
+                    }
+                    if let Some(change) = health.record(&id, answered) {
+                        Self::on_health_change(&client, &extn_state, &mut health, &id, change)
+                            .await;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn on_health_change(
+        client: &ExtnClient,
+        extn_state: &ExtnState,
+        health: &mut ExtnHealth,
+        id: &str,
+        change: ExtnHealthChange,
+    ) {
+        let extn_id = ExtnId::try_from(id.to_owned()).ok();
+        match change {
+            ExtnHealthChange::Unhealthy => {
+                warn!("{} is unhealthy", id);
+                client.set_extn_health(id, false);
+                if let Some(extn_id) = extn_id {
+                    extn_state.update_extn_status(extn_id, ExtnStatus::Interrupted);
+                }
+                if extn_state.is_restartable(id) {
+                    match extn_state.restart_channel(id).await {
+                        Ok(_) => health.reset(id),
+                        Err(e) => error!("Unable to restart {} {:?}", id, e),
+                    }
+                }
+            }
+            ExtnHealthChange::Recovered => {
+                info!("{} recovered", id);
+                client.set_extn_health(id, true);
+                if let Some(extn_id) = extn_id {
+                    extn_state.update_extn_status(extn_id, ExtnStatus::Ready);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extn_health() {
+        let mut health = ExtnHealth::default();
+        let id = "ripple:channel:device:thunder";
+        for _ in 1..MAX_MISSED_PINGS {
+            assert_eq!(health.record(id, false), None);
+        }
+        assert_eq!(health.record(id, false), Some(ExtnHealthChange::Unhealthy));
+        // already reported
+        assert_eq!(health.record(id, false), None);
+        assert_eq!(health.record(id, true), Some(ExtnHealthChange::Recovered));
+        assert_eq!(health.record(id, true), None);
+
+        // a restarted extension is reported again once it misses the pings again
+        for _ in 0..MAX_MISSED_PINGS {
+            health.record(id, false);
+        }
+        health.reset(id);
+        for _ in 1..MAX_MISSED_PINGS {
+            assert_eq!(health.record(id, false), None);
+        }
+        assert_eq!(health.record(id, false), Some(ExtnHealthChange::Unhealthy));
+        health.reset(id);
+        assert_eq!(health.record(id, true), Some(ExtnHealthChange::Recovered));
+    }
+}
@@ -15,4 +15,6 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
+pub mod extn_process;
+pub mod extn_supervisor;
 pub mod ripple_client;
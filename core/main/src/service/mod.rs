@@ -19,5 +19,8 @@ pub mod apps;
 pub mod context_manager;
 pub mod data_governance;
 pub mod extn;
+pub mod replay;
+pub mod shutdown_coordinator;
 pub mod telemetry_builder;
 pub mod user_grants;
+pub mod watchdog;
@@ -0,0 +1,234 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{LineWriter, Write},
+    time::Duration,
+};
+
+use futures::{future::join_all, SinkExt, StreamExt};
+use ripple_sdk::{
+    api::observability::recording::{
+        get_firebolt_exchanges, get_request_key, get_response_key, read_recording, FireboltExchange,
+    },
+    log::{error, info, warn},
+    tokio::time::{sleep_until, timeout, Instant},
+};
+use serde::Serialize;
+use serde_json::Value;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use crate::state::platform_state::PlatformState;
+
+const REPLAY_RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Response of a replayed request which differs from the recorded one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayDiff {
+    pub app_id: String,
+    pub request: Value,
+    pub expected: Option<Value>,
+    pub actual: Option<Value>,
+}
+
+/// Replays the Firebolt requests of a recording session against the internal gateway, one
+/// connection per recorded connection keeping the recorded pacing, and reports the responses
+/// which differ in `<replay_file>.diff.jsonl`. Apps connect with their `appId`, which needs the
+/// `session_tokens.dev_mode` flag.
+pub struct ReplayService;
+
+impl ReplayService {
+    pub async fn start(state: PlatformState) {
+        let manifest = state.get_device_manifest();
+        let Some(path) = manifest.get_recording().replay_file else {
+            return;
+        };
+        let entries = match File::open(&path).map(read_recording) {
+            Ok(Ok(entries)) => entries,
+            _ => {
+                error!("Unable to read the recording {}", path);
+                return;
+            }
+        };
+        if !state.app_manager_state.is_session_token_dev_mode() {
+            warn!("Replaying {} needs session_tokens.dev_mode", path);
+        }
+        let gateway = manifest.get_internal_ws_configuration().gateway;
+        let exchanges = get_firebolt_exchanges(&entries);
+        info!("Replaying {} requests of {}", exchanges.len(), path);
+
+        let connections = Self::get_connections(exchanges.clone());
+        let diffs: Vec<ReplayDiff> = join_all(
+            connections
+                .into_iter()
+                .map(|exchanges| Self::replay_connection(&gateway, exchanges)),
+        )
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let report = format!("{}.diff.jsonl", path);
+        if let Err(e) = Self::write_report(&report, &diffs) {
+            error!("Unable to write the replay report {} {:?}", report, e);
+        }
+        info!(
+            "Replayed {} requests of {}, {} responses differ, see {}",
+            exchanges.len(),
+            path,
+            diffs.len(),
+            report
+        );
+    }
+
+    /// Groups the exchanges by connection, in the order the connections were recorded
+    fn get_connections(exchanges: Vec<FireboltExchange>) -> Vec<Vec<FireboltExchange>> {
+        let mut connections: Vec<Vec<FireboltExchange>> = Vec::new();
+        let mut indexes: HashMap<String, usize> = HashMap::new();
+        for exchange in exchanges {
+            let index = *indexes
+                .entry(exchange.connection_id.clone())
+                .or_insert_with(|| {
+                    connections.push(Vec::new());
+                    connections.len() - 1
+                });
+            connections[index].push(exchange);
+        }
+        connections
+    }
+
+    async fn replay_connection(gateway: &str, exchanges: Vec<FireboltExchange>) -> Vec<ReplayDiff> {
+        let Some(first) = exchanges.first() else {
+            return Vec::new();
+        };
+        let url = format!("ws://{}/?appId={}", gateway, first.app_id);
+        let (mut sender, mut receiver) = match connect_async(url.as_str()).await {
+            Ok((ws_stream, _)) => ws_stream.split(),
+            Err(e) => {
+                error!("Unable to connect to {} {:?}", url, e);
+                return exchanges
+                    .iter()
+                    .filter_map(|exchange| Self::get_diff(exchange, None))
+                    .collect();
+            }
+        };
+
+        let start = Instant::now();
+        let first_ms = first.elapsed_ms;
+        let mut diffs = Vec::new();
+        for exchange in &exchanges {
+            let offset = exchange.elapsed_ms.saturating_sub(first_ms);
+            sleep_until(start + Duration::from_millis(offset)).await;
+            if let Err(e) = sender
+                .send(Message::Text(exchange.request.to_string()))
+                .await
+            {
+                error!("Unable to replay {} {:?}", exchange.request, e);
+                break;
+            }
+            let Some(key) = get_request_key(&exchange.request) else {
+                continue;
+            };
+            // events and other responses which arrive meanwhile are skipped
+            let actual = timeout(REPLAY_RESPONSE_TIMEOUT, async {
+                while let Some(Ok(message)) = receiver.next().await {
+                    let Message::Text(text) = message else {
+                        continue;
+                    };
+                    if let Ok(frame) = serde_json::from_str::<Value>(&text) {
+                        if get_response_key(&frame).as_ref() == Some(&key) {
+                            return Some(frame);
+                        }
+                    }
+                }
+                None
+            })
+            .await
+            .ok()
+            .flatten();
+            if let Some(diff) = Self::get_diff(exchange, actual) {
+                diffs.push(diff);
+            }
+        }
+        let _ = sender.close().await;
+        diffs
+    }
+
+    fn get_diff(exchange: &FireboltExchange, actual: Option<Value>) -> Option<ReplayDiff> {
+        if exchange.response == actual {
+            return None;
+        }
+        Some(ReplayDiff {
+            app_id: exchange.app_id.clone(),
+            request: exchange.request.clone(),
+            expected: exchange.response.clone(),
+            actual,
+        })
+    }
+
+    fn write_report(path: &str, diffs: &[ReplayDiff]) -> std::io::Result<()> {
+        let mut writer = LineWriter::new(File::create(path)?);
+        for diff in diffs {
+            writeln!(writer, "{}", serde_json::to_string(diff)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn exchange(connection_id: &str, id: u64, response: Option<Value>) -> FireboltExchange {
+        FireboltExchange {
+            app_id: "app1".to_owned(),
+            connection_id: connection_id.to_owned(),
+            elapsed_ms: id,
+            request: json!({"jsonrpc":"2.0","id":id,"method":"device.name"}),
+            response,
+        }
+    }
+
+    #[test]
+    fn test_get_connections() {
+        let connections = ReplayService::get_connections(vec![
+            exchange("c2", 1, None),
+            exchange("c1", 2, None),
+            exchange("c2", 3, None),
+        ]);
+        assert_eq!(connections.len(), 2);
+        assert_eq!(connections[0].len(), 2);
+        assert_eq!(connections[0][1].elapsed_ms, 3);
+        assert_eq!(connections[1][0].connection_id, "c1");
+    }
+
+    #[test]
+    fn test_get_diff() {
+        let response = json!({"jsonrpc":"2.0","id":1,"result":"Living Room"});
+        let recorded = exchange("c1", 1, Some(response.clone()));
+        assert!(ReplayService::get_diff(&recorded, Some(response)).is_none());
+
+        let other = json!({"jsonrpc":"2.0","id":1,"result":"Kitchen"});
+        let diff = ReplayService::get_diff(&recorded, Some(other.clone())).unwrap();
+        assert_eq!(diff.expected, recorded.response);
+        assert_eq!(diff.actual, Some(other));
+        assert!(ReplayService::get_diff(&recorded, None).is_some());
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::time::Duration;
+
+use futures::future::join_all;
+use ripple_sdk::{
+    api::{
+        apps::{AppMethod, AppRequest, AppResponse, CloseReason},
+        firebolt::fb_lifecycle::LifecycleState,
+    },
+    log::{error, info, warn},
+    tokio::{
+        self,
+        signal::unix::{signal, SignalKind},
+        sync::oneshot,
+        time::Instant,
+    },
+};
+
+use crate::{service::watchdog::Watchdog, state::platform_state::PlatformState};
+
+const DRAIN_POLL_INTERVAL_MS: u64 = 50;
+
+/// Coordinates the shutdown of Ripple once SIGTERM or SIGINT is received.
+///
+/// 1. New websocket connections are no longer accepted.
+/// 2. Loaded apps are closed and unloaded through the `DelegatedLauncherHandler`, the requests
+///    in flight to the broker endpoints and the privacy and settings writes in progress are drained.
+/// 3. File backed permissions and grants and the session recording are flushed to disk.
+/// 4. Extensions are told about the shutdown and persist their own state.
+///
+/// The second and last steps are bounded by the `shutdown_timeout_ms` of the device manifest.
+pub struct ShutdownCoordinator;
+
+impl ShutdownCoordinator {
+    /// Resolves once SIGTERM or SIGINT is received
+    pub async fn wait_for_signal() {
+        let mut sigterm = match signal(SignalKind::terminate()) {
+            Ok(sigterm) => sigterm,
+            Err(e) => {
+                error!("Couldnt listen to SIGTERM {:?}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                info!("Received SIGINT");
+                return;
+            }
+        };
+        tokio::select! {
+            _ = sigterm.recv() => info!("Received SIGTERM"),
+            _ = tokio::signal::ctrl_c() => info!("Received SIGINT"),
+        }
+    }
+
+    pub async fn shutdown(state: &PlatformState) {
+        let timeout_ms = state.get_device_manifest().get_shutdown_timeout_ms();
+        info!("Shutting down Ripple within {}ms", timeout_ms);
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+        Watchdog::notify_status("Shutting down");
+        state.shutdown_state.shutdown();
+
+        if tokio::time::timeout_at(deadline, Self::drain(state))
+            .await
+            .is_err()
+        {
+            warn!("Shutdown deadline elapsed before apps, requests and writes were drained");
+        }
+
+        state.cap_state.permitted_state.flush();
+        state.cap_state.grant_state.flush();
+        if tokio::time::timeout_at(deadline, state.recording_state.flush())
+            .await
+            .is_err()
+        {
+            warn!("Shutdown deadline elapsed before the recording was flushed");
+        }
+        // extensions get the time left to persist their state
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        state
+            .get_client()
+            .get_extn_client()
+            .shutdown(remaining.as_millis() as u64)
+            .await;
+        info!("Shutdown complete");
+    }
+
+    async fn drain(state: &PlatformState) {
+        let apps = state.app_manager_state.get_app_ids();
+        join_all(apps.into_iter().map(|app_id| async move {
+            Self::send_app_request(state, AppMethod::Close(app_id.clone(), CloseReason::Done))
+                .await;
+            Self::send_app_request(
+                state,
+                AppMethod::SetState(app_id, LifecycleState::Unloading),
+            )
+            .await;
+        }))
+        .await;
+
+        while state.endpoint_state.has_pending_requests()
+            || state.shutdown_state.has_pending_writes()
+        {
+            tokio::time::sleep(Duration::from_millis(DRAIN_POLL_INTERVAL_MS)).await;
+        }
+    }
+
+    async fn send_app_request(state: &PlatformState, method: AppMethod) {
+        let (app_resp_tx, app_resp_rx) = oneshot::channel::<AppResponse>();
+        let app_request = AppRequest::new(method.clone(), app_resp_tx);
+        if let Err(e) = state.get_client().send_app_request(app_request) {
+            error!("Error sending {:?} during shutdown {:?}", method, e);
+            return;
+        }
+        if let Ok(Err(e)) = app_resp_rx.await {
+            warn!("{:?} failed during shutdown {:?}", method, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_tdk::utils::test_utils::Mockable;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let state = PlatformState::mock();
+        ShutdownCoordinator::shutdown(&state).await;
+        assert!(state.shutdown_state.is_shutting_down());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_waits_for_pending_writes() {
+        let state = PlatformState::mock();
+        let write = state.shutdown_state.start_write();
+        let written = Arc::new(AtomicBool::new(false));
+        let written_c = written.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            written_c.store(true, Ordering::SeqCst);
+            drop(write);
+        });
+        ShutdownCoordinator::shutdown(&state).await;
+        assert!(written.load(Ordering::SeqCst));
+    }
+}
@@ -103,6 +103,12 @@ impl GrantState {
         }
     }
 
+    /// Writes the device and app grants to disk, waits for writes in progress
+    pub fn flush(&self) {
+        self.device_grants.write().unwrap().sync();
+        self.grant_app_map.write().unwrap().sync();
+    }
+
     pub fn cleanup_user_grants(&self) {
         self.delete_all_expired_entries();
         self.delete_all_entries_for_lifespan(&GrantLifespan::PowerActive);
@@ -2014,7 +2020,10 @@ mod tests {
                     String::from(PIN_CHALLENGE_EVENT),
                     String::from(PIN_CHALLENGE_EVENT),
                     ctx_c.clone(),
-                    ListenRequest { listen: true },
+                    ListenRequest {
+                        listen: true,
+                        replay: None,
+                    },
                 )
                 .await;
 
@@ -2024,7 +2033,10 @@ mod tests {
                     String::from(ACK_CHALLENGE_EVENT),
                     String::from(ACK_CHALLENGE_EVENT),
                     ctx_c.clone(),
-                    ListenRequest { listen: true },
+                    ListenRequest {
+                        listen: true,
+                        replay: None,
+                    },
                 )
                 .await;
 
@@ -0,0 +1,196 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{fmt, time::Duration};
+
+use ripple_sdk::{
+    log::{debug, error, info, warn},
+    tokio,
+};
+
+use crate::{
+    firebolt::firebolt_gateway::FireboltGatewayCommand,
+    state::{liveness_state::LivenessProbe, platform_state::PlatformState},
+};
+
+const THUNDER_ENDPOINT: &str = "thunder";
+
+/// Outcome of the liveness probes
+#[derive(Debug, Clone, PartialEq)]
+pub struct LivenessReport {
+    pub gateway: bool,
+    pub broker_forwarder: bool,
+    /// None when no Thunder endpoint is configured
+    pub thunder: Option<bool>,
+}
+
+impl LivenessReport {
+    pub fn is_alive(&self) -> bool {
+        self.gateway && self.broker_forwarder && self.thunder.unwrap_or(true)
+    }
+}
+
+impl fmt::Display for LivenessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = |alive: bool| if alive { "ok" } else { "unresponsive" };
+        write!(
+            f,
+            "gateway {}, broker forwarder {}",
+            status(self.gateway),
+            status(self.broker_forwarder)
+        )?;
+        match self.thunder {
+            Some(true) => write!(f, ", thunder connected"),
+            Some(false) => write!(f, ", thunder disconnected"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sends systemd watchdog heartbeats while the gateway command loop, the broker output forwarder
+/// and the Thunder connection are alive. Each loop is probed every half watchdog interval and has
+/// to answer within a full interval, a wedged Ripple stops the heartbeats and gets restarted by
+/// systemd. Only active with the `sysd` feature when the unit sets `WatchdogSec`.
+pub struct Watchdog;
+
+impl Watchdog {
+    pub fn start(state: PlatformState) {
+        let Some(interval) = Self::get_interval() else {
+            debug!("systemd watchdog is not enabled");
+            return;
+        };
+        info!("Starting watchdog with interval {:?}", interval);
+        tokio::spawn(async move {
+            Self::probe(&state);
+            let mut ticker = tokio::time::interval(interval);
+            // the first tick completes immediately
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let report = Self::check(&state, interval * 2);
+                if report.is_alive() {
+                    Self::notify_alive(&report.to_string());
+                } else {
+                    warn!("Liveness probe failed: {}", report);
+                    Self::notify_status(&report.to_string());
+                }
+                Self::probe(&state);
+            }
+        });
+    }
+
+    /// Checks the answers to the previous probes
+    pub fn check(state: &PlatformState, max_age: Duration) -> LivenessReport {
+        let liveness = &state.liveness_state;
+        LivenessReport {
+            gateway: liveness.is_alive(LivenessProbe::Gateway, max_age),
+            broker_forwarder: liveness.is_alive(LivenessProbe::BrokerForwarder, max_age),
+            thunder: state.endpoint_state.is_endpoint_connected(THUNDER_ENDPOINT),
+        }
+    }
+
+    /// Sends the next probes
+    pub fn probe(state: &PlatformState) {
+        if let Err(e) = state
+            .get_client()
+            .send_gateway_command(FireboltGatewayCommand::Ping)
+        {
+            error!("Couldnt probe the gateway {:?}", e);
+        }
+        state.endpoint_state.probe_forwarder();
+    }
+
+    #[cfg(feature = "sysd")]
+    fn get_interval() -> Option<Duration> {
+        let mut usec = 0;
+        if sd_notify::watchdog_enabled(false, &mut usec) && usec > 0 {
+            Some(Duration::from_micros(usec / 2))
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(feature = "sysd"))]
+    fn get_interval() -> Option<Duration> {
+        None
+    }
+
+    #[cfg(feature = "sysd")]
+    fn notify_alive(status: &str) {
+        if let Err(e) = sd_notify::notify(
+            false,
+            &[
+                sd_notify::NotifyState::Watchdog,
+                sd_notify::NotifyState::Status(status),
+            ],
+        ) {
+            error!("Couldnt notify systemd watchdog {:?}", e);
+        }
+    }
+
+    #[cfg(not(feature = "sysd"))]
+    fn notify_alive(status: &str) {
+        debug!("alive: {}", status);
+    }
+
+    /// Reports a free form status like the current boot step to systemd
+    #[cfg(feature = "sysd")]
+    pub fn notify_status(status: &str) {
+        if let Err(e) = sd_notify::notify(false, &[sd_notify::NotifyState::Status(status)]) {
+            debug!("Couldnt notify systemd status {:?}", e);
+        }
+    }
+
+    #[cfg(not(feature = "sysd"))]
+    pub fn notify_status(status: &str) {
+        debug!("status: {}", status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_tdk::utils::test_utils::Mockable;
+
+    #[tokio::test]
+    async fn test_check() {
+        let state = PlatformState::mock();
+        let max_age = Duration::from_secs(1);
+        let report = Watchdog::check(&state, max_age);
+        assert!(!report.is_alive());
+
+        state.liveness_state.ack(LivenessProbe::Gateway);
+        state.liveness_state.ack(LivenessProbe::BrokerForwarder);
+        let report = Watchdog::check(&state, max_age);
+        assert!(report.is_alive());
+        assert_eq!(report.to_string(), "gateway ok, broker forwarder ok");
+    }
+
+    #[test]
+    fn test_report() {
+        let report = LivenessReport {
+            gateway: true,
+            broker_forwarder: false,
+            thunder: Some(false),
+        };
+        assert!(!report.is_alive());
+        assert_eq!(
+            report.to_string(),
+            "gateway ok, broker forwarder unresponsive, thunder disconnected"
+        );
+    }
+}
@@ -103,6 +103,7 @@ impl CapState {
                 call_context,
                 ListenRequest {
                     listen: request.listen,
+                    replay: None,
                 },
             )
         }
@@ -75,7 +75,12 @@ impl PermittedState {
         perms.sync();
     }
 
-    fn get_all_permissions(&self) -> HashMap<String, Vec<FireboltPermission>> {
+    /// Writes the permissions to disk, waits for a write in progress
+    pub fn flush(&self) {
+        self.permitted.write().unwrap().sync();
+    }
+
+    pub fn get_all_permissions(&self) -> HashMap<String, Vec<FireboltPermission>> {
         self.permitted.read().unwrap().value.clone()
     }
     fn has_cached_permissions(&self, app_id: &String) -> bool {
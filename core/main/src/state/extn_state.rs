@@ -16,7 +16,7 @@
 //
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, RwLock},
     thread,
 };
@@ -27,19 +27,22 @@ use ripple_sdk::{
         manifest::extn_manifest::{ExtnManifest, ExtnManifestEntry, ExtnSymbol},
         status_update::ExtnStatus,
     },
-    async_channel::Sender as CSender,
+    async_channel::{Receiver as CReceiver, Sender as CSender},
     extn::{
         client::extn_sender::ExtnSender,
         extn_id::ExtnId,
         ffi::{ffi_channel::ExtnChannel, ffi_library::ExtnMetadata, ffi_message::CExtnMessage},
     },
     libloading::Library,
-    log::info,
+    log::{info, warn},
     tokio::sync::mpsc,
     utils::error::RippleError,
 };
 
-use crate::service::extn::ripple_client::RippleClient;
+use crate::service::extn::{
+    extn_process::{ExtnProcess, ExtnProcessStop},
+    ripple_client::RippleClient,
+};
 
 use super::bootstrap_state::ChannelsState;
 
@@ -108,9 +111,28 @@ impl LoadedLibrary {
     }
 }
 
+/// Where the channel of an extension runs
+#[derive(Debug)]
+pub enum ExtnChannelSource {
+    /// Channel of a library loaded by Ripple, started on a thread of Ripple
+    Library(Box<ExtnChannel>),
+    /// Path of the library hosting the channel in a child process
+    Process(String),
+}
+
+/// Channel hosted in a child process, kept so the process can be restarted
+#[derive(Debug, Clone)]
+pub struct ExtnProcessChannel {
+    path: String,
+    symbol: ExtnSymbol,
+    extn_sender: ExtnSender,
+    extn_rx: CReceiver<CExtnMessage>,
+    stop: ExtnProcessStop,
+}
+
 #[derive(Debug)]
 pub struct PreLoadedExtnChannel {
-    pub channel: Box<ExtnChannel>,
+    pub channel: ExtnChannelSource,
     pub extn_id: ExtnId,
     pub symbol: ExtnSymbol,
 }
@@ -124,9 +146,14 @@ pub struct ExtnState {
     pub loaded_libraries: Arc<RwLock<Vec<LoadedLibrary>>>,
     pub device_channels: Arc<RwLock<Vec<PreLoadedExtnChannel>>>,
     pub deferred_channels: Arc<RwLock<Vec<PreLoadedExtnChannel>>>,
+    /// Optional extensions skipped while bootstrapping, their resolution entries are excluded
+    pub excluded_extns: Arc<RwLock<Vec<ExtnManifestEntry>>>,
+    process_channels: Arc<RwLock<HashMap<String, ExtnProcessChannel>>>,
     extn_status_map: Arc<RwLock<HashMap<String, ExtnStatus>>>,
     extn_status_listeners: Arc<RwLock<HashMap<String, mpsc::Sender<ExtnStatus>>>>,
     pub extn_methods: Arc<RwLock<Methods>>,
+    /// Extensions built against a `ripple_sdk` which does not answer health checks
+    unsupervised_extns: Arc<RwLock<HashSet<String>>>,
 }
 
 impl ExtnState {
@@ -137,9 +164,12 @@ impl ExtnState {
             loaded_libraries: Arc::new(RwLock::new(Vec::new())),
             device_channels: Arc::new(RwLock::new(Vec::new())),
             deferred_channels: Arc::new(RwLock::new(Vec::new())),
+            excluded_extns: Arc::new(RwLock::new(Vec::new())),
+            process_channels: Arc::new(RwLock::new(HashMap::new())),
             extn_status_map: Arc::new(RwLock::new(HashMap::new())),
             extn_status_listeners: Arc::new(RwLock::new(HashMap::new())),
             extn_methods: Arc::new(RwLock::new(Methods::new())),
+            unsupervised_extns: Arc::new(RwLock::new(HashSet::new())),
         }
     }
 
@@ -148,6 +178,28 @@ impl ExtnState {
         let _ = extn_status_map.insert(id.to_string(), status);
     }
 
+    /// Returns the entries of the manifest as bootstrapped, optional entries which were skipped
+    /// have their resolution entries excluded
+    pub fn get_extn_entries(&self, manifest: &ExtnManifest) -> Vec<ExtnManifestEntry> {
+        let excluded_extns = self.excluded_extns.read().unwrap();
+        manifest
+            .extns
+            .iter()
+            .map(|entry| {
+                excluded_extns
+                    .iter()
+                    .find(|excluded| excluded.path == entry.path)
+                    .unwrap_or(entry)
+                    .clone()
+            })
+            .collect()
+    }
+
+    /// Returns the last status of each extension which reported one, keyed by extension id
+    pub fn get_extn_statuses(&self) -> HashMap<String, ExtnStatus> {
+        self.extn_status_map.read().unwrap().clone()
+    }
+
     pub fn is_extn_ready(&self, extn_id: ExtnId) -> bool {
         if let Some(ExtnStatus::Ready) = self
             .extn_status_map
@@ -201,14 +253,75 @@ impl ExtnState {
             symbol.config.clone(),
         );
         let (extn_tx, extn_rx) = ChannelsState::get_iec_channel();
-        let extn_channel = channel.channel;
-        thread::spawn(move || {
-            (extn_channel.start)(extn_sender, extn_rx);
-        });
+        match channel.channel {
+            ExtnChannelSource::Library(extn_channel) => {
+                thread::spawn(move || {
+                    (extn_channel.start)(extn_sender, extn_rx);
+                });
+            }
+            ExtnChannelSource::Process(path) => {
+                let stop = ExtnProcess::start(
+                    path.clone(),
+                    symbol.clone(),
+                    extn_sender.clone(),
+                    extn_rx.clone(),
+                )?;
+                self.process_channels.write().unwrap().insert(
+                    extn_id.to_string(),
+                    ExtnProcessChannel {
+                        path,
+                        symbol: symbol.clone(),
+                        extn_sender,
+                        extn_rx,
+                        stop,
+                    },
+                );
+            }
+        }
         client.add_extn_sender(extn_id, symbol, extn_tx);
         Ok(())
     }
 
+    /// Channels hosted in a process can be restarted, channels of a library loaded by Ripple run
+    /// on a thread which cannot be stopped.
+    /// Excludes an extension from the health checks of the [crate::service::extn::extn_supervisor::ExtnSupervisor]
+    pub fn set_unsupervised(&self, extn_id: &str) {
+        self.unsupervised_extns
+            .write()
+            .unwrap()
+            .insert(extn_id.to_owned());
+    }
+
+    pub fn is_supervised(&self, extn_id: &str) -> bool {
+        !self.unsupervised_extns.read().unwrap().contains(extn_id)
+    }
+
+    pub fn is_restartable(&self, extn_id: &str) -> bool {
+        self.process_channels.read().unwrap().contains_key(extn_id)
+    }
+
+    /// Kills the process hosting the channel and starts a new one which is connected to the same
+    /// senders, so the extension keeps its contracts. The new process starts once the old one no
+    /// longer reads the messages of the extension.
+    pub async fn restart_channel(&self, extn_id: &str) -> Result<(), RippleError> {
+        let Some(channel) = self.process_channels.read().unwrap().get(extn_id).cloned() else {
+            return Err(RippleError::NotAvailable);
+        };
+        warn!("Restarting the process of {}", extn_id);
+        channel.stop.stop().await;
+        let stop = ExtnProcess::start(
+            channel.path.clone(),
+            channel.symbol.clone(),
+            channel.extn_sender.clone(),
+            channel.extn_rx.clone(),
+        )?;
+        self.process_channels
+            .write()
+            .unwrap()
+            .insert(extn_id.to_owned(), ExtnProcessChannel { stop, ..channel });
+        Ok(())
+    }
+
     pub fn extend_methods(&self, methods: Methods) {
         let mut methods_state = self.extn_methods.write().unwrap();
         let _ = methods_state.merge(methods);
@@ -0,0 +1,68 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+/// Long running loops of Ripple which answer liveness probes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LivenessProbe {
+    /// Command loop of the `FireboltGateway`
+    Gateway,
+    /// Response loop of the `BrokerOutputForwarder`
+    BrokerForwarder,
+}
+
+/// Last time each loop answered a liveness probe
+#[derive(Debug, Clone, Default)]
+pub struct LivenessState {
+    acks: Arc<RwLock<HashMap<LivenessProbe, Instant>>>,
+}
+
+impl LivenessState {
+    pub fn ack(&self, probe: LivenessProbe) {
+        self.acks.write().unwrap().insert(probe, Instant::now());
+    }
+
+    /// Returns true when the loop answered a probe within the given age
+    pub fn is_alive(&self, probe: LivenessProbe, max_age: Duration) -> bool {
+        self.acks
+            .read()
+            .unwrap()
+            .get(&probe)
+            .map_or(false, |ack| ack.elapsed() <= max_age)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_liveness() {
+        let state = LivenessState::default();
+        assert!(!state.is_alive(LivenessProbe::Gateway, Duration::from_secs(1)));
+        state.ack(LivenessProbe::Gateway);
+        assert!(state.is_alive(LivenessProbe::Gateway, Duration::from_secs(1)));
+        assert!(!state.is_alive(LivenessProbe::BrokerForwarder, Duration::from_secs(1)));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!state.is_alive(LivenessProbe::Gateway, Duration::from_millis(10)));
+    }
+}
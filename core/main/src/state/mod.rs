@@ -17,11 +17,15 @@
 
 pub mod bootstrap_state;
 pub mod extn_state;
+pub mod liveness_state;
 pub mod metrics_state;
 pub mod openrpc_state;
 pub mod platform_state;
+pub mod rate_limit_state;
+pub mod recording_state;
 pub mod ripple_cache;
 pub mod session_state;
+pub mod shutdown_state;
 pub mod cap {
     pub mod cap_state;
     pub mod generic_cap_state;
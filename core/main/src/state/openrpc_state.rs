@@ -121,11 +121,22 @@ impl OpenRpcState {
         provider_registrations: Vec<String>,
     ) -> OpenRpcState {
         let open_rpc_path = load_firebolt_open_rpc_path().expect("Need valid open-rpc file");
-        let version_manifest: FireboltVersionManifest = serde_json::from_str(&open_rpc_path)
+        Self::new_with_open_rpc(&open_rpc_path, exclusory, extn_sdks, provider_registrations)
+    }
+
+    /// Builds the state from the given Firebolt open rpc document instead of the one
+    /// installed on the device.
+    pub fn new_with_open_rpc(
+        open_rpc_path: &str,
+        exclusory: Option<ExclusoryImpl>,
+        extn_sdks: Vec<String>,
+        provider_registrations: Vec<String>,
+    ) -> OpenRpcState {
+        let version_manifest: FireboltVersionManifest = serde_json::from_str(open_rpc_path)
             .expect("Failed parsing FireboltVersionManifest from open RPC file");
         let firebolt_open_rpc: FireboltOpenRpc = version_manifest.clone().into();
         let ripple_open_rpc: FireboltOpenRpc = FireboltOpenRpc::default();
-        let openrpc_validator: FireboltOpenRpcValidator = serde_json::from_str(&open_rpc_path)
+        let openrpc_validator: FireboltOpenRpcValidator = serde_json::from_str(open_rpc_path)
             .expect("Failed parsing FireboltOpenRpcValidator from open RPC file");
         let mut rpc_method_validator = RpcMethodValidator::new();
         rpc_method_validator.add_schema(openrpc_validator);
@@ -228,6 +239,12 @@ impl OpenRpcState {
         cap_map.extend(caps);
     }
 
+    /// Adds methods which are only served on the internal gateway
+    pub fn extend_ripple_caps(&self, caps: HashMap<String, CapabilitySet>) {
+        let mut cap_map = self.ripple_cap_map.write().unwrap();
+        cap_map.extend(caps);
+    }
+
     pub fn extend_policies(&self, policies: HashMap<String, CapabilityPolicy>) {
         let mut cap_policies = self.cap_policies.write().unwrap();
         cap_policies.extend(policies);
@@ -327,6 +344,18 @@ impl OpenRpcState {
         *self.provider_relation_map.write().unwrap() = provider_relation_map;
     }
 
+    /// Names of the methods of the Firebolt and extension open rpc documents, with the
+    /// module in lowercase
+    pub fn get_method_names(&self) -> Vec<String> {
+        let extended_rpc = self.extended_rpc.read().unwrap();
+        self.open_rpc
+            .methods
+            .iter()
+            .chain(extended_rpc.iter().flat_map(|rpc| rpc.methods.iter()))
+            .map(|m| FireboltOpenRpcMethod::name_with_lowercase_module(&m.name))
+            .collect()
+    }
+
     pub fn get_version(&self) -> FireboltSemanticVersion {
         self.open_rpc.info.clone()
     }
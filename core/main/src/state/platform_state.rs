@@ -37,7 +37,10 @@ use ripple_sdk::{
 use std::collections::HashMap;
 
 use crate::{
-    broker::{endpoint_broker::EndpointBrokerState, rules_engine::RuleEngine},
+    broker::{
+        endpoint_broker::EndpointBrokerState, rule_context::RulePlatformContext,
+        rules_engine::RuleEngine,
+    },
     firebolt::rpc_router::RouterState,
     service::{
         apps::{
@@ -50,8 +53,9 @@ use crate::{
 };
 
 use super::{
-    cap::cap_state::CapState, metrics_state::MetricsState, openrpc_state::OpenRpcState,
-    ripple_cache::RippleCache, session_state::SessionState,
+    cap::cap_state::CapState, liveness_state::LivenessState, metrics_state::MetricsState,
+    openrpc_state::OpenRpcState, rate_limit_state::RateLimitState, recording_state::RecordingState,
+    ripple_cache::RippleCache, session_state::SessionState, shutdown_state::ShutdownState,
 };
 
 /// Platform state encapsulates the internal state of the Ripple Main application.
@@ -109,6 +113,10 @@ pub struct PlatformState {
     pub ripple_cache: RippleCache,
     pub version: Option<String>,
     pub endpoint_state: EndpointBrokerState,
+    pub rate_limit_state: RateLimitState,
+    pub shutdown_state: ShutdownState,
+    pub liveness_state: LivenessState,
+    pub recording_state: RecordingState,
 }
 
 impl PlatformState {
@@ -125,16 +133,27 @@ impl PlatformState {
         let extn_sdks = extn_manifest.extn_sdks.clone();
         let provider_registations = extn_manifest.provider_registrations.clone();
         let metrics_state = MetricsState::default();
+        let session_state = SessionState::default();
+        let app_manager_state = AppManagerState::new(
+            &manifest.configuration.saved_dir,
+            &manifest.get_session_tokens(),
+        );
+        let recording_state = RecordingState::new(manifest.get_recording().record_file);
+        let platform_context = RulePlatformContext::new(
+            session_state.clone(),
+            app_manager_state.clone(),
+            metrics_state.clone(),
+        );
         Self {
             extn_manifest,
             cap_state: CapState::new(manifest.clone()),
-            session_state: SessionState::default(),
+            session_state,
             device_manifest: manifest.clone(),
             ripple_client: client.clone(),
             app_library_state: AppLibraryState::new(app_library),
-            app_events_state: AppEventsState::default(),
+            app_events_state: AppEventsState::new(manifest.get_sticky_events()),
             provider_broker_state: ProviderBrokerState::default(),
-            app_manager_state: AppManagerState::new(&manifest.configuration.saved_dir),
+            app_manager_state,
             open_rpc_state: OpenRpcState::new(Some(exclusory), extn_sdks, provider_registations),
             router_state: RouterState::new(),
             data_governance: DataGovernanceState::default(),
@@ -147,7 +166,13 @@ impl PlatformState {
                 broker_sender,
                 rule_engine,
                 client,
-            ),
+            )
+            .with_platform_context(platform_context)
+            .with_recording_state(recording_state.clone()),
+            rate_limit_state: RateLimitState::new(manifest.get_rate_limits()),
+            shutdown_state: ShutdownState::default(),
+            liveness_state: LivenessState::default(),
+            recording_state,
         }
     }
 
@@ -0,0 +1,196 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use ripple_sdk::api::manifest::device_manifest::{RateLimit, RateLimitConfiguration};
+
+/// How often the buckets which have refilled are evicted
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+    // when the bucket is back to its burst, None if it is never refilled
+    full_at: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct TokenBuckets {
+    buckets: HashMap<(String, String), TokenBucket>,
+    last_eviction: Option<Instant>,
+}
+
+impl TokenBuckets {
+    /// Removes the buckets which are full again, they are the same as a new bucket
+    fn evict_idle(&mut self, now: Instant) {
+        if self.last_eviction.map_or(false, |last| {
+            now.saturating_duration_since(last) < EVICTION_INTERVAL
+        }) {
+            return;
+        }
+        self.last_eviction = Some(now);
+        self.buckets
+            .retain(|_, bucket| bucket.full_at.map_or(true, |full_at| full_at > now));
+    }
+}
+
+/// Token buckets of the Firebolt requests, one per app and method family, configured through
+/// the `rate_limits` of the device manifest.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitState {
+    config: Arc<RateLimitConfiguration>,
+    buckets: Arc<RwLock<TokenBuckets>>,
+}
+
+impl RateLimitState {
+    pub fn new(config: RateLimitConfiguration) -> Self {
+        Self {
+            config: Arc::new(config),
+            buckets: Default::default(),
+        }
+    }
+
+    pub fn get_method_family(method: &str) -> String {
+        method.split('.').next().unwrap_or_default().to_lowercase()
+    }
+
+    /// Takes a token for a request of the app, returns false when the app is over its limit
+    pub fn try_acquire(&self, app_id: &str, method: &str) -> bool {
+        if !self.config.is_enabled() {
+            return true;
+        }
+        let family = Self::get_method_family(method);
+        match self.config.get_limit(app_id, &family) {
+            Some(limit) => self.try_acquire_at(app_id, family, limit, Instant::now()),
+            None => true,
+        }
+    }
+
+    fn try_acquire_at(&self, app_id: &str, family: String, limit: RateLimit, now: Instant) -> bool {
+        let burst = limit.burst as f64;
+        let rate = limit.requests_per_second as f64;
+        let mut buckets = self.buckets.write().unwrap();
+        buckets.evict_idle(now);
+        let bucket = buckets
+            .buckets
+            .entry((app_id.to_owned(), family))
+            .or_insert(TokenBucket {
+                tokens: burst,
+                updated: now,
+                full_at: Some(now),
+            });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+        let acquired = bucket.tokens >= 1.0;
+        if acquired {
+            bucket.tokens -= 1.0;
+        }
+        let missing = burst - bucket.tokens;
+        bucket.full_at = if missing <= 0.0 {
+            Some(now)
+        } else if rate > 0.0 {
+            Some(now + Duration::from_secs_f64(missing / rate))
+        } else {
+            None
+        };
+        acquired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_method_family() {
+        assert_eq!(RateLimitState::get_method_family("Device.name"), "device");
+        assert_eq!(RateLimitState::get_method_family("ping"), "ping");
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let state = RateLimitState::default();
+        let limit = RateLimit {
+            requests_per_second: 2,
+            burst: 2,
+        };
+        let start = Instant::now();
+        let acquire = |family: &str, after_ms| {
+            state.try_acquire_at(
+                "app1",
+                family.to_owned(),
+                limit,
+                start + Duration::from_millis(after_ms),
+            )
+        };
+        assert!(acquire("device", 0));
+        assert!(acquire("device", 0));
+        assert!(!acquire("device", 100));
+        // every family has its own bucket
+        assert!(acquire("player", 100));
+        // one token is refilled every 500ms
+        assert!(acquire("device", 500));
+        assert!(!acquire("device", 600));
+        // the bucket never holds more than the burst
+        assert!(acquire("device", 10000));
+        assert!(acquire("device", 10000));
+        assert!(!acquire("device", 10000));
+    }
+
+    #[test]
+    fn test_evict_idle_buckets() {
+        let state = RateLimitState::default();
+        let limit = RateLimit {
+            requests_per_second: 1,
+            burst: 1,
+        };
+        let start = Instant::now();
+        let acquire = |app_id: &str, after_ms| {
+            state.try_acquire_at(
+                app_id,
+                "device".to_owned(),
+                limit,
+                start + Duration::from_millis(after_ms),
+            )
+        };
+        assert!(acquire("app1", 0));
+        assert!(acquire("app2", 59500));
+        assert_eq!(state.buckets.read().unwrap().buckets.len(), 2);
+        // the bucket of app1 has refilled when the buckets are checked again, the one of app2
+        // is kept until it has refilled as well
+        assert!(!acquire("app2", 60000));
+        assert_eq!(state.buckets.read().unwrap().buckets.len(), 1);
+        // an evicted bucket starts out full
+        assert!(acquire("app1", 60000));
+        assert!(!acquire("app1", 60000));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let state = RateLimitState::default();
+        for _ in 0..100 {
+            assert!(state.try_acquire("app1", "device.name"));
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{
+    fs::OpenOptions,
+    io::{LineWriter, Write},
+    sync::Arc,
+    time::Instant,
+};
+
+use ripple_sdk::{
+    api::observability::recording::RecordEntry,
+    log::{error, info},
+    tokio::{
+        self,
+        sync::{mpsc, oneshot},
+    },
+};
+
+enum RecordCommand {
+    Line(String),
+    Flush(oneshot::Sender<()>),
+}
+
+#[derive(Debug)]
+struct Recorder {
+    start: Instant,
+    sender: mpsc::UnboundedSender<RecordCommand>,
+}
+
+/// Appends the exchanges of the session to a JSONL recording, disabled unless a record file
+/// is configured. The entries are written to the file by a dedicated blocking task so that the
+/// request and response paths never wait on the disk.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingState {
+    recorder: Option<Arc<Recorder>>,
+}
+
+impl RecordingState {
+    pub fn new(record_file: Option<String>) -> Self {
+        let Some(path) = record_file else {
+            return Self::default();
+        };
+        match OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&path)
+        {
+            Ok(file) => {
+                info!("Recording the session to {}", path);
+                Self::with_writer(Box::new(file))
+            }
+            Err(e) => {
+                error!("Unable to open the recording file {} {:?}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    fn with_writer(writer: Box<dyn Write + Send>) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        tokio::task::spawn_blocking(move || {
+            let mut writer = LineWriter::new(writer);
+            while let Some(command) = receiver.blocking_recv() {
+                match command {
+                    RecordCommand::Line(line) => {
+                        if let Err(e) = writeln!(writer, "{}", line) {
+                            error!("Unable to write the recording {:?}", e);
+                        }
+                    }
+                    RecordCommand::Flush(done) => {
+                        if let Err(e) = writer.flush() {
+                            error!("Unable to flush the recording {:?}", e);
+                        }
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        Self {
+            recorder: Some(Arc::new(Recorder {
+                start: Instant::now(),
+                sender,
+            })),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Appends an entry, which is only built when recording is enabled
+    pub fn record<F: FnOnce() -> RecordEntry>(&self, entry: F) {
+        let Some(recorder) = &self.recorder else {
+            return;
+        };
+        let mut entry = entry();
+        entry.elapsed_ms = recorder.start.elapsed().as_millis() as u64;
+        if let Ok(line) = serde_json::to_string(&entry) {
+            if recorder.sender.send(RecordCommand::Line(line)).is_err() {
+                error!("Unable to write the recording, the writer has stopped");
+            }
+        }
+    }
+
+    /// Resolves once the entries recorded so far are written to the recording
+    pub async fn flush(&self) {
+        let Some(recorder) = &self.recorder else {
+            return;
+        };
+        let (done_tx, done_rx) = oneshot::channel();
+        if recorder.sender.send(RecordCommand::Flush(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::api::observability::recording::{read_recording, RecordKind};
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record() {
+        RecordingState::default()
+            .record(|| panic!("entries are not built when recording is disabled"));
+
+        let buffer = Buffer::default();
+        let state = RecordingState::with_writer(Box::new(buffer.clone()));
+        state.record(|| {
+            RecordEntry::new(RecordKind::FireboltRequest, json!({"id": 1})).with_app_id("app1")
+        });
+        state.record(|| RecordEntry::new(RecordKind::FireboltResponse, json!({"id": 1})));
+        state.flush().await;
+        let data = buffer.0.lock().unwrap().clone();
+        let entries = read_recording(data.as_slice()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].app_id, Some("app1".to_owned()));
+        assert_eq!(entries[1].kind, RecordKind::FireboltResponse);
+    }
+}
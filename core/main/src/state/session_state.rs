@@ -161,6 +161,15 @@ impl SessionState {
         }
     }
 
+    /// Returns the app id of each session, keyed by the session or connection id
+    pub fn get_sessions(&self) -> HashMap<String, String> {
+        let session_state = self.session_map.read().unwrap();
+        session_state
+            .iter()
+            .map(|(id, session)| (id.clone(), session.get_app_id()))
+            .collect()
+    }
+
     pub fn get_session_for_connection_id(&self, cid: &str) -> Option<Session> {
         let session_state = self.session_map.read().unwrap();
         session_state.get(cid).cloned()
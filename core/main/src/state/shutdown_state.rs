@@ -0,0 +1,108 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use ripple_sdk::tokio::sync::watch;
+
+/// Shared flag set once Ripple starts shutting down, services stop taking new work when it is set.
+/// Also counts the writes of persisted state in progress, which the shutdown waits for.
+#[derive(Debug, Clone)]
+pub struct ShutdownState {
+    sender: Arc<watch::Sender<bool>>,
+    pending_writes: Arc<AtomicUsize>,
+}
+
+impl Default for ShutdownState {
+    fn default() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+            pending_writes: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+/// Write of persisted state in progress, completed when dropped
+#[derive(Debug)]
+pub struct PendingWrite(Arc<AtomicUsize>);
+
+impl Drop for PendingWrite {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ShutdownState {
+    pub fn shutdown(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Marks a write of persisted state in progress until the returned value is dropped
+    pub fn start_write(&self) -> PendingWrite {
+        self.pending_writes.fetch_add(1, Ordering::SeqCst);
+        PendingWrite(self.pending_writes.clone())
+    }
+
+    pub fn has_pending_writes(&self) -> bool {
+        self.pending_writes.load(Ordering::SeqCst) > 0
+    }
+
+    /// Resolves once the shutdown started
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        let _ = receiver.wait_for(|shutdown| *shutdown).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ripple_sdk::tokio;
+
+    #[tokio::test]
+    async fn test_shutdown_state() {
+        let state = ShutdownState::default();
+        assert!(!state.is_shutting_down());
+        let state_c = state.clone();
+        let waiter = tokio::spawn(async move { state_c.wait().await });
+        state.shutdown();
+        waiter.await.unwrap();
+        assert!(state.is_shutting_down());
+        // waiting after the shutdown resolves right away
+        state.wait().await;
+    }
+
+    #[test]
+    fn test_pending_writes() {
+        let state = ShutdownState::default();
+        let write = state.start_write();
+        let other = state.start_write();
+        assert!(state.has_pending_writes());
+        drop(write);
+        assert!(state.has_pending_writes());
+        drop(other);
+        assert!(!state.has_pending_writes());
+    }
+}
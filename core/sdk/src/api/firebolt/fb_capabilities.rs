@@ -446,6 +446,12 @@ pub const JSON_RPC_STANDARD_ERROR_INVALID_PARAMS: i32 = -32602;
 
 pub const JSON_RPC_STANDARD_ERROR_METHOD_NOT_FOUND: i32 = -32601;
 
+pub const JSON_RPC_ERROR_REQUEST_TIMEOUT: i32 = -32003;
+
+pub const JSON_RPC_ERROR_ENDPOINT_UNAVAILABLE: i32 = -32004;
+
+pub const JSON_RPC_ERROR_RATE_LIMITED: i32 = -32005;
+
 pub const CAPABILITY_GRANT_DENIED: i32 = -40400;
 
 pub const CAPABILITY_UNGRANTED: i32 = -40401;
@@ -20,6 +20,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub struct ListenRequest {
     pub listen: bool,
+    /// Asks for the last payload of the event to be replayed right after subscribing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -187,6 +187,9 @@ pub struct CompletedSessionResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub active_session_id: Option<String>,
     pub transition_pending: bool,
+    /// Signed token the app presents to the gateway to connect for this session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_token: Option<String>,
 }
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
@@ -625,12 +625,19 @@ impl RpcRequest {
         serde_json::from_value::<ListenRequest>(
             self.get_params().unwrap_or(json!({"listen": false})),
         )
-        .unwrap_or(ListenRequest { listen: false })
+        .unwrap_or(ListenRequest {
+            listen: false,
+            replay: None,
+        })
         .listen
     }
     pub fn get_unsubscribe(&self) -> RpcRequest {
         let mut rpc_request = self.clone();
-        rpc_request.params_json = serde_json::to_string(&ListenRequest { listen: false }).unwrap();
+        rpc_request.params_json = serde_json::to_string(&ListenRequest {
+            listen: false,
+            replay: None,
+        })
+        .unwrap();
         rpc_request
     }
 
@@ -37,6 +37,8 @@ use crate::{
 use super::{apps::AppManifest, exclusory::ExclusoryImpl};
 pub const PARTNER_EXCLUSION_REFRESH_TIMEOUT: u32 = 12 * 60 * 60; // 12 hours
 pub const METRICS_LOGGING_PERCENTAGE_DEFAULT: u32 = 10;
+pub const SHUTDOWN_TIMEOUT_MS_DEFAULT: u64 = 5000;
+pub const SESSION_TOKEN_TTL_MS_DEFAULT: u64 = 60000;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct RippleConfiguration {
@@ -70,6 +72,17 @@ pub struct RippleConfiguration {
     pub metrics_logging_percentage: u32,
     #[serde(default)]
     pub internet_monitoring_configuration: InternetMonitoringConfiguration,
+    #[serde(default)]
+    pub rate_limits: RateLimitConfiguration,
+    #[serde(default = "shutdown_timeout_ms_default")]
+    pub shutdown_timeout_ms: u64,
+    #[serde(default)]
+    pub session_tokens: SessionTokenConfiguration,
+    /// Events whose last payload is replayed to every new listener
+    #[serde(default)]
+    pub sticky_events: Vec<String>,
+    #[serde(default)]
+    pub recording: RecordingConfiguration,
 }
 
 fn partner_exclusion_refresh_timeout_default() -> u32 {
@@ -80,6 +93,49 @@ fn metrics_logging_percentage_default() -> u32 {
     METRICS_LOGGING_PERCENTAGE_DEFAULT
 }
 
+fn shutdown_timeout_ms_default() -> u64 {
+    SHUTDOWN_TIMEOUT_MS_DEFAULT
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SessionTokenConfiguration {
+    /// File holding the device key which signs session tokens, a random key is generated on
+    /// every start when not set
+    #[serde(default)]
+    pub key_path: Option<String>,
+    #[serde(default = "session_token_ttl_ms_default")]
+    pub ttl_ms: u64,
+    /// Lets clients of the non-secure gateway claim an app id through the `appId` query
+    /// parameter without a signed token, meant for development only
+    #[serde(default)]
+    pub dev_mode: bool,
+}
+
+impl Default for SessionTokenConfiguration {
+    fn default() -> Self {
+        Self {
+            key_path: None,
+            ttl_ms: session_token_ttl_ms_default(),
+            dev_mode: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RecordingConfiguration {
+    /// JSONL file which gets every Firebolt, broker and Thunder exchange of the session
+    #[serde(default)]
+    pub record_file: Option<String>,
+    /// Recording whose Firebolt requests are replayed once the gateway is up, the responses
+    /// which differ from the recorded ones are reported next to it
+    #[serde(default)]
+    pub replay_file: Option<String>,
+}
+
+fn session_token_ttl_ms_default() -> u64 {
+    SESSION_TOKEN_TTL_MS_DEFAULT
+}
+
 pub fn log_signal_default_level() -> String {
     "OFF".to_string()
 }
@@ -207,12 +263,66 @@ pub struct ApplicationsConfiguration {
 pub struct WsConfiguration {
     pub enabled: bool,
     pub gateway: String,
+    /// Interval without any frame from a client after which it gets pinged
+    #[serde(default)]
+    pub ping_interval_ms: Option<u64>,
+    /// Time a pinged client has to answer before its connection is closed, defaults to the
+    /// ping interval
+    #[serde(default)]
+    pub pong_timeout_ms: Option<u64>,
+    /// Interval without any request from a client after which its connection is closed
+    #[serde(default)]
+    pub idle_timeout_ms: Option<u64>,
+    /// Path of a unix socket the gateway also listens on
+    #[serde(default)]
+    pub unix_socket: Option<String>,
+    /// Maps the peer credentials of unix socket clients to app ids
+    #[serde(default)]
+    pub unix_socket_peers: UnixSocketPeers,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct UnixSocketPeers {
+    /// App ids by the uid of the client process
+    #[serde(default)]
+    pub uids: HashMap<u32, String>,
+    /// App ids by the executable of the client process, these take precedence over uids
+    #[serde(default)]
+    pub executables: Vec<UnixSocketExecutable>,
+}
+
+/// Client process running an executable, it is only matched when it also runs as the uid
+#[derive(Deserialize, Debug, Clone)]
+pub struct UnixSocketExecutable {
+    pub path: String,
+    pub uid: u32,
+    pub app_id: String,
+}
+
+impl UnixSocketPeers {
+    /// Resolves the app id of a client process from its uid and the path of its executable
+    pub fn get_app_id(&self, uid: u32, executable: Option<&str>) -> Option<String> {
+        executable
+            .and_then(|path| {
+                self.executables
+                    .iter()
+                    .find(|e| e.uid == uid && e.path == path)
+                    .map(|e| &e.app_id)
+            })
+            .or_else(|| self.uids.get(&uid))
+            .cloned()
+    }
 }
 
 pub fn ws_configuration_default() -> WsConfiguration {
     WsConfiguration {
         enabled: true,
         gateway: "127.0.0.1:3473".into(),
+        ping_interval_ms: None,
+        pong_timeout_ms: None,
+        idle_timeout_ms: None,
+        unix_socket: None,
+        unix_socket_peers: UnixSocketPeers::default(),
     }
 }
 
@@ -220,6 +330,11 @@ pub fn ws_configuration_internal_default() -> WsConfiguration {
     WsConfiguration {
         enabled: true,
         gateway: "127.0.0.1:3474".into(),
+        ping_interval_ms: None,
+        pong_timeout_ms: None,
+        idle_timeout_ms: None,
+        unix_socket: None,
+        unix_socket_peers: UnixSocketPeers::default(),
     }
 }
 
@@ -656,6 +771,48 @@ impl Default for InternetMonitoringConfiguration {
     }
 }
 
+/// Token bucket which refills `requests_per_second` tokens per second up to `burst` tokens,
+/// every request takes one token.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests_per_second: u32,
+    pub burst: u32,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AppRateLimit {
+    pub default: Option<RateLimit>,
+    /// Limits keyed by method family, the lowercase module of the method like `device`
+    #[serde(default)]
+    pub methods: HashMap<String, RateLimit>,
+}
+
+/// Rate limits of the Firebolt requests of each app. Requests of an app are counted per method
+/// family, the limit of a family is looked up in the app overrides before the device wide
+/// limits, a family specific limit wins over a default one.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct RateLimitConfiguration {
+    pub default: Option<RateLimit>,
+    #[serde(default)]
+    pub methods: HashMap<String, RateLimit>,
+    #[serde(default)]
+    pub apps: HashMap<String, AppRateLimit>,
+}
+
+impl RateLimitConfiguration {
+    pub fn is_enabled(&self) -> bool {
+        self.default.is_some() || !self.methods.is_empty() || !self.apps.is_empty()
+    }
+
+    pub fn get_limit(&self, app_id: &str, family: &str) -> Option<RateLimit> {
+        let app = self.apps.get(app_id);
+        app.and_then(|app| app.methods.get(family).copied())
+            .or_else(|| app.and_then(|app| app.default))
+            .or_else(|| self.methods.get(family).copied())
+            .or(self.default)
+    }
+}
+
 impl Default for RippleConfiguration {
     fn default() -> Self {
         Self {
@@ -676,6 +833,11 @@ impl Default for RippleConfiguration {
             partner_exclusion_refresh_timeout: partner_exclusion_refresh_timeout_default(),
             metrics_logging_percentage: metrics_logging_percentage_default(),
             internet_monitoring_configuration: Default::default(),
+            rate_limits: Default::default(),
+            shutdown_timeout_ms: shutdown_timeout_ms_default(),
+            session_tokens: SessionTokenConfiguration::default(),
+            sticky_events: Vec::new(),
+            recording: RecordingConfiguration::default(),
             log_signal_log_level: log_signal_default_level(),
         }
     }
@@ -711,6 +873,14 @@ impl DeviceManifest {
         self.configuration.internal_ws_configuration.enabled
     }
 
+    pub fn get_ws_configuration(&self) -> WsConfiguration {
+        self.configuration.ws_configuration.clone()
+    }
+
+    pub fn get_internal_ws_configuration(&self) -> WsConfiguration {
+        self.configuration.internal_ws_configuration.clone()
+    }
+
     pub fn get_ws_gateway_host(&self) -> String {
         self.configuration.ws_configuration.gateway.clone()
     }
@@ -793,6 +963,26 @@ impl DeviceManifest {
             .internet_monitoring_configuration
             .default_monitoring_interval_seconds
     }
+
+    pub fn get_rate_limits(&self) -> RateLimitConfiguration {
+        self.configuration.rate_limits.clone()
+    }
+
+    pub fn get_shutdown_timeout_ms(&self) -> u64 {
+        self.configuration.shutdown_timeout_ms
+    }
+
+    pub fn get_session_tokens(&self) -> SessionTokenConfiguration {
+        self.configuration.session_tokens.clone()
+    }
+
+    pub fn get_recording(&self) -> RecordingConfiguration {
+        self.configuration.recording.clone()
+    }
+
+    pub fn get_sticky_events(&self) -> Vec<String> {
+        self.configuration.sticky_events.clone()
+    }
 }
 
 #[cfg(test)]
@@ -813,10 +1003,20 @@ pub(crate) mod tests {
                     ws_configuration: WsConfiguration {
                         enabled: true,
                         gateway: "127.0.0.1:3473".to_string(),
+                        ping_interval_ms: None,
+                        pong_timeout_ms: None,
+                        idle_timeout_ms: None,
+                        unix_socket: None,
+                        unix_socket_peers: UnixSocketPeers::default(),
                     },
                     internal_ws_configuration: WsConfiguration {
                         enabled: true,
                         gateway: "127.0.0.1:3474".to_string(),
+                        ping_interval_ms: None,
+                        pong_timeout_ms: None,
+                        idle_timeout_ms: None,
+                        unix_socket: None,
+                        unix_socket_peers: UnixSocketPeers::default(),
                     },
                     platform_parameters: {
                         let mut params = HashMap::new();
@@ -899,6 +1099,11 @@ pub(crate) mod tests {
                     internet_monitoring_configuration: InternetMonitoringConfiguration {
                         default_monitoring_interval_seconds: 180,
                     },
+                    rate_limits: RateLimitConfiguration::default(),
+                    shutdown_timeout_ms: SHUTDOWN_TIMEOUT_MS_DEFAULT,
+                    session_tokens: SessionTokenConfiguration::default(),
+                    sticky_events: Vec::new(),
+                    recording: RecordingConfiguration::default(),
                 },
                 capabilities: CapabilityConfiguration {
                     supported: vec!["main[manage]".to_string(), "test".to_string()],
@@ -1225,4 +1430,76 @@ pub(crate) mod tests {
                 .accessibility_audio_description_settings
         );
     }
+
+    #[test]
+    fn test_get_rate_limit() {
+        let manifest = DeviceManifest::mock();
+        assert!(!manifest.get_rate_limits().is_enabled());
+
+        let rate_limits = serde_json::from_str::<RateLimitConfiguration>(
+            r#"{
+            "default": { "requests_per_second": 20, "burst": 40 },
+            "methods": { "device": { "requests_per_second": 5, "burst": 10 } },
+            "apps": {
+                "app1": { "default": { "requests_per_second": 50, "burst": 50 } },
+                "app2": { "methods": { "device": { "requests_per_second": 1, "burst": 1 } } }
+            }
+        }"#,
+        )
+        .unwrap();
+        let limit = |requests_per_second, burst| RateLimit {
+            requests_per_second,
+            burst,
+        };
+        assert!(rate_limits.is_enabled());
+        assert_eq!(rate_limits.get_limit("app", "player"), Some(limit(20, 40)));
+        assert_eq!(rate_limits.get_limit("app", "device"), Some(limit(5, 10)));
+        assert_eq!(rate_limits.get_limit("app1", "device"), Some(limit(50, 50)));
+        assert_eq!(rate_limits.get_limit("app2", "device"), Some(limit(1, 1)));
+        assert_eq!(rate_limits.get_limit("app2", "player"), Some(limit(20, 40)));
+    }
+
+    #[test]
+    fn test_ws_configuration_timeouts() {
+        let config = serde_json::from_str::<WsConfiguration>(
+            r#"{ "enabled": true, "gateway": "127.0.0.1:3473", "ping_interval_ms": 30000 }"#,
+        )
+        .unwrap();
+        assert_eq!(config.ping_interval_ms, Some(30000));
+        assert_eq!(config.pong_timeout_ms, None);
+        assert_eq!(config.idle_timeout_ms, None);
+    }
+
+    #[test]
+    fn test_unix_socket_peers() {
+        let config = serde_json::from_str::<WsConfiguration>(
+            r#"{ "enabled": true, "gateway": "127.0.0.1:3473", "unix_socket": "/tmp/ripple.sock",
+                "unix_socket_peers": { "uids": { "1000": "app1" },
+                    "executables": [{ "path": "/usr/bin/app2d", "uid": 1001, "app_id": "app2" }] } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.unix_socket, Some("/tmp/ripple.sock".to_owned()));
+        let peers = config.unix_socket_peers;
+        assert_eq!(peers.get_app_id(1000, None), Some("app1".to_owned()));
+        assert_eq!(
+            peers.get_app_id(1001, Some("/usr/bin/app2d")),
+            Some("app2".to_owned())
+        );
+        // the executable only matches along with its uid
+        assert_eq!(
+            peers.get_app_id(1000, Some("/usr/bin/app2d")),
+            Some("app1".to_owned())
+        );
+        assert_eq!(peers.get_app_id(1002, Some("/usr/bin/app2d")), None);
+        assert_eq!(peers.get_app_id(1001, Some("other")), None);
+    }
+
+    #[test]
+    fn test_session_token_configuration() {
+        let config =
+            serde_json::from_str::<SessionTokenConfiguration>(r#"{ "dev_mode": true }"#).unwrap();
+        assert!(config.dev_mode);
+        assert!(config.key_path.is_none());
+        assert_eq!(config.ttl_ms, SESSION_TOKEN_TTL_MS_DEFAULT);
+    }
 }
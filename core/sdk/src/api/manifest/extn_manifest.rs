@@ -83,6 +83,17 @@ pub struct ExtnResolutionEntry {
     pub exclusion: Option<bool>,
 }
 
+/// How the extension library is hosted, `process` runs each channel of the library in a child
+/// process of Ripple connected over a Unix socket, so a crashing extension does not take down
+/// the gateway.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtnKind {
+    #[default]
+    Library,
+    Process,
+}
+
 /// Contains Resolution strategies and path for the manifest.
 #[derive(Deserialize, Debug, Clone)]
 #[cfg_attr(test, derive(PartialEq))]
@@ -90,6 +101,12 @@ pub struct ExtnManifestEntry {
     pub path: String,
     pub symbols: Vec<ExtnSymbol>,
     pub resolution: Option<Vec<ExtnResolutionEntry>>,
+    #[serde(default)]
+    pub kind: ExtnKind,
+    /// Optional extensions are skipped when they cannot be loaded, for example when they were
+    /// built against an incompatible `ripple_sdk`, instead of failing the bootstrap.
+    #[serde(default)]
+    pub optional: bool,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -138,6 +155,20 @@ impl ExtnManifestEntry {
         path
     }
 
+    pub fn is_process(&self) -> bool {
+        self.kind == ExtnKind::Process
+    }
+
+    /// Marks every resolution entry of this extension as excluded, used when the extension is
+    /// skipped during bootstrap so it is not considered for any of its capabilities.
+    pub fn exclude(&mut self) {
+        if let Some(resolution) = self.resolution.as_mut() {
+            for entry in resolution.iter_mut() {
+                entry.exclusion = Some(true);
+            }
+        }
+    }
+
     pub fn get_symbol(&self, capability: ExtnId) -> Option<ExtnSymbol> {
         let ref_cap = capability.to_string();
         self.symbols.clone().into_iter().find(|x| x.id.eq(&ref_cap))
@@ -245,6 +276,8 @@ mod tests {
             path: "/absolute/path".to_string(),
             symbols: vec![],
             resolution: None,
+            kind: ExtnKind::Library,
+            optional: false,
         };
 
         assert_eq!(
@@ -259,6 +292,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_extn_kind() {
+        let entry: ExtnManifestEntry =
+            serde_json::from_str(r#"{"path": "libthunder", "symbols": [], "resolution": null}"#)
+                .unwrap();
+        assert_eq!(entry.kind, ExtnKind::Library);
+        assert!(!entry.is_process());
+
+        let entry: ExtnManifestEntry = serde_json::from_str(
+            r#"{"path": "libthunder", "symbols": [], "resolution": null, "kind": "process"}"#,
+        )
+        .unwrap();
+        assert!(entry.is_process());
+        assert!(!entry.optional);
+    }
+
+    #[test]
+    fn test_exclude() {
+        let mut entry: ExtnManifestEntry = serde_json::from_str(
+            r#"{"path": "libthunder", "symbols": [], "optional": true, "resolution": [
+                {"capability": "ripple:channel:device:thunder", "priority": 1, "exclusion": null}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(entry.optional);
+        entry.exclude();
+        assert_eq!(entry.resolution.unwrap()[0].exclusion, Some(true));
+    }
+
     #[test]
     fn test_get_symbol() {
         let dist_channel = ExtnId::new_channel(ExtnClassId::Distributor, "test".into()).to_string();
@@ -272,6 +334,8 @@ mod tests {
             path: "relative/path".to_string(),
             symbols: vec![symbol.clone()],
             resolution: None,
+            kind: ExtnKind::Library,
+            optional: false,
         };
         assert_eq!(
             extn_manifest_entry.get_symbol(ExtnId::try_from(dist_channel).unwrap()),
@@ -367,6 +431,8 @@ mod tests {
             path: "relative/path".to_string(),
             symbols: vec![symbol],
             resolution: None,
+            kind: ExtnKind::Library,
+            optional: false,
         };
         manifest.extns = vec![extn_manifest_entry];
 
@@ -391,6 +457,8 @@ mod tests {
             path: "relative/path".to_string(),
             symbols: vec![symbol],
             resolution: None,
+            kind: ExtnKind::Library,
+            optional: false,
         };
 
         manifest.extns = vec![extn_manifest_entry];
@@ -415,6 +483,8 @@ mod tests {
             path: "relative/path".to_string(),
             symbols: vec![symbol],
             resolution: None,
+            kind: ExtnKind::Library,
+            optional: false,
         };
 
         manifest.extns = vec![extn_manifest_entry];
@@ -73,4 +73,5 @@ pub mod observability {
     pub mod log_signal;
     pub mod metrics_util;
     pub mod operational_metrics;
+    pub mod recording;
 }
@@ -0,0 +1,247 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::utils::error::RippleError;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKind {
+    /// Frame received from an app on the Firebolt gateway
+    FireboltRequest,
+    /// Frame sent to an app on the Firebolt gateway
+    FireboltResponse,
+    /// Request handed to a broker endpoint
+    BrokerRequest,
+    /// Response received from a broker endpoint
+    BrokerResponse,
+    /// Message sent on the Thunder websocket
+    ThunderRequest,
+    /// Message received on the Thunder websocket
+    ThunderResponse,
+}
+
+/// A line of a recording session file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecordEntry {
+    pub kind: RecordKind,
+    /// Milliseconds since the start of the recording
+    #[serde(default)]
+    pub elapsed_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    pub payload: Value,
+}
+
+impl RecordEntry {
+    pub fn new(kind: RecordKind, payload: Value) -> Self {
+        Self {
+            kind,
+            elapsed_ms: 0,
+            app_id: None,
+            connection_id: None,
+            request_id: None,
+            method: None,
+            payload,
+        }
+    }
+
+    /// Parses a message which is expected to be json, keeping it as a string otherwise
+    pub fn from_text(kind: RecordKind, text: &str) -> Self {
+        let payload = serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_owned()));
+        Self::new(kind, payload)
+    }
+
+    pub fn with_app_id(mut self, app_id: &str) -> Self {
+        self.app_id = Some(app_id.to_owned());
+        self
+    }
+
+    pub fn with_connection_id(mut self, connection_id: &str) -> Self {
+        self.connection_id = Some(connection_id.to_owned());
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: &str) -> Self {
+        self.request_id = Some(request_id.to_owned());
+        self
+    }
+
+    pub fn with_method(mut self, method: &str) -> Self {
+        self.method = Some(method.to_owned());
+        self
+    }
+}
+
+/// Reads the entries of a recording session, lines which cannot be parsed are skipped
+pub fn read_recording<R: Read>(reader: R) -> Result<Vec<RecordEntry>, RippleError> {
+    let mut entries = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line.map_err(|_| RippleError::ParseError)?;
+        if let Ok(entry) = serde_json::from_str::<RecordEntry>(&line) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// A request an app sent on the gateway along with the response it got
+#[derive(Debug, Clone, PartialEq)]
+pub struct FireboltExchange {
+    pub app_id: String,
+    pub connection_id: String,
+    pub elapsed_ms: u64,
+    pub request: Value,
+    pub response: Option<Value>,
+}
+
+/// Key matching a request frame with its response frame, None for notifications and for
+/// responses the app sent to provider requests
+pub fn get_request_key(frame: &Value) -> Option<String> {
+    get_frame_key(frame, true)
+}
+
+/// Key matching a response frame with its request frame, None for events
+pub fn get_response_key(frame: &Value) -> Option<String> {
+    get_frame_key(frame, false)
+}
+
+fn get_frame_key(frame: &Value, request: bool) -> Option<String> {
+    let get_id = |v: &Value| match v.get("id") {
+        Some(id) if !id.is_null() && v.get("method").is_some() == request => Some(id.to_string()),
+        _ => None,
+    };
+    match frame {
+        Value::Array(entries) => {
+            let mut ids: Vec<String> = entries.iter().filter_map(get_id).collect();
+            if ids.is_empty() {
+                return None;
+            }
+            ids.sort();
+            Some(ids.join(","))
+        }
+        v => get_id(v),
+    }
+}
+
+/// Pairs every Firebolt request of a recording with the first response of its connection
+/// having the same JSON-RPC id
+pub fn get_firebolt_exchanges(entries: &[RecordEntry]) -> Vec<FireboltExchange> {
+    let mut exchanges: Vec<FireboltExchange> = Vec::new();
+    let mut pending: HashMap<(String, String), usize> = HashMap::new();
+    for entry in entries {
+        let connection_id = entry.connection_id.clone().unwrap_or_default();
+        match entry.kind {
+            RecordKind::FireboltRequest => {
+                if let Some(key) = get_request_key(&entry.payload) {
+                    pending.insert((connection_id.clone(), key), exchanges.len());
+                }
+                exchanges.push(FireboltExchange {
+                    app_id: entry.app_id.clone().unwrap_or_default(),
+                    connection_id,
+                    elapsed_ms: entry.elapsed_ms,
+                    request: entry.payload.clone(),
+                    response: None,
+                });
+            }
+            RecordKind::FireboltResponse => {
+                if let Some(key) = get_response_key(&entry.payload) {
+                    if let Some(index) = pending.remove(&(connection_id, key)) {
+                        exchanges[index].response = Some(entry.payload.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    exchanges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(kind: RecordKind, connection_id: &str, payload: Value) -> RecordEntry {
+        RecordEntry::new(kind, payload)
+            .with_app_id("app1")
+            .with_connection_id(connection_id)
+    }
+
+    #[test]
+    fn test_read_recording() {
+        let text = [
+            serde_json::to_string(&entry(RecordKind::FireboltRequest, "c1", json!({}))).unwrap(),
+            "not json".to_owned(),
+            r#"{"kind":"thunder_request","payload":"text"}"#.to_owned(),
+        ]
+        .join("\n");
+        let entries = read_recording(text.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].connection_id, Some("c1".to_owned()));
+        assert_eq!(entries[1].kind, RecordKind::ThunderRequest);
+        assert_eq!(entries[1].payload, json!("text"));
+    }
+
+    #[test]
+    fn test_get_frame_keys() {
+        let request = json!({"jsonrpc":"2.0","id":1,"method":"device.name"});
+        let response = json!({"jsonrpc":"2.0","id":1,"result":"Living Room"});
+        assert_eq!(get_request_key(&request), Some("1".to_owned()));
+        assert_eq!(get_response_key(&request), None);
+        assert_eq!(get_response_key(&response), Some("1".to_owned()));
+        assert_eq!(get_request_key(&response), None);
+        assert_eq!(
+            get_request_key(&json!([{"id":2,"method":"a"},{"method":"b"},{"id":1,"method":"c"}])),
+            Some("1,2".to_owned())
+        );
+        assert_eq!(get_request_key(&json!({"method":"notification"})), None);
+    }
+
+    #[test]
+    fn test_get_firebolt_exchanges() {
+        let entries = vec![
+            entry(
+                RecordKind::FireboltRequest,
+                "c1",
+                json!({"id":1,"method":"device.name"}),
+            ),
+            entry(
+                RecordKind::FireboltRequest,
+                "c2",
+                json!({"id":1,"method":"device.model"}),
+            ),
+            entry(RecordKind::ThunderRequest, "", json!({"id":7})),
+            entry(
+                RecordKind::FireboltResponse,
+                "c2",
+                json!({"id":1,"result":"m"}),
+            ),
+            entry(
+                RecordKind::FireboltResponse,
+                "c1",
+                json!({"id":1,"result":"n"}),
+            ),
+            // an event sharing the id of the request is not its response
+            entry(
+                RecordKind::FireboltResponse,
+                "c1",
+                json!({"id":1,"result":"e"}),
+            ),
+        ];
+        let exchanges = get_firebolt_exchanges(&entries);
+        assert_eq!(exchanges.len(), 2);
+        assert_eq!(exchanges[0].response, Some(json!({"id":1,"result":"n"})));
+        assert_eq!(exchanges[1].response, Some(json!({"id":1,"result":"m"})));
+    }
+}
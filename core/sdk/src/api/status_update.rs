@@ -16,7 +16,7 @@
 //
 
 use crate::{
-    extn::extn_client_message::{ExtnEvent, ExtnPayload, ExtnPayloadProvider},
+    extn::extn_client_message::{ExtnEvent, ExtnPayload, ExtnPayloadProvider, ExtnRequest},
     framework::ripple_contract::RippleContract,
 };
 use serde::{Deserialize, Serialize};
@@ -26,6 +26,9 @@ pub enum ExtnStatus {
     Error,
     Ready,
     Interrupted,
+    /// Set by Main for an optional extension which was skipped because it was built against a
+    /// `ripple_sdk` version which is not compatible with the running Ripple
+    Incompatible,
 }
 
 impl ExtnPayloadProvider for ExtnStatus {
@@ -46,6 +49,53 @@ impl ExtnPayloadProvider for ExtnStatus {
     }
 }
 
+/// Sent by Main to check a channel extension still processes requests. It is answered by the
+/// `ExtnClient` of the extension itself so extensions do not need a processor for it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExtnHealthCheck;
+
+impl ExtnPayloadProvider for ExtnHealthCheck {
+    fn get_extn_payload(&self) -> ExtnPayload {
+        ExtnPayload::Request(ExtnRequest::HealthCheck(self.clone()))
+    }
+
+    fn get_from_payload(payload: ExtnPayload) -> Option<ExtnHealthCheck> {
+        if let ExtnPayload::Request(ExtnRequest::HealthCheck(r)) = payload {
+            return Some(r);
+        }
+
+        None
+    }
+
+    fn contract() -> RippleContract {
+        RippleContract::ExtnStatus
+    }
+}
+
+/// Sent by Main to every extension when Ripple is shutting down. Extensions with state to
+/// persist register a request processor for it and respond once the state is written, the
+/// `ExtnClient` of the other extensions answers right away.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExtnShutdown;
+
+impl ExtnPayloadProvider for ExtnShutdown {
+    fn get_extn_payload(&self) -> ExtnPayload {
+        ExtnPayload::Request(ExtnRequest::Shutdown(self.clone()))
+    }
+
+    fn get_from_payload(payload: ExtnPayload) -> Option<ExtnShutdown> {
+        if let ExtnPayload::Request(ExtnRequest::Shutdown(r)) = payload {
+            return Some(r);
+        }
+
+        None
+    }
+
+    fn contract() -> RippleContract {
+        RippleContract::ExtnStatus
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,4 +107,14 @@ mod tests {
         let contract_type: RippleContract = RippleContract::ExtnStatus;
         test_extn_payload_provider(extn_status, contract_type);
     }
+
+    #[test]
+    fn test_extn_payload_provider_for_extn_health_check() {
+        test_extn_payload_provider(ExtnHealthCheck, RippleContract::ExtnStatus);
+    }
+
+    #[test]
+    fn test_extn_payload_provider_for_extn_shutdown() {
+        test_extn_payload_provider(ExtnShutdown, RippleContract::ExtnStatus);
+    }
 }
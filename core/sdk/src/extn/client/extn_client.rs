@@ -17,12 +17,15 @@
 
 use async_channel::{bounded, Receiver as CReceiver, Sender as CSender};
 use chrono::Utc;
+use futures::{future::join_all, Stream};
 use log::warn;
 #[cfg(not(test))]
 use log::{debug, error, info, trace};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    pin::Pin,
     sync::{Arc, RwLock},
+    task::{Context, Poll},
     time::Duration,
 };
 
@@ -30,7 +33,7 @@ use std::{
 use {println as info, println as trace, println as debug, println as error};
 
 use tokio::sync::{
-    mpsc::Sender as MSender,
+    mpsc::{self, Receiver as MReceiver, Sender as MSender},
     oneshot::{self, Sender as OSender},
 };
 
@@ -44,10 +47,13 @@ use crate::{
             device_request::{InternetConnectionStatus, TimeZone},
         },
         firebolt::fb_metrics::MetricsContext,
-        manifest::extn_manifest::ExtnSymbol,
+        manifest::extn_manifest::{ExtnResolutionEntry, ExtnSymbol},
+        status_update::{ExtnHealthCheck, ExtnShutdown},
     },
     extn::{
-        extn_client_message::{ExtnMessage, ExtnPayloadProvider, ExtnResponse},
+        extn_client_message::{
+            ExtnMessage, ExtnPayloadProvider, ExtnResponse, ExtnStreamControl, ExtnStreamSignal,
+        },
         extn_id::ExtnId,
         ffi::ffi_message::CExtnMessage,
     },
@@ -73,6 +79,10 @@ use crate::utils::mock_utils::get_next_mock_response;
 /// 4. `response_processors` - Map of response processors which are used for Response processor handling
 /// 5. `request_processors` - Map of request processors used for Request process handling
 /// 6. `event_processors` - Map of event processors used for Event Process handling
+/// 7. `contract_map` - Extensions fulfilling each contract ordered by their resolution priority
+/// 8. `unhealthy_extns` - Extensions which stopped answering health checks, skipped when resolving a contract
+/// 9. `stream_processors` - Map of the streams requested by this client, see [ExtnClient::request_stream]
+/// 10. `stream_controls` - Map of the streams emitted by the processors of this client, see [ExtnClient::open_stream]
 ///
 
 #[repr(C)]
@@ -81,13 +91,34 @@ pub struct ExtnClient {
     receiver: CReceiver<CExtnMessage>,
     sender: ExtnSender,
     extn_sender_map: Arc<RwLock<HashMap<String, CSender<CExtnMessage>>>>,
-    contract_map: Arc<RwLock<HashMap<String, String>>>,
+    contract_map: Arc<RwLock<HashMap<String, Vec<ContractCandidate>>>>,
+    unhealthy_extns: Arc<RwLock<HashSet<String>>>,
+    stream_processors: Arc<RwLock<HashMap<String, MSender<ExtnResponse>>>>,
+    stream_controls: Arc<RwLock<HashMap<String, MSender<ExtnStreamSignal>>>>,
     response_processors: Arc<RwLock<HashMap<String, OSender<ExtnMessage>>>>,
     request_processors: Arc<RwLock<HashMap<String, MSender<ExtnMessage>>>>,
     event_processors: Arc<RwLock<HashMap<String, Vec<MSender<ExtnMessage>>>>>,
     ripple_context: Arc<RwLock<RippleContext>>,
 }
 
+/// Number of chunks a stream emits before it waits for the demand of its consumer
+pub const EXTN_STREAM_WINDOW: u32 = 16;
+
+/// Time a stream waits for the demand of its consumer before it is considered gone
+const EXTN_STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Extension which fulfills a contract, lower priorities are preferred and extensions without a
+/// priority come last.
+#[derive(Clone, Debug)]
+struct ContractCandidate {
+    id: String,
+    priority: Option<u64>,
+}
+
+fn sort_candidates(candidates: &mut [ContractCandidate]) {
+    candidates.sort_by_key(|c| c.priority.unwrap_or(u64::MAX));
+}
+
 fn add_stream_processor<P>(id: String, context: P, map: Arc<RwLock<HashMap<String, P>>>) {
     let mut processor_state = map.write().unwrap();
     processor_state.insert(id, context);
@@ -128,6 +159,9 @@ impl ExtnClient {
             sender,
             extn_sender_map: Arc::new(RwLock::new(HashMap::new())),
             contract_map: Arc::new(RwLock::new(HashMap::new())),
+            unhealthy_extns: Arc::new(RwLock::new(HashSet::new())),
+            stream_processors: Arc::new(RwLock::new(HashMap::new())),
+            stream_controls: Arc::new(RwLock::new(HashMap::new())),
             response_processors: Arc::new(RwLock::new(HashMap::new())),
             request_processors: Arc::new(RwLock::new(HashMap::new())),
             event_processors: Arc::new(RwLock::new(HashMap::new())),
@@ -210,23 +244,135 @@ impl ExtnClient {
             sender_map.insert(id.clone(), sender);
         }
         {
-            let mut map = HashMap::new();
+            let mut contract_map = self.contract_map.write().unwrap();
             for contract in symbol.fulfills {
                 match RippleContract::from_manifest(&contract) {
                     Some(v) => {
                         let ripple_contract_string = v.as_clear_string();
                         info!("{} will fulfill {}", id, ripple_contract_string);
                         // creating a map - contract & sender used for request mapping
-                        let _ = map.insert(ripple_contract_string, id.clone());
+                        // the latest extension is preferred until a resolution sets the priorities
+                        let candidates = contract_map.entry(ripple_contract_string).or_default();
+                        candidates.retain(|c| c.id != id);
+                        candidates.insert(
+                            0,
+                            ContractCandidate {
+                                id: id.clone(),
+                                priority: None,
+                            },
+                        );
+                        sort_candidates(candidates);
                     }
                     None => error!("Unknown contract {}", contract),
                 }
             }
-            let mut contract_map = self.contract_map.write().unwrap();
-            contract_map.extend(map);
         }
     }
 
+    /// Used by `Main` to apply the resolution entries of an extension from the manifest. The
+    /// capability of an entry is the contract, extensions are resolved by ascending priority and
+    /// excluded extensions never fulfill the contract.
+    pub fn set_resolution(&self, id: &ExtnId, resolution: &[ExtnResolutionEntry]) {
+        let id = id.to_string();
+        let mut contract_map = self.contract_map.write().unwrap();
+        for entry in resolution {
+            let Some(contract) = RippleContract::from_manifest(&entry.capability) else {
+                error!(
+                    "Unknown contract {} in resolution of {}",
+                    entry.capability, id
+                );
+                continue;
+            };
+            if let Some(candidates) = contract_map.get_mut(&contract.as_clear_string()) {
+                if let Some(true) = entry.exclusion {
+                    candidates.retain(|c| c.id != id);
+                } else if let Some(candidate) = candidates.iter_mut().find(|c| c.id == id) {
+                    candidate.priority = entry.priority;
+                    sort_candidates(candidates);
+                }
+            }
+        }
+    }
+
+    /// Used by `Main` to mark an extension which stopped answering health checks, requests for its
+    /// contracts are resolved to the next healthy extension or fail with [RippleError::ExtnUnhealthy].
+    pub fn set_extn_health(&self, id: &str, healthy: bool) {
+        let mut unhealthy_extns = self.unhealthy_extns.write().unwrap();
+        if healthy {
+            unhealthy_extns.remove(id);
+        } else {
+            unhealthy_extns.insert(id.to_owned());
+        }
+    }
+
+    pub fn is_extn_healthy(&self, id: &str) -> bool {
+        !self.unhealthy_extns.read().unwrap().contains(id)
+    }
+
+    /// Returns the ids of the extensions connected to this client
+    pub fn get_extn_ids(&self) -> Vec<String> {
+        self.extn_sender_map
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Used by `Main` to check an extension still processes requests.
+    /// # Arguments
+    /// `id` - id of the extension
+    /// `timeout_in_msecs` - time the extension has to answer the [ExtnHealthCheck]
+    pub async fn ping(&self, id: &str, timeout_in_msecs: u64) -> RippleResponse {
+        self.request_extn(id, ExtnHealthCheck, timeout_in_msecs)
+            .await
+    }
+
+    /// Sends a request to the given extension and waits for its response, whatever it is
+    async fn request_extn<T: ExtnPayloadProvider>(
+        &self,
+        id: &str,
+        payload: T,
+        timeout_in_msecs: u64,
+    ) -> RippleResponse {
+        let sender = self
+            .get_extn_sender_with_extn_id(id)
+            .ok_or(RippleError::SenderMissing)?;
+        let request_id = uuid::Uuid::new_v4().to_string();
+        let (tx, rx) = oneshot::channel();
+        add_single_processor(
+            request_id.clone(),
+            Some(tx),
+            self.response_processors.clone(),
+        );
+        let result = match self
+            .sender
+            .send_request(request_id.clone(), payload, Some(sender), None)
+        {
+            Ok(_) => {
+                match tokio::time::timeout(Duration::from_millis(timeout_in_msecs), rx).await {
+                    Ok(Ok(_)) => return Ok(()),
+                    _ => Err(RippleError::TimeoutError),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        remove_processor(request_id, self.response_processors.clone());
+        result
+    }
+
+    /// Fails when every extension fulfilling the contract is unhealthy, so requests do not wait
+    /// for an extension which is not answering.
+    fn check_contract_health(&self, contract: &RippleContract) -> RippleResponse {
+        let contract_map = self.contract_map.read().unwrap();
+        if let Some(candidates) = contract_map.get(&contract.as_clear_string()) {
+            if !candidates.is_empty() && !candidates.iter().any(|c| self.is_extn_healthy(&c.id)) {
+                return Err(RippleError::ExtnUnhealthy);
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_other_senders(&self) -> Vec<CSender<CExtnMessage>> {
         self.extn_sender_map
             .read()
@@ -258,7 +404,9 @@ impl ExtnClient {
             };
             trace!("** receiving message latency={} msg={:?}", latency, message);
             if message.payload.is_response() {
-                Self::handle_single(message, self.response_processors.clone());
+                if !self.handle_stream_response(&message) {
+                    Self::handle_single(message, self.response_processors.clone());
+                }
             } else if message.payload.is_event() {
                 let is_main = self.sender.get_cap().is_main();
                 if is_main // This part of code is for the main ExntClient to handle
@@ -297,6 +445,13 @@ impl ExtnClient {
             } else {
                 let current_cap = self.sender.get_cap();
                 let target_contract = message.clone().target;
+                if let Some(control) = ExtnStreamControl::get_from_payload(message.payload.clone())
+                {
+                    // Main forwards the signals of streams emitted by extensions
+                    if self.handle_stream_control(control) || !current_cap.is_main() {
+                        continue;
+                    }
+                }
                 if current_cap.is_main() {
                     if let Some(request) =
                         RippleContextUpdateRequest::is_ripple_context_update(&message.payload)
@@ -339,12 +494,20 @@ impl ExtnClient {
                                 error!("Error forwarding request {:?}", e)
                             }
                         });
+                    } else if let Err(e) = self.check_contract_health(&target_contract) {
+                        self.handle_error(message, e);
                     } else {
                         // could be main contract
                         if !Self::handle_stream(message.clone(), self.request_processors.clone()) {
                             self.handle_no_processor_error(message);
                         }
                     }
+                } else if ExtnHealthCheck::get_from_payload(message.payload.clone()).is_some() {
+                    self.acknowledge(message);
+                } else if ExtnShutdown::get_from_payload(message.payload.clone()).is_some() {
+                    if !Self::handle_stream(message.clone(), self.request_processors.clone()) {
+                        self.acknowledge(message);
+                    }
                 } else if !Self::handle_stream(message.clone(), self.request_processors.clone()) {
                     self.handle_no_processor_error(message);
                 }
@@ -402,21 +565,112 @@ impl ExtnClient {
         }
     }
 
+    /// Tells every extension that Ripple is shutting down with an [ExtnShutdown] request and
+    /// waits until all of them responded, so they can persist their state.
+    /// # Arguments
+    /// `timeout_in_msecs` - time the extensions have to respond
+    pub async fn shutdown(&self, timeout_in_msecs: u64) {
+        if !self.sender.get_cap().is_main() {
+            error!("Shutdown is not allowed outside main");
+            return;
+        }
+        let ids = self.get_extn_ids();
+        let results = join_all(
+            ids.iter()
+                .map(|id| self.request_extn(id, ExtnShutdown, timeout_in_msecs)),
+        )
+        .await;
+        for (id, result) in ids.iter().zip(results) {
+            if let Err(e) = result {
+                error!("{} did not complete its shutdown {:?}", id, e);
+            }
+        }
+    }
+
     fn has_event_listener(&self, input: &str) -> bool {
         let processors = self.event_processors.read().unwrap();
         processors.contains_key(input)
     }
 
     fn handle_no_processor_error(&self, message: ExtnMessage) {
+        self.handle_error(message, RippleError::ProcessorError)
+    }
+
+    fn handle_error(&self, message: ExtnMessage, error: RippleError) {
         let req_sender = self.get_extn_sender_with_extn_id(&message.requestor.to_string());
 
-        if let Ok(resp) = message.get_response(ExtnResponse::Error(RippleError::ProcessorError)) {
+        if let Ok(resp) = message.get_response(ExtnResponse::Error(error)) {
             if self.sender.respond(resp.into(), req_sender).is_err() {
                 error!("Couldnt send no processor response");
             }
         }
     }
 
+    /// Answers a request from Main which needs no processor
+    fn acknowledge(&self, message: ExtnMessage) {
+        let req_sender = self.get_extn_sender_with_extn_id(&message.requestor.to_string());
+
+        if let Ok(resp) = message.get_response(ExtnResponse::None(())) {
+            if self.sender.respond(resp.into(), req_sender).is_err() {
+                error!("Couldnt send {:?} response", message.payload);
+            }
+        }
+    }
+
+    /// Forwards the chunks of a stream requested by this client, returns false for other responses
+    fn handle_stream_response(&self, message: &ExtnMessage) -> bool {
+        let Some(response) = message.payload.as_response() else {
+            return false;
+        };
+        if let ExtnResponse::StreamEnd = response {
+            // dropping the sender ends the stream once the consumer read the pending chunks
+            return self
+                .stream_processors
+                .write()
+                .unwrap()
+                .remove(&message.id)
+                .is_some();
+        }
+        let mut stream_processors = self.stream_processors.write().unwrap();
+        let Some(sender) = stream_processors.get(&message.id) else {
+            return false;
+        };
+        // the last slot is kept for the error ending a stream which sent more than asked for
+        let response = match response {
+            ExtnResponse::Error(_) => response,
+            _ if sender.capacity() > 1 => response,
+            _ => {
+                error!("Ending stream {} sent over its demand", message.id);
+                ExtnResponse::Error(RippleError::InvalidOutput)
+            }
+        };
+        // an error is the last response of a stream which failed
+        let failed = matches!(response, ExtnResponse::Error(_));
+        if let Err(e) = sender.try_send(response) {
+            error!(
+                "Unable to forward a response of stream {} {:?}",
+                message.id, e
+            );
+        }
+        if failed {
+            stream_processors.remove(&message.id);
+        }
+        true
+    }
+
+    /// Delivers a signal of the consumer to a stream emitted by this client
+    fn handle_stream_control(&self, control: ExtnStreamControl) -> bool {
+        let stream_controls = self.stream_controls.read().unwrap();
+        let Some(sender) = stream_controls.get(&control.id) else {
+            debug!("No stream {} for {:?}", control.id, control.signal);
+            return false;
+        };
+        if let Err(e) = sender.try_send(control.signal) {
+            error!("Unable to signal stream {} {:?}", control.id, e);
+        }
+        true
+    }
+
     fn handle_single(
         msg: ExtnMessage,
         processor: Arc<RwLock<HashMap<String, OSender<ExtnMessage>>>>,
@@ -544,22 +798,31 @@ impl ExtnClient {
         &self,
         contract: RippleContract,
     ) -> Option<CSender<CExtnMessage>> {
-        let contract_str: String = contract.as_clear_string();
-        debug!("get_extn_sender_with_contract: {}", contract_str);
-        let id = {
-            self.contract_map
-                .read()
-                .unwrap()
-                .get(&contract_str)
-                .cloned()
-        };
-        if let Some(extn_id) = id {
+        debug!(
+            "get_extn_sender_with_contract: {}",
+            contract.as_clear_string()
+        );
+        if let Some(extn_id) = self.get_extn_id_with_contract(&contract) {
             return self.get_extn_sender_with_extn_id(&extn_id);
         }
 
         None
     }
 
+    /// Unhealthy extensions fall back to the next extension in priority order
+    fn get_extn_id_with_contract(&self, contract: &RippleContract) -> Option<String> {
+        self.contract_map
+            .read()
+            .unwrap()
+            .get(&contract.as_clear_string())
+            .and_then(|candidates| {
+                candidates
+                    .iter()
+                    .find(|c| self.is_extn_healthy(&c.id))
+                    .map(|c| c.id.clone())
+            })
+    }
+
     pub fn get_extn_sender_with_extn_id(&self, id: &str) -> Option<CSender<CExtnMessage>> {
         return self.extn_sender_map.read().unwrap().get(id).cloned();
     }
@@ -609,6 +872,7 @@ impl ExtnClient {
         &mut self,
         payload: impl ExtnPayloadProvider,
     ) -> Result<ExtnMessage, RippleError> {
+        self.check_contract_health(&payload.get_contract())?;
         let id = uuid::Uuid::new_v4().to_string();
         let (tx, rx) = oneshot::channel();
         add_single_processor(id.clone(), Some(tx), self.response_processors.clone());
@@ -621,6 +885,35 @@ impl ExtnClient {
         Err(RippleError::ExtnError)
     }
 
+    /// Same as [ExtnClient::request], the response processor is removed once the timeout
+    /// elapses so that late responses are dropped.
+    pub async fn request_message_with_timeout(
+        &mut self,
+        payload: impl ExtnPayloadProvider,
+        timeout_in_msecs: u64,
+    ) -> Result<ExtnMessage, RippleError> {
+        self.check_contract_health(&payload.get_contract())?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let (tx, rx) = oneshot::channel();
+        add_single_processor(id.clone(), Some(tx), self.response_processors.clone());
+        let other_sender = self.get_extn_sender_with_contract(payload.get_contract());
+        let result = match self
+            .sender
+            .send_request(id.clone(), payload, other_sender, None)
+        {
+            Ok(_) => {
+                match tokio::time::timeout(Duration::from_millis(timeout_in_msecs), rx).await {
+                    Ok(Ok(r)) => Ok(r),
+                    Ok(Err(_)) => Err(RippleError::ExtnError),
+                    Err(_) => Err(RippleError::TimeoutError),
+                }
+            }
+            Err(e) => Err(e),
+        };
+        remove_processor(id, self.response_processors.clone());
+        result
+    }
+
     pub async fn send_rpc_main(
         &mut self,
         payload: impl ExtnPayloadProvider,
@@ -682,6 +975,7 @@ impl ExtnClient {
             return Err(RippleError::InvalidAccess);
         }
         trace!("Main internal request with payload: {:?}", payload);
+        self.check_contract_health(&payload.get_contract())?;
 
         let id = uuid::Uuid::new_v4().to_string();
         let (tx, rx) = oneshot::channel();
@@ -858,6 +1152,7 @@ impl ExtnClient {
         payload: impl ExtnPayloadProvider,
         timeout_in_msecs: u64,
     ) -> Result<T, RippleError> {
+        self.check_contract_health(&payload.get_contract())?;
         let id = uuid::Uuid::new_v4().to_string();
         let (tx, tr) = bounded(2);
         let other_sender = self.get_extn_sender_with_contract(payload.get_contract());
@@ -885,6 +1180,62 @@ impl ExtnClient {
         Err(RippleError::InvalidOutput)
     }
 
+    /// Request method for processors which answer with a stream of responses, see
+    /// [ExtnClient::open_stream]. The processor emits up to [EXTN_STREAM_WINDOW] chunks ahead of
+    /// the consumer and more as the returned stream is read. The stream ends after the last
+    /// chunk or after an [ExtnResponse::Error] and dropping it cancels the stream.
+    ///
+    /// # Arguments
+    /// `payload` - impl [ExtnPayloadProvider]
+    pub fn request_stream(
+        &self,
+        payload: impl ExtnPayloadProvider,
+    ) -> Result<ExtnResponseStream, RippleError> {
+        let contract = payload.get_contract();
+        self.check_contract_health(&contract)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        // room for the whole window and the error ending the stream
+        let (tx, rx) = mpsc::channel(EXTN_STREAM_WINDOW as usize + 1);
+        add_single_processor(id.clone(), Some(tx), self.stream_processors.clone());
+        let other_sender = self.get_extn_sender_with_contract(contract.clone());
+        if let Err(e) = self
+            .sender
+            .send_request(id.clone(), payload, other_sender.clone(), None)
+        {
+            remove_processor(id, self.stream_processors.clone());
+            return Err(e);
+        }
+        Ok(ExtnResponseStream {
+            id,
+            contract,
+            rx,
+            sender: self.sender.clone(),
+            other_sender,
+            stream_processors: self.stream_processors.clone(),
+            consumed: 0,
+            ended: false,
+        })
+    }
+
+    /// Used by processors to answer a request with a stream of responses. The stream ends when the
+    /// returned sink is dropped.
+    ///
+    /// # Arguments
+    /// `request` - [ExtnMessage] request object
+    pub fn open_stream(&self, request: ExtnMessage) -> ExtnStreamSink {
+        let (tx, rx) = mpsc::channel(EXTN_STREAM_WINDOW as usize);
+        add_single_processor(request.id.clone(), Some(tx), self.stream_controls.clone());
+        ExtnStreamSink {
+            client: self.clone(),
+            request,
+            credits: EXTN_STREAM_WINDOW,
+            signals: rx,
+            cancelled: false,
+            timed_out: false,
+            idle_timeout: EXTN_STREAM_IDLE_TIMEOUT,
+        }
+    }
+
     /// Request method which accepts a impl [ExtnPayloadProvider] and uses the capability provided by the trait to send the request.
     /// This method doesnt provide a response it just provides a result after a successful send. Useful for transient requests from
     /// protocols which do not need a single point of request and response.
@@ -895,6 +1246,7 @@ impl ExtnClient {
         &self,
         payload: impl ExtnPayloadProvider,
     ) -> Result<String, RippleError> {
+        self.check_contract_health(&payload.get_contract())?;
         let id = uuid::Uuid::new_v4().to_string();
         let other_sender = self.get_extn_sender_with_contract(payload.get_contract());
         self.sender
@@ -1007,6 +1359,146 @@ impl ExtnClient {
     }
 }
 
+/// Responses of a request made with [ExtnClient::request_stream]
+#[derive(Debug)]
+pub struct ExtnResponseStream {
+    id: String,
+    contract: RippleContract,
+    rx: MReceiver<ExtnResponse>,
+    sender: ExtnSender,
+    other_sender: Option<CSender<CExtnMessage>>,
+    stream_processors: Arc<RwLock<HashMap<String, MSender<ExtnResponse>>>>,
+    consumed: u32,
+    ended: bool,
+}
+
+impl ExtnResponseStream {
+    fn signal(&self, signal: ExtnStreamSignal) {
+        let control = ExtnStreamControl {
+            id: self.id.clone(),
+            contract: self.contract.clone(),
+            signal,
+        };
+        if let Err(e) = self.sender.send_request(
+            uuid::Uuid::new_v4().to_string(),
+            control,
+            self.other_sender.clone(),
+            None,
+        ) {
+            error!("Unable to signal stream {} {:?}", self.id, e);
+        }
+    }
+}
+
+impl Stream for ExtnResponseStream {
+    type Item = ExtnResponse;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<ExtnResponse>> {
+        match self.rx.poll_recv(cx) {
+            Poll::Ready(Some(response)) => {
+                self.consumed += 1;
+                // asks for more once half of the window is read
+                if self.consumed >= EXTN_STREAM_WINDOW / 2 {
+                    self.signal(ExtnStreamSignal::Demand(self.consumed));
+                    self.consumed = 0;
+                }
+                Poll::Ready(Some(response))
+            }
+            Poll::Ready(None) => {
+                self.ended = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for ExtnResponseStream {
+    fn drop(&mut self) {
+        if !self.ended {
+            remove_processor(self.id.clone(), self.stream_processors.clone());
+            self.signal(ExtnStreamSignal::Cancel);
+        }
+    }
+}
+
+/// Emits the responses of a stream opened with [ExtnClient::open_stream]
+#[derive(Debug)]
+pub struct ExtnStreamSink {
+    client: ExtnClient,
+    request: ExtnMessage,
+    credits: u32,
+    signals: MReceiver<ExtnStreamSignal>,
+    cancelled: bool,
+    timed_out: bool,
+    idle_timeout: Duration,
+}
+
+impl ExtnStreamSink {
+    fn apply(&mut self, signal: ExtnStreamSignal) {
+        match signal {
+            ExtnStreamSignal::Demand(demand) => self.credits += demand,
+            ExtnStreamSignal::Cancel => self.cancelled = true,
+        }
+    }
+
+    /// Sends the next chunk of the stream, waits while the consumer has not asked for more.
+    /// Fails with [RippleError::SendFailure] once the consumer cancelled the stream and with
+    /// [RippleError::TimeoutError] once it stopped reading it, the consumer then gets the
+    /// timeout as the last response.
+    pub async fn send(&mut self, chunk: ExtnResponse) -> RippleResponse {
+        while let Ok(signal) = self.signals.try_recv() {
+            self.apply(signal);
+        }
+        while self.credits == 0 && !self.cancelled && !self.timed_out {
+            match tokio::time::timeout(self.idle_timeout, self.signals.recv()).await {
+                Ok(Some(signal)) => self.apply(signal),
+                _ => {
+                    warn!("Consumer of stream {} stopped reading", self.request.id);
+                    self.timed_out = true;
+                }
+            }
+        }
+        if self.cancelled {
+            return Err(RippleError::SendFailure);
+        }
+        if self.timed_out {
+            return Err(RippleError::TimeoutError);
+        }
+        self.credits -= 1;
+        let msg = self.request.get_response(chunk)?;
+        self.client.send_message(msg).await
+    }
+
+    /// Whether the consumer cancelled the stream or stopped reading it
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled || self.timed_out
+    }
+}
+
+impl Drop for ExtnStreamSink {
+    fn drop(&mut self) {
+        remove_processor(self.request.id.clone(), self.client.stream_controls.clone());
+        // a cancelled stream is already gone on the side of the consumer
+        if self.cancelled {
+            return;
+        }
+        let last = if self.timed_out {
+            ExtnResponse::Error(RippleError::TimeoutError)
+        } else {
+            ExtnResponse::StreamEnd
+        };
+        if let Ok(msg) = self.request.get_response(last) {
+            let req_sender = self
+                .client
+                .get_extn_sender_with_extn_id(&msg.requestor.to_string());
+            if let Err(e) = self.client.sender.respond(msg.into(), req_sender) {
+                error!("Unable to end stream {} {:?}", self.request.id, e);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -1024,7 +1516,7 @@ pub mod tests {
             client::{
                 extn_processor::{
                     tests::{MockEventProcessor, MockRequestProcessor},
-                    ExtnStreamProcessor,
+                    DefaultExtnStreamer, ExtnRequestProcessor, ExtnStreamProcessor, ExtnStreamer,
                 },
                 extn_sender::tests::Mockable as extn_sender_mockable,
             },
@@ -1037,9 +1529,14 @@ pub mod tests {
         },
     };
     use async_channel::unbounded;
+    use async_trait::async_trait;
     use core::panic;
+    use futures::StreamExt;
     use rstest::rstest;
-    use std::collections::HashMap;
+    use std::{
+        collections::HashMap,
+        sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    };
     use testing_logger::{self, validate};
     use tokio::sync::{mpsc, oneshot};
     use tokio::time::Duration;
@@ -1114,6 +1611,17 @@ pub mod tests {
         assert_eq!(result.unwrap(), ExtnResponse::String("success".to_string()));
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_message_with_timeout() {
+        let mut client = ExtnClient::mock();
+        let result = client
+            .request_message_with_timeout(AccountSessionRequest::Get, 100)
+            .await;
+        assert_eq!(result.unwrap_err(), RippleError::TimeoutError);
+        // the response processor does not outlive the request
+        assert!(client.response_processors.read().unwrap().is_empty());
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_request_with_timeout_main() {
         let mut client = ExtnClient::mock();
@@ -1317,6 +1825,326 @@ pub mod tests {
         assert!(senders.is_some(), "Expected Some, got None");
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_contract_resolution() {
+        let mut extn_client = ExtnClient::mock();
+        let symbol = |id: &ExtnId| ExtnSymbol {
+            id: id.to_string(),
+            uses: Vec::new(),
+            fulfills: vec![RippleContract::DeviceInfo.as_clear_string()],
+            config: None,
+        };
+        let resolution = |priority, exclusion| {
+            vec![ExtnResolutionEntry {
+                capability: RippleContract::DeviceInfo.as_clear_string(),
+                priority: Some(priority),
+                exclusion,
+            }]
+        };
+        let first = ExtnId::new_channel(ExtnClassId::Device, "first".into());
+        let second = ExtnId::new_channel(ExtnClassId::Device, "second".into());
+        let (first_tx, _first_rx) = unbounded();
+        let (second_tx, _second_rx) = unbounded();
+        extn_client.add_sender(first.clone(), symbol(&first), first_tx);
+        extn_client.add_sender(second.clone(), symbol(&second), second_tx);
+        let resolved =
+            |client: &ExtnClient| client.get_extn_id_with_contract(&RippleContract::DeviceInfo);
+
+        // latest extension is preferred without resolution
+        assert_eq!(resolved(&extn_client), Some(second.to_string()));
+
+        extn_client.set_resolution(&first, &resolution(1, None));
+        extn_client.set_resolution(&second, &resolution(2, None));
+        assert_eq!(resolved(&extn_client), Some(first.to_string()));
+
+        // unhealthy extensions fall back to the next priority
+        extn_client.set_extn_health(&first.to_string(), false);
+        assert_eq!(resolved(&extn_client), Some(second.to_string()));
+
+        extn_client.set_extn_health(&second.to_string(), false);
+        assert!(resolved(&extn_client).is_none());
+        let response = extn_client
+            .request(MockRequest {
+                app_id: "test_app_id".to_string(),
+                contract: RippleContract::DeviceInfo,
+                expected_response: None,
+            })
+            .await;
+        assert_eq!(response.unwrap_err(), RippleError::ExtnUnhealthy);
+
+        extn_client.set_extn_health(&second.to_string(), true);
+        assert_eq!(resolved(&extn_client), Some(second.to_string()));
+
+        extn_client.set_resolution(&second, &resolution(2, Some(true)));
+        assert!(resolved(&extn_client).is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ping() {
+        let (main_sender, main_rx) = ExtnSender::mock();
+        let main_client = ExtnClient::new(main_rx.clone(), main_sender.clone());
+        let extn_id = ExtnId::new_channel(ExtnClassId::Device, "info".into());
+        let (extn_sender, extn_tx, extn_rx) = ExtnSender::mock_extn(
+            extn_id.clone(),
+            Vec::new(),
+            vec![RippleContract::DeviceInfo.as_clear_string()],
+            Some(HashMap::new()),
+            main_sender.tx.clone(),
+        );
+        let extn_client = ExtnClient::new(extn_rx.clone(), extn_sender.clone());
+        main_client.clone().add_sender(
+            extn_id.clone(),
+            ExtnSymbol {
+                id: extn_id.to_string(),
+                uses: Vec::new(),
+                fulfills: vec![RippleContract::DeviceInfo.as_clear_string()],
+                config: Some(HashMap::new()),
+            },
+            extn_tx,
+        );
+        let main_client_for_thread = main_client.clone();
+        tokio::spawn(async move {
+            main_client_for_thread.initialize().await;
+        });
+
+        // the extension is not processing its messages yet
+        assert_eq!(
+            main_client.ping(&extn_id.to_string(), 100).await,
+            Err(RippleError::TimeoutError)
+        );
+
+        tokio::spawn(async move {
+            extn_client.initialize().await;
+        });
+        assert!(main_client.ping(&extn_id.to_string(), 1000).await.is_ok());
+        assert_eq!(
+            main_client.ping("ripple:channel:device:unknown", 100).await,
+            Err(RippleError::SenderMissing)
+        );
+    }
+
+    #[derive(Debug, Clone)]
+    struct MockStreamState {
+        client: ExtnClient,
+        sent: Arc<AtomicU32>,
+        cancelled: Arc<AtomicBool>,
+        idle_timeout: Duration,
+    }
+
+    /// Streams the numbers below the [ExtnResponse::Number] of the request
+    struct MockStreamProcessor {
+        state: MockStreamState,
+        streamer: DefaultExtnStreamer,
+    }
+
+    impl ExtnStreamProcessor for MockStreamProcessor {
+        type STATE = MockStreamState;
+        type VALUE = MockRequest;
+
+        fn get_state(&self) -> Self::STATE {
+            self.state.clone()
+        }
+
+        fn receiver(&mut self) -> mpsc::Receiver<ExtnMessage> {
+            self.streamer.receiver()
+        }
+
+        fn sender(&self) -> mpsc::Sender<ExtnMessage> {
+            self.streamer.sender()
+        }
+
+        fn contract(&self) -> RippleContract {
+            RippleContract::DeviceInfo
+        }
+    }
+
+    #[async_trait]
+    impl ExtnRequestProcessor for MockStreamProcessor {
+        fn get_client(&self) -> ExtnClient {
+            self.state.client.clone()
+        }
+
+        async fn process_request(
+            state: Self::STATE,
+            msg: ExtnMessage,
+            request: Self::VALUE,
+        ) -> bool {
+            let Some(ExtnResponse::Number(count)) = request.expected_response else {
+                return false;
+            };
+            let mut sink = Self::open_stream(state.client.clone(), msg);
+            sink.idle_timeout = state.idle_timeout;
+            tokio::spawn(async move {
+                for i in 0..count {
+                    if sink.send(ExtnResponse::Number(i)).await.is_err() {
+                        state.cancelled.store(true, Ordering::SeqCst);
+                        return;
+                    }
+                    state.sent.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+            true
+        }
+    }
+
+    /// Starts main and an extension streaming [RippleContract::DeviceInfo]
+    fn start_stream_extn(idle_timeout: Duration) -> (ExtnClient, MockStreamState) {
+        let (main_sender, main_rx) = ExtnSender::mock();
+        let main_client = ExtnClient::new(main_rx, main_sender.clone());
+        let extn_id = ExtnId::new_channel(ExtnClassId::Device, "info".into());
+        let (extn_sender, extn_tx, extn_rx) = ExtnSender::mock_extn(
+            extn_id.clone(),
+            Vec::new(),
+            vec![RippleContract::DeviceInfo.as_clear_string()],
+            Some(HashMap::new()),
+            main_sender.tx.clone(),
+        );
+        let mut extn_client = ExtnClient::new(extn_rx, extn_sender);
+        main_client.clone().add_sender(
+            extn_id.clone(),
+            ExtnSymbol {
+                id: extn_id.to_string(),
+                uses: Vec::new(),
+                fulfills: vec![RippleContract::DeviceInfo.as_clear_string()],
+                config: Some(HashMap::new()),
+            },
+            extn_tx,
+        );
+        let state = MockStreamState {
+            client: extn_client.clone(),
+            sent: Arc::new(AtomicU32::new(0)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            idle_timeout,
+        };
+        extn_client.add_request_processor(MockStreamProcessor {
+            state: state.clone(),
+            streamer: DefaultExtnStreamer::new(),
+        });
+        let main_client_for_thread = main_client.clone();
+        tokio::spawn(async move {
+            main_client_for_thread.initialize().await;
+        });
+        tokio::spawn(async move {
+            extn_client.initialize().await;
+        });
+        (main_client, state)
+    }
+
+    fn stream_request(count: u32) -> MockRequest {
+        MockRequest {
+            app_id: "test_app_id".to_string(),
+            contract: RippleContract::DeviceInfo,
+            expected_response: Some(ExtnResponse::Number(count)),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_stream() {
+        let (main_client, state) = start_stream_extn(EXTN_STREAM_IDLE_TIMEOUT);
+        let count = EXTN_STREAM_WINDOW * 3;
+        let stream = main_client.request_stream(stream_request(count)).unwrap();
+        let chunks: Vec<ExtnResponse> =
+            tokio::time::timeout(Duration::from_secs(5), stream.collect())
+                .await
+                .unwrap();
+        let expected: Vec<ExtnResponse> = (0..count).map(ExtnResponse::Number).collect();
+        assert_eq!(chunks, expected);
+        assert_eq!(state.sent.load(Ordering::SeqCst), count);
+        assert!(main_client.stream_processors.read().unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_stream_backpressure() {
+        let (main_client, state) = start_stream_extn(EXTN_STREAM_IDLE_TIMEOUT);
+        let count = EXTN_STREAM_WINDOW * 2;
+        let mut stream = main_client.request_stream(stream_request(count)).unwrap();
+
+        // the extension stops at the window while nothing is read
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(state.sent.load(Ordering::SeqCst), EXTN_STREAM_WINDOW);
+
+        for i in 0..count {
+            let chunk = tokio::time::timeout(Duration::from_secs(5), stream.next())
+                .await
+                .unwrap();
+            assert_eq!(chunk, Some(ExtnResponse::Number(i)));
+        }
+        let end = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap();
+        assert_eq!(end, None);
+        assert!(!state.cancelled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_stream_cancel() {
+        let (main_client, state) = start_stream_extn(EXTN_STREAM_IDLE_TIMEOUT);
+        let count = EXTN_STREAM_WINDOW * 4;
+        let mut stream = main_client.request_stream(stream_request(count)).unwrap();
+        for i in 0..3 {
+            assert_eq!(stream.next().await, Some(ExtnResponse::Number(i)));
+        }
+        drop(stream);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(state.cancelled.load(Ordering::SeqCst));
+        assert_eq!(state.sent.load(Ordering::SeqCst), EXTN_STREAM_WINDOW);
+        assert!(main_client.stream_processors.read().unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_request_stream_idle_timeout() {
+        let (main_client, state) = start_stream_extn(Duration::from_millis(100));
+        let count = EXTN_STREAM_WINDOW * 2;
+        let mut stream = main_client.request_stream(stream_request(count)).unwrap();
+
+        // the stream ends with an error once the extension gave up on the slow consumer
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(state.cancelled.load(Ordering::SeqCst));
+        for i in 0..EXTN_STREAM_WINDOW {
+            assert_eq!(stream.next().await, Some(ExtnResponse::Number(i)));
+        }
+        let last = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap();
+        assert_eq!(last, Some(ExtnResponse::Error(RippleError::TimeoutError)));
+        assert_eq!(stream.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_request_stream_over_demand() {
+        let client = ExtnClient::mock();
+        let (tx, mut rx) = mpsc::channel(EXTN_STREAM_WINDOW as usize + 1);
+        add_single_processor(
+            "stream".to_owned(),
+            Some(tx),
+            client.stream_processors.clone(),
+        );
+        let chunk = |i: u32| ExtnMessage {
+            id: "stream".to_owned(),
+            requestor: ExtnId::get_main_target("main".into()),
+            target: RippleContract::DeviceInfo,
+            target_id: None,
+            payload: ExtnPayload::Response(ExtnResponse::Number(i)),
+            callback: None,
+            ts: None,
+        };
+        for i in 0..EXTN_STREAM_WINDOW + 2 {
+            assert_eq!(
+                client.handle_stream_response(&chunk(i)),
+                i <= EXTN_STREAM_WINDOW
+            );
+        }
+        for i in 0..EXTN_STREAM_WINDOW {
+            assert_eq!(rx.recv().await, Some(ExtnResponse::Number(i)));
+        }
+        assert_eq!(
+            rx.recv().await,
+            Some(ExtnResponse::Error(RippleError::InvalidOutput))
+        );
+        assert_eq!(rx.recv().await, None);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_get_extn_sender_with_extn_id() {
         let extn_client = ExtnClient::mock();
@@ -1923,6 +2751,130 @@ pub mod tests {
         );
     }
 
+    #[derive(Debug, Clone)]
+    struct MockShutdownState {
+        client: ExtnClient,
+        flushed: Arc<AtomicBool>,
+    }
+
+    /// Persists its state on [ExtnShutdown] before responding
+    struct MockShutdownProcessor {
+        state: MockShutdownState,
+        streamer: DefaultExtnStreamer,
+    }
+
+    impl ExtnStreamProcessor for MockShutdownProcessor {
+        type STATE = MockShutdownState;
+        type VALUE = ExtnShutdown;
+
+        fn get_state(&self) -> Self::STATE {
+            self.state.clone()
+        }
+
+        fn receiver(&mut self) -> mpsc::Receiver<ExtnMessage> {
+            self.streamer.receiver()
+        }
+
+        fn sender(&self) -> mpsc::Sender<ExtnMessage> {
+            self.streamer.sender()
+        }
+    }
+
+    #[async_trait]
+    impl ExtnRequestProcessor for MockShutdownProcessor {
+        fn get_client(&self) -> ExtnClient {
+            self.state.client.clone()
+        }
+
+        async fn process_request(
+            state: Self::STATE,
+            msg: ExtnMessage,
+            _request: Self::VALUE,
+        ) -> bool {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            state.flushed.store(true, Ordering::SeqCst);
+            Self::respond(state.client, msg, ExtnResponse::None(()))
+                .await
+                .is_ok()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_shutdown() {
+        let (main_sender, main_rx) = ExtnSender::mock();
+        let main_client = ExtnClient::new(main_rx, main_sender.clone());
+        let main_client_for_thread = main_client.clone();
+        tokio::spawn(async move {
+            main_client_for_thread.initialize().await;
+        });
+        let mut flushed = Vec::new();
+        for (name, persists) in [("store", true), ("info", false)] {
+            let extn_id = ExtnId::new_channel(ExtnClassId::Device, name.into());
+            let (extn_sender, extn_tx, extn_rx) = ExtnSender::mock_extn(
+                extn_id.clone(),
+                Vec::new(),
+                Vec::new(),
+                Some(HashMap::new()),
+                main_sender.tx.clone(),
+            );
+            let mut extn_client = ExtnClient::new(extn_rx, extn_sender);
+            main_client.clone().add_sender(
+                extn_id.clone(),
+                ExtnSymbol {
+                    id: extn_id.to_string(),
+                    uses: Vec::new(),
+                    fulfills: Vec::new(),
+                    config: Some(HashMap::new()),
+                },
+                extn_tx,
+            );
+            if persists {
+                let state = MockShutdownState {
+                    client: extn_client.clone(),
+                    flushed: Arc::new(AtomicBool::new(false)),
+                };
+                flushed.push(state.flushed.clone());
+                extn_client.add_request_processor(MockShutdownProcessor {
+                    state,
+                    streamer: DefaultExtnStreamer::new(),
+                });
+            }
+            tokio::spawn(async move {
+                extn_client.initialize().await;
+            });
+        }
+
+        // returns once the extension persisted its state
+        main_client.shutdown(1000).await;
+        assert!(flushed.iter().all(|f| f.load(Ordering::SeqCst)));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_shutdown_timeout() {
+        let (main_sender, main_rx) = ExtnSender::mock();
+        let main_client = ExtnClient::new(main_rx, main_sender);
+        let main_client_for_thread = main_client.clone();
+        tokio::spawn(async move {
+            main_client_for_thread.initialize().await;
+        });
+        let (s, _receiver) = unbounded();
+        main_client.clone().add_sender(
+            ExtnId::new_channel(ExtnClassId::Device, "thunder".into()),
+            ExtnSymbol {
+                id: "id".to_string(),
+                uses: Vec::new(),
+                fulfills: Vec::new(),
+                config: None,
+            },
+            s,
+        );
+
+        // an extension which never responds does not hold the shutdown past the timeout
+        tokio::time::timeout(Duration::from_secs(1), main_client.shutdown(100))
+            .await
+            .unwrap();
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_handle_no_processor_error() {
         let (mock_sender, mock_rx) = ExtnSender::mock();
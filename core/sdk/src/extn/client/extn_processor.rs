@@ -15,7 +15,7 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use super::extn_client::ExtnClient;
+use super::extn_client::{ExtnClient, ExtnStreamSink};
 use crate::{
     extn::extn_client_message::{ExtnMessage, ExtnPayload, ExtnPayloadProvider, ExtnResponse},
     framework::{ripple_contract::RippleContract, RippleResponse},
@@ -222,6 +222,12 @@ pub trait ExtnRequestProcessor: ExtnStreamProcessor + Send + Sync + 'static {
         extn_client.send_message(request.ack()).await
     }
 
+    /// Answers the request with a stream of responses, each [ExtnStreamSink::send] emits a chunk
+    /// and dropping the sink ends the stream.
+    fn open_stream(extn_client: ExtnClient, request: ExtnMessage) -> ExtnStreamSink {
+        extn_client.open_stream(request)
+    }
+
     async fn run(&mut self) {
         debug!(
             "starting request processor for contract {}",
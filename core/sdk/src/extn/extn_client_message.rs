@@ -62,7 +62,7 @@ use crate::{
         observability::analytics::AnalyticsRequest,
         session::{AccountSessionRequest, AccountSessionResponse, SessionTokenRequest},
         settings::{SettingValue, SettingsRequest},
-        status_update::ExtnStatus,
+        status_update::{ExtnHealthCheck, ExtnShutdown, ExtnStatus},
         storage_property::StorageManagerRequest,
         usergrant_entry::UserGrantsStoreRequest,
     },
@@ -70,7 +70,7 @@ use crate::{
     utils::error::RippleError,
 };
 
-use super::{extn_id::ExtnId, ffi::ffi_message::CExtnMessage};
+use super::{extn_id::ExtnId, extn_rpc::ExtnRpcRequest, ffi::ffi_message::CExtnMessage};
 
 /// Default Message enum for the Communication Channel
 /// Message would be either a request or response or event
@@ -318,6 +318,10 @@ pub enum ExtnRequest {
     DistributorToken(DistributorTokenRequest),
     Context(RippleContextUpdateRequest),
     Analytics(AnalyticsRequest),
+    HealthCheck(ExtnHealthCheck),
+    Shutdown(ExtnShutdown),
+    ExtnRpc(ExtnRpcRequest),
+    StreamControl(ExtnStreamControl),
 }
 
 impl ExtnPayloadProvider for ExtnRequest {
@@ -338,6 +342,47 @@ impl ExtnPayloadProvider for ExtnRequest {
     }
 }
 
+/// Signal from the consumer of a stream to the extension emitting it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExtnStreamSignal {
+    /// The consumer can take this many more chunks
+    Demand(u32),
+    Cancel,
+}
+
+/// Sent by the consumer of a stream to the extension emitting it, through the contract of the
+/// request which opened the stream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtnStreamControl {
+    /// Id of the request which opened the stream
+    pub id: String,
+    pub contract: RippleContract,
+    pub signal: ExtnStreamSignal,
+}
+
+impl ExtnPayloadProvider for ExtnStreamControl {
+    fn get_extn_payload(&self) -> ExtnPayload {
+        ExtnPayload::Request(ExtnRequest::StreamControl(self.clone()))
+    }
+
+    fn get_from_payload(payload: ExtnPayload) -> Option<Self> {
+        if let ExtnPayload::Request(ExtnRequest::StreamControl(r)) = payload {
+            return Some(r);
+        }
+
+        None
+    }
+
+    fn contract() -> RippleContract {
+        // Replaced by the contract of the stream
+        RippleContract::Internal
+    }
+
+    fn get_contract(&self) -> RippleContract {
+        self.contract.clone()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ExtnResponse {
     None(()),
@@ -362,6 +407,8 @@ pub enum ExtnResponse {
     Settings(HashMap<String, SettingValue>),
     BoolMap(HashMap<String, bool>),
     SecureStorage(SecureStorageResponse),
+    /// Last response of a stream, see [crate::extn::client::extn_client::ExtnClient::request_stream]
+    StreamEnd,
 }
 
 impl ExtnPayloadProvider for ExtnResponse {
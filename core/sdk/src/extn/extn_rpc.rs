@@ -0,0 +1,506 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+use std::{fmt::Debug, marker::PhantomData, sync::Arc};
+
+use async_trait::async_trait;
+use log::error;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use tokio::sync::mpsc::{Receiver as MReceiver, Sender as MSender};
+
+use crate::{
+    extn::{
+        client::{
+            extn_client::ExtnClient,
+            extn_processor::{
+                DefaultExtnStreamer, ExtnRequestProcessor, ExtnStreamProcessor, ExtnStreamer,
+            },
+        },
+        extn_client_message::{
+            ExtnMessage, ExtnPayload, ExtnPayloadProvider, ExtnRequest, ExtnResponse,
+        },
+    },
+    framework::ripple_contract::{ContractAdjective, RippleContract},
+    utils::error::RippleError,
+};
+
+/// Name of a typed contract between extensions. Extensions list it as `<name>.extn_rpc` in the
+/// `fulfills` and `uses` of their manifest entry, so the name cannot contain a `.`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExtnRpcAdjective {
+    pub name: String,
+}
+
+impl Serialize for ExtnRpcAdjective {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.name)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExtnRpcAdjective {
+    fn deserialize<D>(deserializer: D) -> Result<ExtnRpcAdjective, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        Ok(ExtnRpcAdjective { name })
+    }
+}
+
+/// Returns false for the names which do not round trip through the `<name>.extn_rpc` entries
+/// of the manifest, checked at compile time by [crate::extn_rpc_contract]
+pub const fn is_valid_contract_name(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'.' {
+            return false;
+        }
+        i += 1;
+    }
+    !bytes.is_empty()
+}
+
+impl ContractAdjective for ExtnRpcAdjective {
+    fn get_contract(&self) -> RippleContract {
+        RippleContract::ExtnRpc(self.clone())
+    }
+}
+
+/// Defines the request and response of a contract between extensions once, the provider
+/// registers an [ExtnRpcProcessor] and callers use an [ExtnRpcClient] of the same contract.
+/// Use [crate::extn_rpc_contract] to declare one.
+pub trait ExtnRpcContract: Send + Sync + 'static {
+    type Request: Serialize + DeserializeOwned + Debug + Send + Sync + 'static;
+    type Response: Serialize + DeserializeOwned + Debug + Send + Sync + 'static;
+
+    fn name() -> &'static str;
+
+    fn contract() -> RippleContract {
+        RippleContract::ExtnRpc(ExtnRpcAdjective {
+            name: Self::name().to_owned(),
+        })
+    }
+}
+
+/// Declares an [ExtnRpcContract], the name of the contract cannot contain a `.`
+///
+/// # Example
+/// ```
+/// use ripple_sdk::extn_rpc_contract;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// pub struct DeviceNameRequest {
+///     pub lang: String,
+/// }
+///
+/// extn_rpc_contract!(DeviceNameContract, "device_name", DeviceNameRequest, String);
+/// ```
+#[macro_export]
+macro_rules! extn_rpc_contract {
+    ($contract:ident, $name:expr, $request:ty, $response:ty) => {
+        pub struct $contract;
+
+        const _: () = assert!(
+            $crate::extn::extn_rpc::is_valid_contract_name($name),
+            "extn rpc contract names cannot be empty or contain a ."
+        );
+
+        impl $crate::extn::extn_rpc::ExtnRpcContract for $contract {
+            type Request = $request;
+            type Response = $response;
+
+            fn name() -> &'static str {
+                $name
+            }
+        }
+    };
+}
+
+/// Errors of a call on an [ExtnRpcClient]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtnRpcError {
+    /// The request or the response does not match the types of the contract
+    Serialization(String),
+    /// The caller does not list the contract in the `uses` of its manifest entry
+    PermissionDenied(String),
+    /// Error returned by the provider or by the extension messaging
+    Ripple(RippleError),
+}
+
+impl std::fmt::Display for ExtnRpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtnRpcError::Serialization(e) => write!(f, "Serialization {}", e),
+            ExtnRpcError::PermissionDenied(c) => write!(f, "PermissionDenied {}", c),
+            ExtnRpcError::Ripple(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<RippleError> for ExtnRpcError {
+    fn from(value: RippleError) -> Self {
+        ExtnRpcError::Ripple(value)
+    }
+}
+
+/// Untyped request of an [ExtnRpcContract] exchanged between extensions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtnRpcRequest {
+    pub contract: String,
+    pub params: Value,
+}
+
+impl ExtnPayloadProvider for ExtnRpcRequest {
+    fn get_extn_payload(&self) -> ExtnPayload {
+        ExtnPayload::Request(ExtnRequest::ExtnRpc(self.clone()))
+    }
+
+    fn get_from_payload(payload: ExtnPayload) -> Option<Self> {
+        if let ExtnPayload::Request(ExtnRequest::ExtnRpc(r)) = payload {
+            return Some(r);
+        }
+
+        None
+    }
+
+    fn contract() -> RippleContract {
+        // Replaced by the contract of the request
+        RippleContract::ExtnRpc(ExtnRpcAdjective::default())
+    }
+
+    fn get_contract(&self) -> RippleContract {
+        RippleContract::ExtnRpc(ExtnRpcAdjective {
+            name: self.contract.clone(),
+        })
+    }
+}
+
+/// Typed client of an [ExtnRpcContract] used by the extensions calling the contract
+#[derive(Debug, Clone)]
+pub struct ExtnRpcClient<C: ExtnRpcContract> {
+    client: ExtnClient,
+    contract: PhantomData<C>,
+}
+
+impl<C: ExtnRpcContract> ExtnRpcClient<C> {
+    pub fn new(client: ExtnClient) -> ExtnRpcClient<C> {
+        ExtnRpcClient {
+            client,
+            contract: PhantomData,
+        }
+    }
+
+    pub async fn call(&mut self, request: C::Request) -> Result<C::Response, ExtnRpcError> {
+        let request = self.get_request(request)?;
+        let message = self.client.request(request).await?;
+        Self::get_response(message)
+    }
+
+    /// Same as [ExtnRpcClient::call], the response is no longer awaited once the timeout elapses
+    pub async fn call_with_timeout(
+        &mut self,
+        request: C::Request,
+        timeout_in_msecs: u64,
+    ) -> Result<C::Response, ExtnRpcError> {
+        let request = self.get_request(request)?;
+        let message = self
+            .client
+            .request_message_with_timeout(request, timeout_in_msecs)
+            .await?;
+        Self::get_response(message)
+    }
+
+    fn get_request(&self, request: C::Request) -> Result<ExtnRpcRequest, ExtnRpcError> {
+        let contract = C::contract();
+        if !self.client.check_contract_permitted(contract.clone()) {
+            return Err(ExtnRpcError::PermissionDenied(contract.as_clear_string()));
+        }
+        let params = serde_json::to_value(&request)
+            .map_err(|e| ExtnRpcError::Serialization(e.to_string()))?;
+        Ok(ExtnRpcRequest {
+            contract: C::name().to_owned(),
+            params,
+        })
+    }
+
+    fn get_response(message: ExtnMessage) -> Result<C::Response, ExtnRpcError> {
+        match message.payload.extract() {
+            Some(ExtnResponse::Value(value)) => serde_json::from_value(value)
+                .map_err(|e| ExtnRpcError::Serialization(e.to_string())),
+            Some(ExtnResponse::Error(RippleError::InvalidExtnRpcRequest(e))) => {
+                Err(ExtnRpcError::Serialization(e))
+            }
+            Some(ExtnResponse::Error(e)) => Err(ExtnRpcError::Ripple(e)),
+            _ => Err(ExtnRpcError::Ripple(RippleError::InvalidOutput)),
+        }
+    }
+}
+
+/// Implemented by the extension fulfilling an [ExtnRpcContract]
+#[async_trait]
+pub trait ExtnRpcHandler<C: ExtnRpcContract>: Send + Sync + 'static {
+    async fn handle(&self, request: C::Request) -> Result<C::Response, RippleError>;
+}
+
+pub struct ExtnRpcState<C: ExtnRpcContract> {
+    client: ExtnClient,
+    handler: Arc<dyn ExtnRpcHandler<C>>,
+}
+
+impl<C: ExtnRpcContract> Clone for ExtnRpcState<C> {
+    fn clone(&self) -> Self {
+        ExtnRpcState {
+            client: self.client.clone(),
+            handler: self.handler.clone(),
+        }
+    }
+}
+
+/// Request processor decoding the requests of an [ExtnRpcContract] for its [ExtnRpcHandler]
+pub struct ExtnRpcProcessor<C: ExtnRpcContract> {
+    state: ExtnRpcState<C>,
+    streamer: DefaultExtnStreamer,
+}
+
+impl<C: ExtnRpcContract> ExtnRpcProcessor<C> {
+    pub fn new(client: ExtnClient, handler: impl ExtnRpcHandler<C>) -> ExtnRpcProcessor<C> {
+        ExtnRpcProcessor {
+            state: ExtnRpcState {
+                client,
+                handler: Arc::new(handler),
+            },
+            streamer: DefaultExtnStreamer::new(),
+        }
+    }
+}
+
+impl<C: ExtnRpcContract> ExtnStreamProcessor for ExtnRpcProcessor<C> {
+    type STATE = ExtnRpcState<C>;
+    type VALUE = ExtnRpcRequest;
+
+    fn get_state(&self) -> Self::STATE {
+        self.state.clone()
+    }
+
+    fn sender(&self) -> MSender<ExtnMessage> {
+        self.streamer.sender()
+    }
+
+    fn receiver(&mut self) -> MReceiver<ExtnMessage> {
+        self.streamer.receiver()
+    }
+
+    fn contract(&self) -> RippleContract {
+        C::contract()
+    }
+}
+
+#[async_trait]
+impl<C: ExtnRpcContract> ExtnRequestProcessor for ExtnRpcProcessor<C> {
+    fn get_client(&self) -> ExtnClient {
+        self.state.client.clone()
+    }
+
+    async fn process_request(
+        state: Self::STATE,
+        msg: ExtnMessage,
+        extracted_message: Self::VALUE,
+    ) -> bool {
+        if extracted_message.contract != C::name() {
+            return Self::handle_error(state.client, msg, RippleError::InvalidInput).await;
+        }
+        let request = match serde_json::from_value::<C::Request>(extracted_message.params) {
+            Ok(request) => request,
+            Err(e) => {
+                error!("Invalid request for {} {:?}", C::name(), e);
+                let error = RippleError::InvalidExtnRpcRequest(format!(
+                    "{} rejected the request {}",
+                    C::contract().as_clear_string(),
+                    e
+                ));
+                return Self::handle_error(state.client, msg, error).await;
+            }
+        };
+        let response = match state.handler.handle(request).await {
+            Ok(response) => match serde_json::to_value(response) {
+                Ok(value) => ExtnResponse::Value(value),
+                Err(e) => {
+                    error!("Invalid response for {} {:?}", C::name(), e);
+                    ExtnResponse::Error(RippleError::InvalidOutput)
+                }
+            },
+            Err(e) => ExtnResponse::Error(e),
+        };
+        Self::respond(state.client, msg, response).await.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::manifest::extn_manifest::ExtnSymbol,
+        extn::{
+            client::extn_sender::{tests::Mockable, ExtnSender},
+            extn_id::{ExtnClassId, ExtnId},
+        },
+    };
+    use std::collections::HashMap;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct EchoRequest {
+        pub message: String,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    pub struct EchoResponse {
+        pub message: String,
+    }
+
+    extn_rpc_contract!(EchoContract, "echo", EchoRequest, EchoResponse);
+    // same contract with a request the provider does not understand
+    extn_rpc_contract!(BrokenEchoContract, "echo", u32, EchoResponse);
+
+    struct EchoHandler;
+
+    #[async_trait]
+    impl ExtnRpcHandler<EchoContract> for EchoHandler {
+        async fn handle(&self, request: EchoRequest) -> Result<EchoResponse, RippleError> {
+            if request.message.is_empty() {
+                return Err(RippleError::InvalidInput);
+            }
+            if request.message == "unparsable" {
+                return Err(RippleError::ParseError);
+            }
+            Ok(EchoResponse {
+                message: request.message,
+            })
+        }
+    }
+
+    #[test]
+    fn test_contract() {
+        let contract = EchoContract::contract();
+        assert_eq!(contract.as_clear_string(), "echo.extn_rpc");
+        assert_eq!(
+            RippleContract::from_manifest("echo.extn_rpc"),
+            Some(contract)
+        );
+        assert!(is_valid_contract_name("device_name"));
+        assert!(!is_valid_contract_name("device.name"));
+        assert!(!is_valid_contract_name(""));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_call() {
+        let (main_sender, main_rx) = ExtnSender::mock();
+        let main_client = ExtnClient::new(main_rx, main_sender.clone());
+        let provider_id = ExtnId::new_channel(ExtnClassId::Device, "echo".into());
+        let fulfills = vec!["echo.extn_rpc".to_owned()];
+        let (provider_sender, provider_tx, provider_rx) = ExtnSender::mock_extn(
+            provider_id.clone(),
+            Vec::new(),
+            fulfills.clone(),
+            Some(HashMap::new()),
+            main_sender.tx.clone(),
+        );
+        let mut provider_client = ExtnClient::new(provider_rx, provider_sender);
+        provider_client
+            .add_request_processor(ExtnRpcProcessor::new(provider_client.clone(), EchoHandler));
+        main_client.clone().add_sender(
+            provider_id.clone(),
+            ExtnSymbol {
+                id: provider_id.to_string(),
+                uses: Vec::new(),
+                fulfills,
+                config: None,
+            },
+            provider_tx,
+        );
+        let main_client_for_thread = main_client.clone();
+        tokio::spawn(async move { main_client_for_thread.initialize().await });
+        tokio::spawn(async move { provider_client.initialize().await });
+
+        let mut client = ExtnRpcClient::<EchoContract>::new(main_client.clone());
+        let response = client
+            .call_with_timeout(
+                EchoRequest {
+                    message: "hello".into(),
+                },
+                1000,
+            )
+            .await;
+        assert_eq!(
+            response,
+            Ok(EchoResponse {
+                message: "hello".into()
+            })
+        );
+
+        let response = client
+            .call_with_timeout(EchoRequest { message: "".into() }, 1000)
+            .await;
+        assert_eq!(
+            response,
+            Err(ExtnRpcError::Ripple(RippleError::InvalidInput))
+        );
+
+        // errors of the handler are not mistaken for requests the provider could not decode
+        let response = client
+            .call_with_timeout(
+                EchoRequest {
+                    message: "unparsable".into(),
+                },
+                1000,
+            )
+            .await;
+        assert_eq!(response, Err(ExtnRpcError::Ripple(RippleError::ParseError)));
+
+        let response = ExtnRpcClient::<BrokenEchoContract>::new(main_client)
+            .call_with_timeout(1, 1000)
+            .await;
+        assert!(matches!(response, Err(ExtnRpcError::Serialization(_))));
+    }
+
+    #[tokio::test]
+    async fn test_call_not_permitted() {
+        let (main_sender, _main_rx) = ExtnSender::mock();
+        let (caller_sender, _caller_tx, caller_rx) = ExtnSender::mock_extn(
+            ExtnId::new_channel(ExtnClassId::Distributor, "caller".into()),
+            Vec::new(),
+            Vec::new(),
+            None,
+            main_sender.tx.clone(),
+        );
+        let mut client =
+            ExtnRpcClient::<EchoContract>::new(ExtnClient::new(caller_rx, caller_sender));
+        let response = client
+            .call(EchoRequest {
+                message: "hello".into(),
+            })
+            .await;
+        assert_eq!(
+            response,
+            Err(ExtnRpcError::PermissionDenied("echo.extn_rpc".into()))
+        );
+    }
+}
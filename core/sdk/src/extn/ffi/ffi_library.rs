@@ -20,10 +20,21 @@ use crate::{
 };
 use libloading::{Library, Symbol};
 use log::{debug, error, info};
-use semver::Version;
+use semver::{Comparator, Op, Version};
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
+/// Version of the `ripple_sdk` crate the running Ripple was built with.
+pub const SDK_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// First `ripple_sdk` whose extensions answer the health checks of Ripple, older extensions
+/// drop them as invalid messages.
+pub const HEALTH_CHECK_SDK_VERSION: Version = Version::new(1, 2, 0);
+
+pub fn get_sdk_version() -> Version {
+    Version::parse(SDK_VERSION).unwrap()
+}
+
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct ExtnMetadata {
@@ -36,6 +47,10 @@ pub struct CExtnSymbolMetadata {
     fulfills: String,
     id: String,
     required_version: String,
+    /// Version of the `ripple_sdk` the library was compiled against, missing for libraries built
+    /// before it was exported
+    #[serde(default)]
+    sdk_version: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +60,25 @@ pub struct ExtnSymbolMetadata {
     pub required_version: Version,
 }
 
+/// Raised when an extension symbol requires a `ripple_sdk` which is not semver compatible with
+/// the running Ripple.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtnVersionMismatch {
+    pub id: ExtnId,
+    pub required_version: Version,
+    pub sdk_version: Version,
+}
+
+impl std::fmt::Display for ExtnVersionMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} requires ripple_sdk ^{} but Ripple is running ripple_sdk {}, rebuild the extension against ripple_sdk {}",
+            self.id, self.required_version, self.sdk_version, self.sdk_version
+        )
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct CExtnMetadata {
@@ -61,12 +95,21 @@ impl TryInto<ExtnMetadata> for Box<CExtnMetadata> {
             for c_entry in cap_entries {
                 if let Ok(id) = ExtnId::try_from(c_entry.id) {
                     if let Ok(fulfills) = ContractFulfiller::try_from(c_entry.fulfills) {
-                        if let Ok(required_version) = Version::from_str(&c_entry.required_version) {
-                            metadata.push(ExtnSymbolMetadata {
+                        // the sdk the library was actually built with wins over the declared one
+                        let required_version = c_entry
+                            .sdk_version
+                            .as_deref()
+                            .unwrap_or(&c_entry.required_version);
+                        match Version::from_str(required_version) {
+                            Ok(required_version) => metadata.push(ExtnSymbolMetadata {
                                 id,
                                 fulfills,
                                 required_version,
-                            })
+                            }),
+                            Err(e) => error!(
+                                "{} has an invalid required_version {}: {:?}",
+                                id, required_version, e
+                            ),
                         }
                     }
                 }
@@ -89,6 +132,8 @@ impl From<ExtnMetadata> for CExtnMetadata {
                 id: data.id.to_string(),
                 fulfills: data.clone().fulfills.into(),
                 required_version: data.get_version().to_string(),
+                // evaluated in the library, so it is the sdk the library is compiled against
+                sdk_version: Some(SDK_VERSION.to_owned()),
             });
         }
         let symbols = serde_json::to_string(&metadata).unwrap();
@@ -101,6 +146,18 @@ impl From<ExtnMetadata> for CExtnMetadata {
 }
 
 impl ExtnSymbolMetadata {
+    /// Metadata of a symbol requiring the `ripple_sdk` the library is compiled against
+    pub fn new(id: ExtnId, fulfills: ContractFulfiller) -> ExtnSymbolMetadata {
+        ExtnSymbolMetadata {
+            id,
+            fulfills,
+            required_version: get_sdk_version(),
+        }
+    }
+
+    /// Metadata of a symbol with a declared `required_version`. Libraries export the version of
+    /// the `ripple_sdk` they are compiled against along, which Ripple checks instead, so
+    /// [ExtnSymbolMetadata::new] is preferred.
     pub fn get(
         id: ExtnId,
         fulfills: ContractFulfiller,
@@ -120,6 +177,33 @@ impl ExtnSymbolMetadata {
     pub fn get_version(&self) -> Version {
         self.required_version.clone()
     }
+
+    /// Returns true when the symbol is built against a `ripple_sdk` answering health checks
+    pub fn supports_health_check(&self) -> bool {
+        self.required_version >= HEALTH_CHECK_SDK_VERSION
+    }
+
+    /// Checks the `ripple_sdk` version required by the symbol is semver compatible with the given
+    /// sdk version, which follows the caret rules of Cargo so `1.1.0` accepts `1.x` from `1.1.0`
+    /// and `0.2.0` only accepts `0.2.x`.
+    pub fn check_sdk_version(&self, sdk_version: &Version) -> Result<(), ExtnVersionMismatch> {
+        let comparator = Comparator {
+            op: Op::Caret,
+            major: self.required_version.major,
+            minor: Some(self.required_version.minor),
+            patch: Some(self.required_version.patch),
+            pre: self.required_version.pre.clone(),
+        };
+        if comparator.matches(sdk_version) {
+            Ok(())
+        } else {
+            Err(ExtnVersionMismatch {
+                id: self.id.clone(),
+                required_version: self.required_version.clone(),
+                sdk_version: sdk_version.clone(),
+            })
+        }
+    }
 }
 
 /// Macro to assist extensions define their metadata. Each Extension library will contain one metadata symbol
@@ -133,14 +217,12 @@ impl ExtnSymbolMetadata {
 /// use ripple_sdk::extn::ffi::ffi_library::ExtnSymbolMetadata;
 /// use ripple_sdk::extn::extn_id::{ExtnClassId,ExtnId};
 /// use ripple_sdk::framework::ripple_contract::{RippleContract, ContractFulfiller};
-/// use semver::Version;
 /// use ripple_sdk::extn::ffi::ffi_library::ExtnMetadata;
 /// fn init_library() -> CExtnMetadata {
 /// let _ = init_logger("device_channel".into());
-/// let thunder_channel_meta = ExtnSymbolMetadata::get(
+/// let thunder_channel_meta = ExtnSymbolMetadata::new(
 ///     ExtnId::new_channel(ExtnClassId::Device, "device_interface".into()),
 ///     ContractFulfiller::new(vec![RippleContract::DeviceInfo]),
-///     Version::new(1, 1, 0),
 /// );
 
 /// let extn_metadata = ExtnMetadata {
@@ -243,10 +325,8 @@ mod tests {
             .contracts
             .contains(&RippleContract::Launcher));
 
-        // Assert required_version
-        assert_eq!(symbol.required_version.major, 1);
-        assert_eq!(symbol.required_version.minor, 1);
-        assert_eq!(symbol.required_version.patch, 0);
+        // the exported sdk version wins over the declared required_version
+        assert_eq!(symbol.required_version, get_sdk_version());
     }
 
     // Test for From<ExtnMetadata> implementation
@@ -274,6 +354,76 @@ mod tests {
         assert_eq!(required_version.patch, 0);
     }
 
+    #[test]
+    fn test_exported_sdk_version() {
+        // a stale declared version is replaced by the sdk the library is compiled against
+        let symbol = ExtnSymbolMetadata::get(
+            ExtnId::new_channel(ExtnClassId::Launcher, "internal".into()),
+            ContractFulfiller::new(vec![RippleContract::Launcher]),
+            Version::new(0, 1, 0),
+        );
+        let c_metadata: CExtnMetadata = ExtnMetadata {
+            name: "launcher".into(),
+            symbols: vec![symbol],
+        }
+        .into();
+        let metadata: ExtnMetadata = Box::new(c_metadata).try_into().unwrap();
+        assert_eq!(metadata.symbols[0].required_version, get_sdk_version());
+
+        // libraries built before the sdk version was exported keep their declared version
+        let c_metadata = CExtnMetadata {
+            name: "launcher".into(),
+            metadata: r#"[{"fulfills":"[\"\\\"launcher\\\"\"]","id":"ripple:channel:launcher:internal","required_version":"0.1.0"}]"#.into(),
+        };
+        let metadata: ExtnMetadata = Box::new(c_metadata).try_into().unwrap();
+        assert_eq!(metadata.symbols[0].required_version, Version::new(0, 1, 0));
+
+        let symbol = ExtnSymbolMetadata::new(
+            ExtnId::new_channel(ExtnClassId::Launcher, "internal".into()),
+            ContractFulfiller::new(vec![RippleContract::Launcher]),
+        );
+        assert_eq!(symbol.get_version(), get_sdk_version());
+    }
+
+    #[test]
+    fn test_check_sdk_version() {
+        let symbol = |required_version| {
+            ExtnSymbolMetadata::get(
+                ExtnId::new_channel(ExtnClassId::Launcher, "internal".into()),
+                ContractFulfiller::new(vec![RippleContract::Launcher]),
+                required_version,
+            )
+        };
+        let sdk_version = Version::new(1, 2, 3);
+        assert!(symbol(Version::new(1, 1, 0))
+            .check_sdk_version(&sdk_version)
+            .is_ok());
+        assert!(symbol(Version::new(1, 2, 3))
+            .check_sdk_version(&sdk_version)
+            .is_ok());
+        assert!(symbol(Version::new(1, 3, 0))
+            .check_sdk_version(&sdk_version)
+            .is_err());
+        assert!(symbol(Version::new(0, 9, 0))
+            .check_sdk_version(&sdk_version)
+            .is_err());
+        let mismatch = symbol(Version::new(2, 0, 0))
+            .check_sdk_version(&sdk_version)
+            .unwrap_err();
+        assert_eq!(mismatch.required_version, Version::new(2, 0, 0));
+        assert!(mismatch.to_string().contains("ripple_sdk 1.2.3"));
+
+        assert!(symbol(Version::new(0, 2, 0))
+            .check_sdk_version(&Version::new(0, 2, 5))
+            .is_ok());
+        assert!(symbol(Version::new(0, 2, 0))
+            .check_sdk_version(&Version::new(0, 3, 0))
+            .is_err());
+        assert!(symbol(get_sdk_version())
+            .check_sdk_version(&get_sdk_version())
+            .is_ok());
+    }
+
     // Test for ExtnSymbolMetadata methods
     #[test]
     fn test_extn_symbol_metadata_methods() {
@@ -308,6 +458,12 @@ mod tests {
             extn_symbol_metadata.get_version(),
             Version::parse("1.1.0").unwrap()
         );
+        assert!(!extn_symbol_metadata.supports_health_check());
+        assert!(ExtnSymbolMetadata::new(
+            ExtnId::new_channel(ExtnClassId::Launcher, "internal".into()),
+            ContractFulfiller::new(vec![RippleContract::Launcher]),
+        )
+        .supports_health_check());
     }
 
     // Test for export_extn_metadata! macro
@@ -0,0 +1,260 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_channel::{Receiver as CReceiver, Sender as CSender};
+use log::{debug, error, info, warn};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines},
+    net::UnixStream,
+};
+
+use crate::{
+    extn::ffi::{ffi_channel::ExtnChannelBuilder, ffi_message::CExtnMessage},
+    framework::RippleResponse,
+    utils::error::RippleError,
+};
+
+use super::ipc_message::{ExtnIpcFrame, EXTN_IPC_SOCKET_ENV};
+
+/// Time the callback of an outgoing message is kept for a message coming back with its id
+const EXTN_IPC_CALLBACK_TTL: Duration = Duration::from_secs(60);
+
+type Callbacks = HashMap<String, (CSender<CExtnMessage>, Instant)>;
+
+/// Removes the callbacks of messages which never got anything back
+fn prune_callbacks(callbacks: &mut Callbacks, now: Instant) {
+    callbacks.retain(|_, (_, ts)| now.duration_since(*ts) < EXTN_IPC_CALLBACK_TTL);
+}
+
+/// Forwards the messages of `outgoing` to the other process and the messages of the other
+/// process to `incoming` until either side closes. Writing happens in its own task, so the
+/// messages of the other process keep being read while a large message is written.
+///
+/// Callbacks do not cross processes, the callback of an outgoing message is kept instead and
+/// receives the message which comes back with the same id, usually the response. Callbacks of
+/// messages without an answer are dropped after [EXTN_IPC_CALLBACK_TTL].
+pub async fn bridge<R, W>(
+    lines: Lines<R>,
+    writer: W,
+    outgoing: CReceiver<CExtnMessage>,
+    incoming: CSender<CExtnMessage>,
+) where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let callbacks = Arc::new(Mutex::new(Callbacks::new()));
+    let mut write_task = tokio::spawn(write_messages(writer, outgoing, callbacks.clone()));
+    tokio::select! {
+        _ = read_messages(lines, incoming, callbacks) => {}
+        _ = &mut write_task => {}
+    }
+    write_task.abort();
+    debug!("Extension bridge closed");
+}
+
+async fn write_messages<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    outgoing: CReceiver<CExtnMessage>,
+    callbacks: Arc<Mutex<Callbacks>>,
+) {
+    while let Ok(mut message) = outgoing.recv().await {
+        if let Some(callback) = message.callback.take() {
+            let now = Instant::now();
+            let mut callbacks = callbacks.lock().unwrap();
+            prune_callbacks(&mut callbacks, now);
+            callbacks.insert(message.id.clone(), (callback, now));
+        }
+        let line = ExtnIpcFrame::Message(message.into()).to_line();
+        if let Err(e) = writer.write_all(line.as_bytes()).await {
+            error!("Unable to write to the extension socket {:?}", e);
+            break;
+        }
+    }
+}
+
+async fn read_messages<R: AsyncBufRead + Unpin>(
+    mut lines: Lines<R>,
+    incoming: CSender<CExtnMessage>,
+    callbacks: Arc<Mutex<Callbacks>>,
+) {
+    while let Ok(Some(line)) = lines.next_line().await {
+        match ExtnIpcFrame::from_line(&line) {
+            Ok(ExtnIpcFrame::Message(message)) => {
+                let message: CExtnMessage = message.into();
+                let callback = callbacks.lock().unwrap().remove(&message.id);
+                if let Some((callback, _)) = callback {
+                    if let Err(e) = callback.send(message).await {
+                        error!("Unable to send to the callback {:?}", e);
+                    }
+                } else if incoming.send(message).await.is_err() {
+                    break;
+                }
+            }
+            Ok(frame) => warn!("Unexpected frame {:?}", frame),
+            Err(_) => error!("Invalid frame {}", line),
+        }
+    }
+}
+
+/// Hosts a channel of an extension in the current process. Connects to the socket given by
+/// Ripple in [EXTN_IPC_SOCKET_ENV], builds and starts the channel of the symbol Ripple sends in
+/// its hello and forwards its messages until the connection closes.
+pub async fn host_channel(builder: &ExtnChannelBuilder) -> RippleResponse {
+    let path = std::env::var(EXTN_IPC_SOCKET_ENV).map_err(|_| RippleError::MissingInput)?;
+    let stream = UnixStream::connect(&path).await.map_err(|e| {
+        error!("Unable to connect to {} {:?}", path, e);
+        RippleError::ExtnError
+    })?;
+    let (reader, writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let hello = match lines.next_line().await {
+        Ok(Some(line)) => match ExtnIpcFrame::from_line(&line)? {
+            ExtnIpcFrame::Hello(hello) => hello,
+            frame => {
+                error!("Expected a hello, received {:?}", frame);
+                return Err(RippleError::InvalidInput);
+            }
+        },
+        _ => return Err(RippleError::ExtnError),
+    };
+
+    let (main_tx, main_rx) = async_channel::unbounded();
+    let (extn_tx, extn_rx) = async_channel::unbounded();
+    let sender = hello.get_sender(main_tx)?;
+    let channel = (builder.build)(hello.id.clone())?;
+    info!("Starting {} channel", hello.id);
+    std::thread::spawn(move || {
+        (channel.start)(sender, extn_rx);
+    });
+    bridge(lines, writer, main_rx, extn_tx).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extn::ipc::ipc_message::ExtnIpcMessage;
+
+    fn message(id: &str) -> CExtnMessage {
+        CExtnMessage {
+            id: id.to_owned(),
+            requestor: "ripple:channel:device:info".to_owned(),
+            target: "\"device_info\"".to_owned(),
+            target_id: "".to_owned(),
+            payload: "{}".to_owned(),
+            callback: None,
+            ts: 0,
+        }
+    }
+
+    #[test]
+    fn test_prune_callbacks() {
+        let (tx, _rx) = async_channel::unbounded();
+        let now = Instant::now();
+        let mut callbacks = Callbacks::new();
+        callbacks.insert("old".to_owned(), (tx.clone(), now));
+        callbacks.insert(
+            "new".to_owned(),
+            (tx, now + EXTN_IPC_CALLBACK_TTL - Duration::from_secs(1)),
+        );
+        prune_callbacks(&mut callbacks, now + EXTN_IPC_CALLBACK_TTL);
+        assert!(!callbacks.contains_key("old"));
+        assert!(callbacks.contains_key("new"));
+    }
+
+    #[tokio::test]
+    async fn test_bridge() {
+        let (local, remote) = UnixStream::pair().unwrap();
+        let (reader, writer) = local.into_split();
+        let (outgoing_tx, outgoing_rx) = async_channel::unbounded();
+        let (incoming_tx, incoming_rx) = async_channel::unbounded();
+        tokio::spawn(bridge(
+            BufReader::new(reader).lines(),
+            writer,
+            outgoing_rx,
+            incoming_tx,
+        ));
+
+        // outgoing messages are written without their callback
+        let (callback_tx, callback_rx) = async_channel::unbounded();
+        let mut request = message("1");
+        request.callback = Some(callback_tx);
+        outgoing_tx.send(request).await.unwrap();
+        let (remote_reader, mut remote_writer) = remote.into_split();
+        let mut remote_lines = BufReader::new(remote_reader).lines();
+        let line = remote_lines.next_line().await.unwrap().unwrap();
+        assert_eq!(
+            ExtnIpcFrame::from_line(&line).unwrap(),
+            ExtnIpcFrame::Message(ExtnIpcMessage::from(message("1")))
+        );
+
+        // the response goes to the callback, other messages to the incoming channel
+        for id in ["2", "1"] {
+            let line = ExtnIpcFrame::Message(message(id).into()).to_line();
+            remote_writer.write_all(line.as_bytes()).await.unwrap();
+        }
+        assert_eq!(incoming_rx.recv().await.unwrap().id, "2");
+        assert_eq!(callback_rx.recv().await.unwrap().id, "1");
+
+        // the bridge stops once the other process disconnects
+        drop(remote_writer);
+        drop(remote_lines);
+        assert!(incoming_rx.recv().await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_bridge_large_messages() {
+        // both processes send messages larger than the socket buffers at the same time
+        let (local, remote) = UnixStream::pair().unwrap();
+        let mut ends = Vec::new();
+        for stream in [local, remote] {
+            let (reader, writer) = stream.into_split();
+            let (outgoing_tx, outgoing_rx) = async_channel::unbounded();
+            let (incoming_tx, incoming_rx) = async_channel::unbounded();
+            tokio::spawn(bridge(
+                BufReader::new(reader).lines(),
+                writer,
+                outgoing_rx,
+                incoming_tx,
+            ));
+            ends.push((outgoing_tx, incoming_rx));
+        }
+        let payload = serde_json::to_string(&"x".repeat(1024 * 1024)).unwrap();
+        for (outgoing_tx, _) in &ends {
+            for i in 0..4 {
+                let mut large = message(&i.to_string());
+                large.payload = payload.clone();
+                outgoing_tx.send(large).await.unwrap();
+            }
+        }
+        for (_, incoming_rx) in &ends {
+            for _ in 0..4 {
+                let received = tokio::time::timeout(Duration::from_secs(5), incoming_rx.recv())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(received.payload.len(), payload.len());
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2023 Comcast Cable Communications Management, LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::manifest::extn_manifest::ExtnSymbol,
+    extn::{client::extn_sender::ExtnSender, extn_id::ExtnId, ffi::ffi_message::CExtnMessage},
+    utils::error::RippleError,
+};
+use async_channel::Sender as CSender;
+
+/// Version of the frames exchanged with extensions hosted in another process, bumped on
+/// incompatible changes.
+pub const EXTN_IPC_VERSION: u32 = 1;
+
+/// Environment variable with the path of the Unix socket a hosted extension connects to
+pub const EXTN_IPC_SOCKET_ENV: &str = "RIPPLE_EXTN_SOCKET";
+
+/// Frames exchanged between Ripple and an extension hosted in another process, one JSON object
+/// per line. Ripple sends [ExtnIpcHello] once the extension connects, messages flow both ways
+/// afterwards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExtnIpcFrame {
+    Hello(ExtnIpcHello),
+    Message(ExtnIpcMessage),
+}
+
+impl ExtnIpcFrame {
+    pub fn to_line(&self) -> String {
+        let mut line = serde_json::to_string(self).unwrap_or_default();
+        line.push('\n');
+        line
+    }
+
+    pub fn from_line(line: &str) -> Result<Self, RippleError> {
+        serde_json::from_str(line.trim_end()).map_err(|_| RippleError::ParseError)
+    }
+}
+
+/// Symbol of the channel the hosted extension has to start
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtnIpcHello {
+    pub version: u32,
+    pub id: String,
+    pub uses: Vec<String>,
+    pub fulfills: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<HashMap<String, String>>,
+}
+
+impl From<ExtnSymbol> for ExtnIpcHello {
+    fn from(symbol: ExtnSymbol) -> Self {
+        ExtnIpcHello {
+            version: EXTN_IPC_VERSION,
+            id: symbol.id,
+            uses: symbol.uses,
+            fulfills: symbol.fulfills,
+            config: symbol.config,
+        }
+    }
+}
+
+impl ExtnIpcHello {
+    /// Builds the sender of the hosted extension, messages sent on `tx` are forwarded to Ripple
+    pub fn get_sender(&self, tx: CSender<CExtnMessage>) -> Result<ExtnSender, RippleError> {
+        if self.version != EXTN_IPC_VERSION {
+            return Err(RippleError::InvalidInput);
+        }
+        let id = ExtnId::try_from(self.id.clone())?;
+        Ok(ExtnSender::new(
+            tx,
+            id,
+            self.uses.clone(),
+            self.fulfills.clone(),
+            self.config.clone(),
+        ))
+    }
+}
+
+/// [CExtnMessage] without its callback, which only exists within a process. The payload and
+/// target keep the serialization of [CExtnMessage].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtnIpcMessage {
+    pub id: String,
+    pub requestor: String,
+    pub target: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub target_id: String,
+    pub payload: String,
+    pub ts: i64,
+}
+
+impl From<CExtnMessage> for ExtnIpcMessage {
+    fn from(value: CExtnMessage) -> Self {
+        ExtnIpcMessage {
+            id: value.id,
+            requestor: value.requestor,
+            target: value.target,
+            target_id: value.target_id,
+            payload: value.payload,
+            ts: value.ts,
+        }
+    }
+}
+
+impl From<ExtnIpcMessage> for CExtnMessage {
+    fn from(value: ExtnIpcMessage) -> Self {
+        CExtnMessage {
+            id: value.id,
+            requestor: value.requestor,
+            target: value.target,
+            target_id: value.target_id,
+            payload: value.payload,
+            callback: None,
+            ts: value.ts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::config::Config,
+        extn::{
+            extn_client_message::{ExtnMessage, ExtnPayload, ExtnRequest},
+            extn_id::ExtnClassId,
+        },
+        framework::ripple_contract::RippleContract,
+    };
+
+    #[test]
+    fn test_message_frame() {
+        let (tx, _rx) = async_channel::unbounded();
+        let message: CExtnMessage = ExtnMessage {
+            id: "test_id".to_owned(),
+            requestor: ExtnId::new_channel(ExtnClassId::Device, "info".to_owned()),
+            target: RippleContract::Config,
+            target_id: None,
+            payload: ExtnPayload::Request(ExtnRequest::Config(Config::DefaultName)),
+            callback: Some(tx),
+            ts: Some(1234567890),
+        }
+        .into();
+
+        let frame = ExtnIpcFrame::Message(message.clone().into());
+        let line = frame.to_line();
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+
+        let ExtnIpcFrame::Message(received) = ExtnIpcFrame::from_line(&line).unwrap() else {
+            panic!("expected a message frame");
+        };
+        let received: CExtnMessage = received.into();
+        assert!(received.callback.is_none());
+        let received: ExtnMessage = received.try_into().unwrap();
+        let sent: ExtnMessage = message.try_into().unwrap();
+        assert_eq!(received.id, sent.id);
+        assert_eq!(received.requestor, sent.requestor);
+        assert_eq!(received.target, sent.target);
+        assert_eq!(received.payload, sent.payload);
+        assert_eq!(received.ts, sent.ts);
+    }
+
+    #[test]
+    fn test_hello_frame() {
+        let id = ExtnId::new_channel(ExtnClassId::Device, "info".to_owned());
+        let hello: ExtnIpcHello = ExtnSymbol {
+            id: id.to_string(),
+            uses: vec!["config".to_owned()],
+            fulfills: vec!["device_info".to_owned()],
+            config: None,
+        }
+        .into();
+        let frame = ExtnIpcFrame::from_line(&ExtnIpcFrame::Hello(hello.clone()).to_line()).unwrap();
+        assert_eq!(frame, ExtnIpcFrame::Hello(hello.clone()));
+
+        let (tx, _rx) = async_channel::unbounded();
+        let sender = hello.get_sender(tx.clone()).unwrap();
+        assert_eq!(sender.id, id);
+        assert_eq!(sender.fulfills, vec!["device_info".to_owned()]);
+
+        let newer = ExtnIpcHello {
+            version: EXTN_IPC_VERSION + 1,
+            ..hello
+        };
+        assert!(newer.get_sender(tx).is_err());
+    }
+}
@@ -18,7 +18,9 @@
 pub mod client;
 pub mod extn_client_message;
 pub mod extn_id;
+pub mod extn_rpc;
 pub mod ffi;
+pub mod ipc;
 
 #[cfg(feature = "tdk")]
 pub mod mock_extension_client;
@@ -20,7 +20,7 @@ use crate::{
         session::{EventAdjective, SessionAdjective},
         storage_property::StorageAdjective,
     },
-    extn::extn_id::ExtnProviderAdjective,
+    extn::{extn_id::ExtnProviderAdjective, extn_rpc::ExtnRpcAdjective},
     utils::{error::RippleError, serde_utils::SerdeClearString},
 };
 use jsonrpsee::core::DeserializeOwned;
@@ -122,6 +122,8 @@ pub enum RippleContract {
     // Runtime ability for a given distributor to turn off a certian feature
     RemoteFeatureControl,
     Analytics,
+    /// Typed contract between extensions, see [crate::extn::extn_rpc::ExtnRpcContract]
+    ExtnRpc(ExtnRpcAdjective),
 }
 
 pub trait ContractAdjective: serde::ser::Serialize + DeserializeOwned {
@@ -181,6 +183,7 @@ impl RippleContract {
             Self::Session(adj) => Some(adj.as_string()),
             Self::DeviceEvents(adj) => Some(adj.as_string()),
             Self::ExtnProvider(adj) => Some(adj.id.to_string()),
+            Self::ExtnRpc(adj) => Some(adj.as_string()),
             _ => None,
         }
     }
@@ -201,6 +204,7 @@ impl RippleContract {
             "extn_provider" => {
                 return Self::get_contract_from_adjective::<ExtnProviderAdjective>(&adjective)
             }
+            "extn_rpc" => return Self::get_contract_from_adjective::<ExtnRpcAdjective>(&adjective),
             "storage" => match serde_json::from_str::<StorageAdjective>(&adjective) {
                 Ok(v) => return Some(v.get_contract()),
                 Err(e) => error!("contract parser_error={:?}", e),
@@ -223,6 +227,7 @@ impl RippleContract {
             Self::Storage(_) => Some("storage".to_owned()),
             Self::Session(_) => Some("session".to_owned()),
             Self::ExtnProvider(_) => Some("extn_provider".to_owned()),
+            Self::ExtnRpc(_) => Some("extn_rpc".to_owned()),
             Self::DeviceEvents(_) => Some("device_events".to_owned()),
             _ => None,
         }
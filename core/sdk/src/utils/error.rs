@@ -41,6 +41,10 @@ pub enum RippleError {
     ServiceNotReady,
     BrokerError(String),
     TimeoutError,
+    /// Every extension fulfilling the contract stopped answering health checks
+    ExtnUnhealthy,
+    /// The provider of an extension rpc contract could not decode the request
+    InvalidExtnRpcRequest(String),
 }
 
 impl std::fmt::Display for RippleError {
@@ -69,6 +73,10 @@ impl std::fmt::Display for RippleError {
                 write!(f, "{}", msg)
             }
             RippleError::TimeoutError => write!(f, "Timeout"),
+            RippleError::ExtnUnhealthy => write!(f, "ExtnUnhealthy"),
+            RippleError::InvalidExtnRpcRequest(contract) => {
+                write!(f, "InvalidExtnRpcRequest {}", contract)
+            }
         }
     }
 }
@@ -15,7 +15,10 @@
 // SPDX-License-Identifier: Apache-2.0
 //
 
-use ripple_sdk::log::{debug, error};
+use ripple_sdk::{
+    api::observability::recording::{RecordEntry, RecordKind},
+    log::{debug, error},
+};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::{collections::HashMap, fmt::Display};
@@ -28,6 +31,74 @@ use crate::{
 
 pub type MockData = HashMap<String, Vec<ParamResponse>>;
 
+/// Builds mock data out of the Thunder exchanges of a recording session. The first response
+/// recorded for each method and params is kept, along with the events which followed a
+/// register request.
+pub fn get_mock_data_from_recording(entries: &[RecordEntry]) -> MockData {
+    let mut mock_data = MockData::new();
+    // requests awaiting their response by id
+    let mut requests: HashMap<u64, (String, Option<Value>)> = HashMap::new();
+    // register responses by notification id, along with the time of the response
+    let mut registrations: HashMap<String, (String, usize, u64)> = HashMap::new();
+    for entry in entries {
+        let payload = &entry.payload;
+        let method = payload.get("method").and_then(|m| m.as_str());
+        let id = payload.get("id").and_then(|id| id.as_u64());
+        match (&entry.kind, method, id) {
+            (RecordKind::ThunderRequest, Some(method), Some(id)) => {
+                requests.insert(id, (method.to_owned(), payload.get("params").cloned()));
+            }
+            (RecordKind::ThunderResponse, None, Some(id)) => {
+                let Some((method, params)) = requests.remove(&id) else {
+                    continue;
+                };
+                let notification_id = params.as_ref().and_then(|p| {
+                    Some(format!(
+                        "{}.{}",
+                        p.get("id")?.as_str()?,
+                        p.get("event")?.as_str()?
+                    ))
+                });
+                // register requests are looked up by their event only
+                let params = match (method.ends_with(".register"), &params) {
+                    (true, Some(p)) => p.get("event").map(|e| json!({ "event": e })),
+                    _ => params,
+                };
+                let responses = mock_data.entry(method.clone()).or_default();
+                if responses.iter().any(|r| r.params == params) {
+                    continue;
+                }
+                if let Some(notification_id) = notification_id {
+                    registrations
+                        .insert(notification_id, (method, responses.len(), entry.elapsed_ms));
+                }
+                responses.push(ParamResponse {
+                    params,
+                    result: payload.get("result").cloned(),
+                    error: payload.get("error").cloned(),
+                    events: None,
+                });
+            }
+            (RecordKind::ThunderResponse, Some(method), None) => {
+                let Some((key, index, registered_ms)) = registrations.get(method) else {
+                    continue;
+                };
+                if let Some(response) = mock_data.get_mut(key).and_then(|r| r.get_mut(*index)) {
+                    response
+                        .events
+                        .get_or_insert_with(Vec::new)
+                        .push(EventValue {
+                            delay: Some(entry.elapsed_ms.saturating_sub(*registered_ms)),
+                            data: payload.get("params").cloned().unwrap_or_default(),
+                        });
+                }
+            }
+            _ => {}
+        }
+    }
+    mock_data
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ParamResponse {
     pub params: Option<Value>,
@@ -319,4 +390,62 @@ mod tests {
             panic!("Failure in get all with thunder register params")
         }
     }
+
+    #[test]
+    fn test_get_mock_data_from_recording() {
+        let entry = |kind, elapsed_ms, payload| {
+            let mut entry = RecordEntry::new(kind, payload);
+            entry.elapsed_ms = elapsed_ms;
+            entry
+        };
+        let entries = vec![
+            entry(
+                RecordKind::ThunderRequest,
+                0,
+                json!({"jsonrpc":"2.0","id":1,"method":"org.rdk.System.1.getDeviceInfo","params":{"params":["make"]}}),
+            ),
+            entry(
+                RecordKind::ThunderRequest,
+                1,
+                json!({"jsonrpc":"2.0","id":2,"method":"org.rdk.System.1.register","params":{"event":"onTimeZoneDSTChanged","id":"client.org.rdk.System.1.events"}}),
+            ),
+            entry(
+                RecordKind::ThunderResponse,
+                5,
+                json!({"jsonrpc":"2.0","id":1,"result":{"make":"Arris","success":true}}),
+            ),
+            entry(
+                RecordKind::ThunderResponse,
+                10,
+                json!({"jsonrpc":"2.0","id":2,"result":0}),
+            ),
+            entry(
+                RecordKind::ThunderResponse,
+                60,
+                json!({"jsonrpc":"2.0","method":"client.org.rdk.System.1.events.onTimeZoneDSTChanged","params":{"newTimeZone":"UTC"}}),
+            ),
+            // responses without a recorded request are left out
+            entry(
+                RecordKind::ThunderResponse,
+                70,
+                json!({"jsonrpc":"2.0","id":9,"result":0}),
+            ),
+        ];
+        let mock_data = get_mock_data_from_recording(&entries);
+        assert_eq!(mock_data.len(), 2);
+        let device_info = &mock_data["org.rdk.System.1.getDeviceInfo"][0];
+        assert_eq!(device_info.params, Some(json!({"params":["make"]})));
+        assert_eq!(
+            device_info.result,
+            Some(json!({"make":"Arris","success":true}))
+        );
+        let register = &mock_data["org.rdk.System.1.register"][0];
+        assert_eq!(
+            register.params,
+            Some(json!({"event":"onTimeZoneDSTChanged"}))
+        );
+        let events = register.events.as_ref().unwrap();
+        assert_eq!(events[0].delay, Some(50));
+        assert_eq!(events[0].data, json!({"newTimeZone":"UTC"}));
+    }
 }
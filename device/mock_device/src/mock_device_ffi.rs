@@ -32,7 +32,6 @@ use ripple_sdk::{
     },
     framework::ripple_contract::{ContractFulfiller, RippleContract},
     log::{debug, info},
-    semver::Version,
     tokio::{self, runtime::Runtime},
     utils::{error::RippleError, logger::init_logger},
 };
@@ -48,17 +47,15 @@ pub const EXTN_NAME: &str = "mock_device";
 fn init_library() -> CExtnMetadata {
     let _ = init_logger(EXTN_NAME.into());
     let id = ExtnId::new_channel(ExtnClassId::Device, EXTN_NAME.into());
-    let mock_device_channel = ExtnSymbolMetadata::get(
+    let mock_device_channel = ExtnSymbolMetadata::new(
         id.clone(),
         ContractFulfiller::new(vec![RippleContract::ExtnProvider(ExtnProviderAdjective {
             id,
         })]),
-        Version::new(1, 0, 0),
     );
-    let mock_device_extn = ExtnSymbolMetadata::get(
+    let mock_device_extn = ExtnSymbolMetadata::new(
         ExtnId::new_extn(ExtnClassId::Jsonrpsee, EXTN_NAME.into()),
         ContractFulfiller::new(vec![RippleContract::JsonRpsee]),
-        Version::new(1, 0, 0),
     );
 
     debug!("Returning mock_device metadata builder");
@@ -146,6 +143,7 @@ export_jsonrpc_extn_builder!(JsonRpseeExtnBuilder, init_jsonrpsee_builder);
 
 #[cfg(test)]
 mod tests {
+    use ripple_sdk::extn::ffi::ffi_library::SDK_VERSION;
     use serde_json::json;
 
     use crate::test_utils::extn_sender_web_socket_mock_server;
@@ -159,8 +157,8 @@ mod tests {
             CExtnMetadata {
                 name: "mock_device".to_owned(),
                 metadata: json!([
-                    {"fulfills": json!([json!({"extn_provider": "ripple:channel:device:mock_device"}).to_string()]).to_string(), "id": "ripple:channel:device:mock_device", "required_version": "1.0.0"},
-                    {"fulfills": json!([json!("json_rpsee").to_string()]).to_string(), "id": "ripple:extn:jsonrpsee:mock_device", "required_version": "1.0.0"}
+                    {"fulfills": json!([json!({"extn_provider": "ripple:channel:device:mock_device"}).to_string()]).to_string(), "id": "ripple:channel:device:mock_device", "required_version": SDK_VERSION, "sdk_version": SDK_VERSION},
+                    {"fulfills": json!([json!("json_rpsee").to_string()]).to_string(), "id": "ripple:extn:jsonrpsee:mock_device", "required_version": SDK_VERSION, "sdk_version": SDK_VERSION}
                     ])
                     .to_string()
             }
@@ -18,7 +18,7 @@
 use std::{fs::File, io::BufReader, path::PathBuf, sync::Arc};
 
 use ripple_sdk::{
-    api::config::Config,
+    api::{config::Config, observability::recording::read_recording},
     extn::{client::extn_client::ExtnClient, extn_client_message::ExtnResponse},
     log::{debug, error},
     tokio,
@@ -30,7 +30,7 @@ use url::{Host, Url};
 use crate::{
     errors::{BootFailedError, LoadMockDataError, MockDeviceError},
     mock_config::MockConfig,
-    mock_data::MockData,
+    mock_data::{get_mock_data_from_recording, MockData},
     mock_web_socket_server::{MockWebSocketServer, WsServerParameters},
 };
 
@@ -152,12 +152,20 @@ pub async fn load_mock_data_v2(client: ExtnClient) -> Result<MockData, MockDevic
         return Err(LoadMockDataError::PathDoesNotExist(path))?;
     }
 
+    let is_recording = path.extension().map_or(false, |e| e == "jsonl");
     let file = File::open(path.clone()).map_err(|e| {
         error!("Failed to open mock data file {e:?}");
         LoadMockDataError::FileOpenFailed(path)
     })?;
     let reader = BufReader::new(file);
 
+    // a recording session serves the Thunder responses it captured
+    if is_recording {
+        let entries =
+            read_recording(reader).map_err(|_| LoadMockDataError::MockDataNotValidJson)?;
+        return Ok(get_mock_data_from_recording(&entries));
+    }
+
     if let Ok(v) = serde_json::from_reader(reader) {
         return Ok(v);
     }
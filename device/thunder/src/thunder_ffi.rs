@@ -30,7 +30,6 @@ use thunder_ripple_sdk::ripple_sdk::{
     },
     framework::ripple_contract::{ContractFulfiller, RippleContract},
     log::{debug, info},
-    semver::Version,
     tokio,
     utils::{error::RippleError, extn_utils::ExtnUtils, logger::init_logger},
 };
@@ -39,7 +38,7 @@ use crate::bootstrap::boot_thunder_channel::boot_thunder_channel;
 
 fn init_library() -> CExtnMetadata {
     let _ = init_logger("device_channel".into());
-    let thunder_channel_meta = ExtnSymbolMetadata::get(
+    let thunder_channel_meta = ExtnSymbolMetadata::new(
         ExtnId::new_channel(ExtnClassId::Device, "thunder".into()),
         ContractFulfiller::new(vec![
             RippleContract::DeviceInfo,
@@ -54,7 +53,6 @@ fn init_library() -> CExtnMetadata {
             RippleContract::RemoteAccessory,
             RippleContract::Wifi,
         ]),
-        Version::new(1, 1, 0),
     );
 
     debug!("Returning thunder library entries");
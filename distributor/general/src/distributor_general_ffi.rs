@@ -34,7 +34,6 @@ use ripple_sdk::{
     },
     framework::ripple_contract::{ContractFulfiller, RippleContract},
     log::{debug, info},
-    semver::Version,
     tokio,
     utils::{error::RippleError, extn_utils::ExtnUtils, logger::init_logger},
 };
@@ -55,7 +54,7 @@ use crate::{
 fn init_library() -> CExtnMetadata {
     let _ = init_logger("distributor_general".into());
 
-    let dist_meta = ExtnSymbolMetadata::get(
+    let dist_meta = ExtnSymbolMetadata::new(
         ExtnId::new_channel(ExtnClassId::Distributor, "general".into()),
         ContractFulfiller::new(vec![
             RippleContract::Permissions,
@@ -70,7 +69,6 @@ fn init_library() -> CExtnMetadata {
             RippleContract::Session(SessionAdjective::Distributor),
             RippleContract::Session(SessionAdjective::Platform),
         ]),
-        Version::new(1, 1, 0),
     );
 
     debug!("Returning distributor builder");
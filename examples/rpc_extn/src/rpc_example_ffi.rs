@@ -30,7 +30,6 @@ use ripple_sdk::{
     },
     framework::ripple_contract::{ContractFulfiller, RippleContract},
     log::debug,
-    semver::Version,
     utils::logger::init_logger,
 };
 
@@ -42,10 +41,9 @@ use crate::rpc::{
 fn init_library() -> CExtnMetadata {
     let _ = init_logger("rpc_extn".into());
 
-    let json_rpsee_extn_meta = ExtnSymbolMetadata::get(
+    let json_rpsee_extn_meta = ExtnSymbolMetadata::new(
         ExtnId::new_extn(ExtnClassId::Jsonrpsee, "custom".into()),
         ContractFulfiller::new(vec![RippleContract::JsonRpsee]),
-        Version::new(1, 1, 0),
     );
 
     debug!("Returning extended custom library entries");
@@ -30,7 +30,6 @@ use ripple_sdk::{
     },
     framework::ripple_contract::{ContractFulfiller, RippleContract},
     log::{debug, error, info},
-    semver::Version,
     tokio::{self, sync::mpsc::channel},
     utils::{error::RippleError, extn_utils::ExtnUtils, logger::init_logger},
 };
@@ -42,10 +41,9 @@ use crate::telemetry_processor::TelemetryProcessor;
 fn init_library() -> CExtnMetadata {
     let _ = init_logger("tm".into());
 
-    let dist_meta = ExtnSymbolMetadata::get(
+    let dist_meta = ExtnSymbolMetadata::new(
         ExtnId::new_channel(ExtnClassId::Distributor, "tm".into()),
         ContractFulfiller::new(vec![RippleContract::OperationalMetricListener]),
-        Version::new(1, 1, 0),
     );
 
     debug!("Returning tm builder");